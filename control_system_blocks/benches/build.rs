@@ -0,0 +1,69 @@
+//! Time taken to build a large control system, failing if it exceeds a budget.
+//!
+//! Run with `cargo bench --bench build`.
+
+use std::time::{Duration, Instant};
+
+use control_system_blocks::{consumers::Print, math::Add, producers::Constant};
+use control_system_lib::{ControlSystemBuilder, ControlSystemParameters};
+
+const BLOCKS: usize = 1000;
+const RUNS: u32 = 10;
+const BUDGET: Duration = Duration::from_millis(200);
+
+/// Chain of `BLOCKS` adders, each adding one to the output of the previous one
+fn chain() -> ControlSystemBuilder {
+    let mut builder = ControlSystemBuilder::default();
+    builder
+        .add_block(
+            Constant::<f64>::new("zero", 0.0.into()),
+            &[],
+            &[("y", "zero")],
+        )
+        .unwrap();
+    builder
+        .add_block(
+            Constant::<f64>::new("one", 1.0.into()),
+            &[],
+            &[("y", "one")],
+        )
+        .unwrap();
+
+    let mut previous = "zero".to_string();
+    for i in 0..BLOCKS - 3 {
+        let output = format!("sum{i}");
+        builder
+            .add_block(
+                Add::<f64, 2>::new(&format!("add{i}"), [1.0, 1.0].into()),
+                &[("u1", &previous), ("u2", "one")],
+                &[("y", &output)],
+            )
+            .unwrap();
+        previous = output;
+    }
+
+    builder
+        .add_block(Print::<f64>::new("print"), &[("u", &previous)], &[])
+        .unwrap();
+    builder
+}
+
+fn main() {
+    let mut total = Duration::ZERO;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        let system = chain()
+            .build("chain", ControlSystemParameters::new(1.0))
+            .unwrap();
+        total += start.elapsed();
+
+        assert_eq!(system.execution_order().len(), BLOCKS);
+    }
+
+    let mean = total / RUNS;
+    println!("Adding and building {BLOCKS} blocks: {mean:?} per run");
+    assert!(
+        mean < BUDGET,
+        "building {BLOCKS} blocks took {mean:?}, over the budget of {BUDGET:?}"
+    );
+}
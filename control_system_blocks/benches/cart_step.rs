@@ -0,0 +1,133 @@
+//! Step throughput of the cascaded position and velocity loops of the cart example,
+//! without the plotters. Run it on two revisions to compare the cost of reading and
+//! writing signals.
+//!
+//! Run with `cargo bench --bench cart_step`.
+
+extern crate control_system_lib as control_system;
+
+use std::time::Instant;
+
+use control_system::{
+    io::{Input, Output},
+    numeric::ode::{ODESolver, RungeKutta4},
+    Block, BlockIO, ControlSystem, ControlSystemBuilder, ControlSystemParameters, StepInfo,
+    StepResult,
+};
+use control_system_blocks::{
+    math::Add,
+    producers::Constant,
+    siso::{Delay, PIDParams, PID},
+};
+use nalgebra::Vector2;
+
+const STEPS: usize = 1_000_000;
+
+/// Unit mass pushed by a force
+#[derive(BlockIO)]
+struct Cart {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u_force: Input<f64>,
+
+    #[blockio(output)]
+    y_pos: Output<f64>,
+    #[blockio(output)]
+    y_vel: Output<f64>,
+
+    state: Vector2<f64>,
+}
+
+impl Block for Cart {
+    fn step(&mut self, k: StepInfo) -> control_system::Result<StepResult> {
+        let acc = self.u_force.get();
+        let odefun = |_, x: Vector2<f64>| Vector2::new(x[1], acc);
+        self.state = RungeKutta4::solve(odefun, k.t, k.dt, self.state);
+
+        self.y_pos.set(self.state[0]);
+        self.y_vel.set(self.state[1]);
+
+        Ok(StepResult::Continue)
+    }
+}
+
+fn cart_system() -> ControlSystem {
+    let mut builder = ControlSystemBuilder::default();
+    builder
+        .add_block(
+            Cart {
+                name: "cart".to_string(),
+                u_force: Input::default(),
+                y_pos: Output::default(),
+                y_vel: Output::default(),
+                state: Vector2::zeros(),
+            },
+            &[("u_force", "/force")],
+            &[("y_pos", "/cart/pos"), ("y_vel", "/cart/vel")],
+        )
+        .unwrap();
+
+    // Inner velocity loop and outer position loop
+    for (name, measurement, reference, output, kp) in [
+        ("vel", "/cart/vel", "/ref/vel", "/force", 4.0),
+        ("pos", "/cart/pos", "/ref/pos", "/ref/vel", 1.0),
+    ] {
+        let delayed = format!("{measurement}_delayed");
+        let error = format!("/err/{name}");
+        builder
+            .add_block(
+                Delay::new(&format!("{name}_delay"), [0.0].into()),
+                &[("u", measurement)],
+                &[("y", &delayed)],
+            )
+            .unwrap();
+        builder
+            .add_block(
+                Add::<f64, 2>::new(&format!("{name}_err"), [1.0, -1.0].into()),
+                &[("u1", reference), ("u2", &delayed)],
+                &[("y", &error)],
+            )
+            .unwrap();
+        let params = PIDParams {
+            kp,
+            ..Default::default()
+        };
+        builder
+            .add_block(
+                PID::new(&format!("pid_{name}"), params).unwrap(),
+                &[("u", &error)],
+                &[("y", output)],
+            )
+            .unwrap();
+    }
+
+    builder
+        .add_block(
+            Constant::new("pos_ref", 15.0.into()),
+            &[],
+            &[("y", "/ref/pos")],
+        )
+        .unwrap();
+
+    builder
+        .build("cart", ControlSystemParameters::new(0.01))
+        .unwrap()
+}
+
+fn main() {
+    let mut system = cart_system();
+
+    let start = Instant::now();
+    for _ in 0..STEPS {
+        system.step().unwrap();
+    }
+    let elapsed = start.elapsed();
+
+    println!(
+        "{STEPS} steps of the cart system in {elapsed:?}: {:.0} steps/s, {:?} per step",
+        STEPS as f64 / elapsed.as_secs_f64(),
+        elapsed / STEPS as u32
+    );
+}
@@ -1,6 +1,6 @@
 use anyhow::Result;
 use control_system_blocks::{consumers::Print, math::Add, producers::Constant, siso::Delay};
-use control_system_lib::{ControlSystemBuilder, ControlSystemParameters, StepResult};
+use control_system_lib::{ControlSystemBuilder, ControlSystemParameters};
 
 fn main() -> Result<()> {
     let add = Add::<i32, 2>::new("add", [1, 1].into());
@@ -18,15 +18,10 @@ fn main() -> Result<()> {
     builder.add_block(add, &[("u1", "one"), ("u2", "feedback")], &[("y", "sum")])?;
     builder.add_block(print, &[("u", "sum")], &[])?;
 
-    let mut controlsystem = builder.build(
-        "adder",
-        ControlSystemParameters {
-            dt: 1.0,
-            max_iter: 10,
-        },
-    )?;
+    let mut controlsystem =
+        builder.build("adder", ControlSystemParameters::new(1.0).with_max_iter(10))?;
 
-    while controlsystem.step()? != StepResult::Stop {}
+    while !controlsystem.step()?.is_stop() {}
 
     Ok(())
 }
@@ -0,0 +1,19 @@
+use std::path::Path;
+
+use anyhow::Result;
+use control_system_blocks::registry::builtin_registry;
+use control_system_lib::{ControlSystemBuilder, ControlSystemParameters};
+
+/// Same system as the `add` example, described in `adder.toml`
+fn main() -> Result<()> {
+    let config = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/adder.toml");
+
+    let builder = ControlSystemBuilder::from_config(&config, &builtin_registry())?;
+
+    let mut controlsystem =
+        builder.build("adder", ControlSystemParameters::new(1.0).with_max_iter(10))?;
+
+    while !controlsystem.step()?.is_stop() {}
+
+    Ok(())
+}
@@ -0,0 +1,416 @@
+use std::{cell::Cell, f64::consts::PI, rc::Rc};
+
+use control_system::{
+    io::Input, Block, BlockIO, ControlSystemBuilder, ControlSystemError, ControlSystemParameters,
+    Result, StepInfo, StepResult,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::producers::{SineWave, WaveParams};
+
+const SINE_BLOCK: &str = "frequency_response_sine";
+const PROBE_BLOCK: &str = "frequency_response_probe";
+
+/// Settings of [`frequency_response`]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FrequencyResponseParams {
+    /// Step of the simulation
+    pub dt: f64,
+    /// Amplitude of the injected sinusoids
+    pub amplitude: f64,
+    /// Largest change of the relative gain and of the phase (in radians) between two
+    /// consecutive periods for the response to be considered steady
+    pub tolerance: f64,
+    /// Periods after which the measurement at a frequency is abandoned
+    pub max_periods: usize,
+}
+
+impl FrequencyResponseParams {
+    pub fn new(dt: f64) -> Self {
+        FrequencyResponseParams {
+            dt,
+            amplitude: 1.0,
+            tolerance: 1e-4,
+            max_periods: 1000,
+        }
+    }
+}
+
+/// Steady-state response of a system to sinusoidal inputs
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FrequencyResponse {
+    /// Frequencies in Hz
+    pub frequencies: Vec<f64>,
+    /// Ratio between the amplitudes of the output and of the input
+    pub magnitude: Vec<f64>,
+    /// Phase of the output relative to the input, in radians
+    pub phase: Vec<f64>,
+}
+
+impl FrequencyResponse {
+    pub fn magnitude_db(&self) -> Vec<f64> {
+        self.magnitude.iter().map(|m| 20.0 * m.log10()).collect()
+    }
+}
+
+/// Measures the frequency response from signal `input` to signal `output` of the
+/// system in `builder`.
+///
+/// A [`SineWave`] producing `input` is added to the system, so `input` must not have
+/// a producer. For each frequency the system is reset and run until the gain and phase
+/// of `output`, computed by correlation over a period, are steady.
+///
+/// Frequencies are rounded to a whole number of steps per period, so they should be
+/// well below the Nyquist frequency `1 / (2 dt)`. Fails if a frequency is not positive
+/// or has a period shorter than 4 steps.
+pub fn frequency_response(
+    mut builder: ControlSystemBuilder,
+    input: &str,
+    output: &str,
+    frequencies: &[f64],
+    params: &FrequencyResponseParams,
+) -> Result<FrequencyResponse> {
+    let wave = |frequency| WaveParams {
+        amplitude: params.amplitude,
+        frequency,
+        phase: 0.0,
+        offset: 0.0,
+    };
+
+    // Steps in a period of each frequency
+    let periods = frequencies
+        .iter()
+        .map(|&frequency| {
+            let steps = (1.0 / (frequency * params.dt)).round();
+            if frequency.is_finite() && frequency > 0.0 && steps >= 4.0 {
+                Ok(steps as usize)
+            } else {
+                Err(ControlSystemError::Other(
+                    format!(
+                        "Frequency {} Hz must be positive and have a period of at least 4 steps of {} s",
+                        frequency, params.dt
+                    )
+                    .into(),
+                ))
+            }
+        })
+        .collect::<Result<Vec<usize>>>()?;
+
+    let probe = Probe::new(PROBE_BLOCK);
+    let sample = probe.sample.clone();
+
+    builder.add_block(SineWave::new(SINE_BLOCK, wave(1.0)), &[], &[("y", input)])?;
+    builder.add_block(probe, &[("u", output)], &[])?;
+    let mut system = builder.build(
+        "frequency_response",
+        ControlSystemParameters::new(params.dt),
+    )?;
+
+    let mut response = FrequencyResponse::default();
+    for steps in periods {
+        // Actual frequency of the periods the correlation is computed on
+        let frequency = 1.0 / (steps as f64 * params.dt);
+
+        system.reset();
+        system.set_block_params(SINE_BLOCK, serde_json::to_value(wave(frequency)).unwrap())?;
+
+        // The response is steady when gain and phase stop changing between periods.
+        // Unlike with a SettlingDetector, their final values are not known beforehand,
+        // and the phase must be compared modulo 2 pi.
+        let mut last: Option<(f64, f64)> = None;
+        let mut steady = None;
+        for _ in 0..params.max_periods {
+            let (mut i, mut q) = (0.0, 0.0);
+            for _ in 0..steps {
+                if system.step()?.is_stop() {
+                    return Err(ControlSystemError::Other(
+                        format!("System stopped while measuring the response at {frequency} Hz")
+                            .into(),
+                    ));
+                }
+
+                let (t, y) = sample.get();
+                let angle = 2.0 * PI * frequency * t;
+                i += y * angle.sin();
+                q += y * angle.cos();
+            }
+
+            let gain = 2.0 * i.hypot(q) / (steps as f64 * params.amplitude);
+            let phase = q.atan2(i);
+
+            if let Some((last_gain, last_phase)) = last {
+                let phase_change = (phase - last_phase + PI).rem_euclid(2.0 * PI) - PI;
+                if (gain - last_gain).abs() <= params.tolerance * gain
+                    && phase_change.abs() <= params.tolerance
+                {
+                    steady = Some((gain, phase));
+                    break;
+                }
+            }
+            last = Some((gain, phase));
+        }
+
+        let (gain, phase) = steady.ok_or_else(|| {
+            ControlSystemError::Other(
+                format!(
+                    "Response at {frequency} Hz did not settle in {} periods",
+                    params.max_periods
+                )
+                .into(),
+            )
+        })?;
+
+        response.frequencies.push(frequency);
+        response.magnitude.push(gain);
+        response.phase.push(phase);
+    }
+
+    Ok(response)
+}
+
+/// Band around the target, relative to the amplitude of the step, the response must
+/// remain in to be considered settled
+const SETTLING_BAND: f64 = 0.02;
+
+/// Characteristics of the response of a system to a step, see [`step_response_metrics`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StepResponseMetrics {
+    /// Time taken to go from 10% to 90% of the step. None if the response never
+    /// reaches 90% of the step.
+    pub rise_time: Option<f64>,
+    /// Largest excursion beyond the target, as a percentage of the step. Zero if the
+    /// response never crosses the target.
+    pub overshoot: f64,
+    /// Time after which the response remains within 2% of the step from the target.
+    /// None if it is still outside the band at the last sample.
+    pub settling_time: Option<f64>,
+}
+
+/// Computes the metrics of a step response sampled at `times`, going from the first
+/// sample towards `target`. Times are measured from the first sample, which is taken
+/// as the instant the step was applied.
+///
+/// Fails if there are no samples, or not as many times as values.
+pub fn step_response_metrics(
+    times: &[f64],
+    values: &[f64],
+    target: f64,
+) -> Result<StepResponseMetrics> {
+    if times.len() != values.len() {
+        return Err(ControlSystemError::Other(
+            format!(
+                "Step response has {} times but {} values",
+                times.len(),
+                values.len()
+            )
+            .into(),
+        ));
+    }
+    if values.is_empty() {
+        return Err(ControlSystemError::Other(
+            "Step response must have at least one sample".into(),
+        ));
+    }
+
+    let t0 = times[0];
+    let initial = values[0];
+    let step = target - initial;
+
+    if step == 0.0 {
+        return Ok(StepResponseMetrics {
+            rise_time: Some(0.0),
+            overshoot: 0.0,
+            settling_time: Some(0.0),
+        });
+    }
+
+    // Progress of the response towards the target: 0 at the start, 1 at the target
+    let progress: Vec<f64> = values.iter().map(|v| (v - initial) / step).collect();
+
+    let rise_time = match (
+        crossing_time(times, &progress, 0.1),
+        crossing_time(times, &progress, 0.9),
+    ) {
+        (Some(t10), Some(t90)) => Some(t90 - t10),
+        _ => None,
+    };
+
+    let peak = progress.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let overshoot = (peak - 1.0).max(0.0) * 100.0;
+
+    let settling_time = match progress
+        .iter()
+        .rposition(|p| (p - 1.0).abs() > SETTLING_BAND)
+    {
+        None => Some(0.0),
+        Some(last) if last + 1 == times.len() => None,
+        Some(last) => Some(times[last + 1] - t0),
+    };
+
+    Ok(StepResponseMetrics {
+        rise_time,
+        overshoot,
+        settling_time,
+    })
+}
+
+/// First time `progress` reaches `level`, interpolated between the samples
+fn crossing_time(times: &[f64], progress: &[f64], level: f64) -> Option<f64> {
+    if progress[0] >= level {
+        return Some(times[0]);
+    }
+
+    let i = progress.iter().position(|&p| p >= level)?;
+    let fraction = (level - progress[i - 1]) / (progress[i] - progress[i - 1]);
+    Some(times[i - 1] + fraction * (times[i] - times[i - 1]))
+}
+
+/// Exposes the last value of its input, with the time it was read at
+#[derive(BlockIO)]
+struct Probe {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u: Input<f64>,
+
+    sample: Rc<Cell<(f64, f64)>>,
+}
+
+impl Probe {
+    fn new(name: &str) -> Self {
+        Probe {
+            name: name.to_string(),
+            u: Input::default(),
+            sample: Rc::new(Cell::new((0.0, 0.0))),
+        }
+    }
+}
+
+impl Block for Probe {
+    fn step(&mut self, k: StepInfo) -> Result<StepResult> {
+        self.sample.set((k.t, self.u.get()));
+        Ok(StepResult::Continue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::siso::LowPass;
+
+    fn low_pass(cutoff_hz: f64) -> ControlSystemBuilder {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(
+                LowPass::<f64>::new("low_pass", cutoff_hz.into()).unwrap(),
+                &[("u", "u")],
+                &[("y", "y")],
+            )
+            .unwrap();
+        builder
+    }
+
+    #[test]
+    fn low_pass_is_3_db_down_at_cutoff() {
+        let response = frequency_response(
+            low_pass(1.0),
+            "u",
+            "y",
+            &[0.01, 1.0, 10.0],
+            &FrequencyResponseParams::new(1e-3),
+        )
+        .unwrap();
+
+        let db = response.magnitude_db();
+        assert!(db[0].abs() < 0.01, "{:?}", db);
+        assert!((db[1] + 3.01).abs() < 0.05, "{:?}", db);
+        assert!((db[2] + 20.0).abs() < 0.5, "{:?}", db);
+
+        assert!(
+            (response.phase[1] + PI / 4.0).abs() < 0.02,
+            "{:?}",
+            response.phase
+        );
+    }
+
+    #[test]
+    fn frequency_too_high_for_dt() {
+        for frequency in [300.0, 0.0, -1.0] {
+            assert!(frequency_response(
+                low_pass(1.0),
+                "u",
+                "y",
+                &[frequency],
+                &FrequencyResponseParams::new(1e-3),
+            )
+            .is_err());
+        }
+    }
+
+    /// Samples of `response` every millisecond for `duration` seconds
+    fn sample(duration: f64, response: impl Fn(f64) -> f64) -> (Vec<f64>, Vec<f64>) {
+        let times: Vec<f64> = (0..=(duration * 1000.0) as usize)
+            .map(|i| i as f64 * 1e-3)
+            .collect();
+        let values = times.iter().map(|&t| response(t)).collect();
+        (times, values)
+    }
+
+    #[test]
+    fn underdamped_step_response() {
+        // Second order system with unit natural frequency and damping ratio 0.5
+        let zeta: f64 = 0.5;
+        let wd = (1.0 - zeta * zeta).sqrt();
+        let (times, values) = sample(30.0, |t| {
+            1.0 - (-zeta * t).exp() * ((wd * t).cos() + zeta / wd * (wd * t).sin())
+        });
+
+        let metrics = step_response_metrics(&times, &values, 1.0).unwrap();
+
+        let overshoot = 100.0 * (-PI * zeta / wd).exp();
+        assert!(
+            (metrics.overshoot - overshoot).abs() < 0.01,
+            "{:?}",
+            metrics
+        );
+        assert!(
+            (metrics.rise_time.unwrap() - 1.64).abs() < 0.01,
+            "{:?}",
+            metrics
+        );
+        assert!(
+            (metrics.settling_time.unwrap() - 8.08).abs() < 0.05,
+            "{:?}",
+            metrics
+        );
+    }
+
+    #[test]
+    fn overdamped_step_response() {
+        // First order lag with a time constant of 2 s, from 1 to 3
+        let tau = 2.0;
+        let (times, values) = sample(20.0, |t| 1.0 + 2.0 * (1.0 - (-t / tau).exp()));
+
+        let metrics = step_response_metrics(&times, &values, 3.0).unwrap();
+
+        assert_eq!(metrics.overshoot, 0.0);
+        assert!((metrics.rise_time.unwrap() - tau * 9f64.ln()).abs() < 1e-3);
+        assert!((metrics.settling_time.unwrap() - tau * 50f64.ln()).abs() < 2e-3);
+    }
+
+    #[test]
+    fn unfinished_step_response() {
+        let (times, values) = sample(1.0, |t| t / 2.0);
+
+        let metrics = step_response_metrics(&times, &values, 1.0).unwrap();
+        assert_eq!(metrics.rise_time, None);
+        assert_eq!(metrics.settling_time, None);
+    }
+
+    #[test]
+    fn invalid_step_response() {
+        assert!(step_response_metrics(&[], &[], 1.0).is_err());
+        assert!(step_response_metrics(&[0.0, 1.0], &[0.0], 1.0).is_err());
+    }
+}
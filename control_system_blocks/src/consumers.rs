@@ -1,4 +1,10 @@
-use control_system::{io::Input, Block, BlockIO, Result, StepInfo, StepResult};
+use control_system::{
+    downcast_state, invalid_params, io::Input, Block, BlockIO, BlockState, ParameterStore, Result,
+    StepInfo, StepResult,
+};
+use num::Float;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::any::Any;
 
 #[derive(BlockIO)]
 pub struct Print<T> {
@@ -11,7 +17,7 @@ pub struct Print<T> {
 
 impl<T> Print<T>
 where
-    T: Default + 'static,
+    T: 'static,
 {
     pub fn new(name: &str) -> Self {
         Print {
@@ -31,8 +37,177 @@ where
             k.t,
             self.name,
             self.u.signal_name(),
-            self.u.get()
+            self.u.try_get()?
         );
         Ok(StepResult::Continue)
     }
 }
+
+#[derive(Serialize, Deserialize)]
+pub struct SettlingParams<T> {
+    pub target: T,
+    /// Maximum distance from the target for the signal to be considered settled
+    pub tolerance: T,
+    /// Number of consecutive steps the signal must stay within the tolerance
+    pub hold_steps: usize,
+}
+
+/// Stops the simulation once the input has settled around a target value
+#[derive(BlockIO)]
+pub struct SettlingDetector<T> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u: Input<T>,
+
+    params: SettlingParams<T>,
+    settled_steps: usize,
+}
+
+impl<T> SettlingDetector<T>
+where
+    T: 'static,
+{
+    /// Fails if `hold_steps` is 0
+    pub fn new(name: &str, params: SettlingParams<T>) -> Result<Self> {
+        if params.hold_steps < 1 {
+            return Err(invalid_params(
+                name,
+                "SettlingDetector must hold for at least 1 step",
+            ));
+        }
+
+        Ok(SettlingDetector {
+            name: name.to_string(),
+            u: Input::default(),
+            params,
+            settled_steps: 0,
+        })
+    }
+}
+
+impl<T> SettlingDetector<T>
+where
+    T: Serialize + DeserializeOwned + 'static,
+{
+    pub fn from_store(
+        name: &str,
+        store: &mut ParameterStore,
+        default_params: SettlingParams<T>,
+    ) -> Result<Self> {
+        let params = store.get_block_params(name, default_params)?;
+
+        Self::new(name, params)
+    }
+}
+
+impl<T> Block for SettlingDetector<T>
+where
+    T: Float + 'static,
+{
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        if (self.u.get() - self.params.target).abs() <= self.params.tolerance {
+            self.settled_steps += 1;
+        } else {
+            self.settled_steps = 0;
+        }
+
+        if self.settled_steps >= self.params.hold_steps {
+            Ok(StepResult::Stop)
+        } else {
+            Ok(StepResult::Continue)
+        }
+    }
+
+    fn save_state(&self) -> Option<BlockState> {
+        Some(Box::new(self.settled_steps))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) -> Result<()> {
+        self.settled_steps = *downcast_state::<usize>(&self.name, state)?;
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.settled_steps = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        producers::Constant,
+        siso::LowPass,
+        test_utils::{params, Probe},
+    };
+    use control_system::{ControlSystemBuilder, ControlSystemError};
+
+    #[test]
+    fn settling_detector_stops_first_order_system() {
+        let dt = 0.01;
+        let hold_steps = 10;
+
+        // Time constant of 1 s
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Constant::new("step", 1.0.into()), &[], &[("y", "u")])
+            .unwrap();
+        builder
+            .add_block(
+                LowPass::<f64>::new("plant", (0.5 / std::f64::consts::PI).into()).unwrap(),
+                &[("u", "u")],
+                &[("y", "y")],
+            )
+            .unwrap();
+        let settling = SettlingParams {
+            target: 1.0,
+            tolerance: 0.02,
+            hold_steps,
+        };
+        builder
+            .add_block(
+                SettlingDetector::new("settling", settling).unwrap(),
+                &[("u", "y")],
+                &[],
+            )
+            .unwrap();
+        let (probe, values) = Probe::<f64>::new("probe");
+        builder.add_block(probe, &[("u", "y")], &[]).unwrap();
+
+        let mut system = builder.build("test", params(dt)).unwrap();
+        for _ in 0..1000 {
+            if system.step().unwrap().is_stop() {
+                break;
+            }
+        }
+        let y = values.take();
+
+        // 2% settling time of a first order system
+        let settling_time = -(0.02f64.ln());
+        let stop_time = y.len() as f64 * dt;
+        assert!(
+            (stop_time - settling_time - hold_steps as f64 * dt).abs() < 0.05,
+            "{stop_time}"
+        );
+        assert!((y[y.len() - 1] - 1.0).abs() <= 0.02);
+    }
+
+    fn settling(hold_steps: usize) -> SettlingParams<f64> {
+        SettlingParams {
+            target: 1.0,
+            tolerance: 0.1,
+            hold_steps,
+        }
+    }
+
+    #[test]
+    fn settling_detector_rejects_zero_hold_steps() {
+        assert!(SettlingDetector::new("settling", settling(1)).is_ok());
+        assert!(matches!(
+            SettlingDetector::new("settling", settling(0)),
+            Err(ControlSystemError::InvalidBlockParams { .. })
+        ));
+    }
+}
@@ -1,6 +1,13 @@
-pub mod siso;
-pub mod producers;
 pub mod consumers;
+pub mod logic;
 pub mod math;
+pub mod producers;
+pub mod siso;
+pub mod statespace;
+
+#[cfg(test)]
+mod test_utils;
 
 extern crate control_system_lib as control_system;
+pub mod analysis;
+pub mod registry;
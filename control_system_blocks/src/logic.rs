@@ -0,0 +1,296 @@
+use std::ops::Sub;
+
+use arrayinit::arr;
+use control_system::{
+    io::{Input, Output},
+    Block, BlockIO, ParameterStore, ParameterStoreError, Result, StepInfo, StepResult,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+#[derive(BlockIO)]
+pub struct And<const N: usize> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input_arr)]
+    u: [Input<bool>; N],
+
+    #[blockio(output)]
+    y: Output<bool>,
+}
+
+impl<const N: usize> And<N> {
+    pub fn new(name: &str) -> Self {
+        And {
+            name: name.to_string(),
+            u: arr![|_| Input::default()],
+            y: Output::default(),
+        }
+    }
+}
+
+impl<const N: usize> Block for And<N> {
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        self.y.set(self.u.iter().all(|u| u.get()));
+
+        Ok(StepResult::Continue)
+    }
+}
+
+#[derive(BlockIO)]
+pub struct Or<const N: usize> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input_arr)]
+    u: [Input<bool>; N],
+
+    #[blockio(output)]
+    y: Output<bool>,
+}
+
+impl<const N: usize> Or<N> {
+    pub fn new(name: &str) -> Self {
+        Or {
+            name: name.to_string(),
+            u: arr![|_| Input::default()],
+            y: Output::default(),
+        }
+    }
+}
+
+impl<const N: usize> Block for Or<N> {
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        self.y.set(self.u.iter().any(|u| u.get()));
+
+        Ok(StepResult::Continue)
+    }
+}
+
+#[derive(BlockIO)]
+pub struct Not {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u: Input<bool>,
+
+    #[blockio(output)]
+    y: Output<bool>,
+}
+
+impl Not {
+    pub fn new(name: &str) -> Self {
+        Not {
+            name: name.to_string(),
+            u: Input::default(),
+            y: Output::default(),
+        }
+    }
+}
+
+impl Block for Not {
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        self.y.set(!self.u.get());
+
+        Ok(StepResult::Continue)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompareOp {
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Eq,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ComparatorParams<T> {
+    pub op: CompareOp,
+
+    /// Maximum difference for the inputs to be considered equal by `CompareOp::Eq`
+    pub tolerance: Option<T>,
+}
+
+impl<T> From<CompareOp> for ComparatorParams<T> {
+    fn from(value: CompareOp) -> Self {
+        ComparatorParams {
+            op: value,
+            tolerance: None,
+        }
+    }
+}
+
+/// Outputs `u1 <op> u2`
+#[derive(BlockIO)]
+pub struct Compare<T> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u1: Input<T>,
+
+    #[blockio(input)]
+    u2: Input<T>,
+
+    #[blockio(output)]
+    y: Output<bool>,
+
+    params: ComparatorParams<T>,
+}
+
+impl<T> Compare<T> {
+    pub fn new(name: &str, params: ComparatorParams<T>) -> Self {
+        Compare {
+            name: name.to_string(),
+            u1: Input::default(),
+            u2: Input::default(),
+            y: Output::default(),
+            params,
+        }
+    }
+}
+
+impl<T> Compare<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    pub fn from_store(
+        name: &str,
+        store: &mut ParameterStore,
+        default_params: ComparatorParams<T>,
+    ) -> Result<Self, ParameterStoreError> {
+        let params = store.get_block_params(name, default_params)?;
+
+        Ok(Self::new(name, params))
+    }
+}
+
+impl<T> Block for Compare<T>
+where
+    T: PartialOrd + Sub<Output = T> + Clone + 'static,
+{
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        let u1 = self.u1.get();
+        let u2 = self.u2.get();
+
+        let y = match self.params.op {
+            CompareOp::Gt => u1 > u2,
+            CompareOp::Ge => u1 >= u2,
+            CompareOp::Lt => u1 < u2,
+            CompareOp::Le => u1 <= u2,
+            CompareOp::Eq => match &self.params.tolerance {
+                Some(tolerance) => {
+                    let diff = if u1 > u2 { u1 - u2 } else { u2 - u1 };
+                    diff <= *tolerance
+                }
+                None => u1 == u2,
+            },
+        };
+        self.y.set(y);
+
+        Ok(StepResult::Continue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        producers::Constant,
+        test_utils::{params, Probe},
+    };
+    use control_system::ControlSystemBuilder;
+
+    /// Output of `block` after one step, with each input port connected to a signal
+    /// holding the given value
+    fn output<T: Clone + 'static, B: Block + 'static>(block: B, inputs: &[(&str, T)]) -> bool {
+        let mut builder = ControlSystemBuilder::default();
+        for (port, value) in inputs {
+            builder
+                .add_block(
+                    Constant::new(port, value.clone().into()),
+                    &[],
+                    &[("y", port)],
+                )
+                .unwrap();
+        }
+
+        let connections: Vec<(&str, &str)> =
+            inputs.iter().map(|(port, _)| (*port, *port)).collect();
+        builder
+            .add_block(block, &connections, &[("y", "y")])
+            .unwrap();
+
+        let (probe, values) = Probe::new("probe");
+        builder.add_block(probe, &[("u", "y")], &[]).unwrap();
+
+        builder.build("test", params(1.0)).unwrap().step().unwrap();
+        values.take()[0]
+    }
+
+    const BOOLS: [bool; 2] = [false, true];
+
+    #[test]
+    fn and_truth_table() {
+        for a in BOOLS {
+            for b in BOOLS {
+                let y = output(And::<2>::new("and"), &[("u1", a), ("u2", b)]);
+                assert_eq!(y, a && b, "{a} and {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn or_truth_table() {
+        for a in BOOLS {
+            for b in BOOLS {
+                let y = output(Or::<2>::new("or"), &[("u1", a), ("u2", b)]);
+                assert_eq!(y, a || b, "{a} or {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn not_truth_table() {
+        for a in BOOLS {
+            assert_eq!(output(Not::new("not"), &[("u", a)]), !a);
+        }
+    }
+
+    fn compare(params: ComparatorParams<f64>, u1: f64, u2: f64) -> bool {
+        output(Compare::new("compare", params), &[("u1", u1), ("u2", u2)])
+    }
+
+    #[test]
+    fn comparison_operators() {
+        let cases = [
+            (CompareOp::Gt, [false, false, true]),
+            (CompareOp::Ge, [false, true, true]),
+            (CompareOp::Lt, [true, false, false]),
+            (CompareOp::Le, [true, true, false]),
+            (CompareOp::Eq, [false, true, false]),
+        ];
+
+        for (op, expected) in cases {
+            let y = [1.0, 2.0, 3.0].map(|u1| compare(op.into(), u1, 2.0));
+            assert_eq!(y, expected, "{op:?}");
+        }
+    }
+
+    #[test]
+    fn equality_with_tolerance() {
+        assert!(!compare(CompareOp::Eq.into(), 0.1 + 0.2, 0.3));
+
+        let params = |tolerance| ComparatorParams {
+            op: CompareOp::Eq,
+            tolerance: Some(tolerance),
+        };
+        assert!(compare(params(1e-9), 0.1 + 0.2, 0.3));
+        assert!(compare(params(1e-9), 0.3, 0.1 + 0.2));
+        assert!(!compare(params(1e-9), 0.3, 0.31));
+        assert!(compare(params(0.1), 0.3, 0.35));
+    }
+}
@@ -1,14 +1,22 @@
 use arrayinit::arr;
 use control_system::{
+    downcast_state,
     io::{Input, Output},
-    Block, BlockIO, ParameterStore, Result, StepInfo, StepResult,
+    parse_params, Block, BlockIO, BlockState, ParameterStore, ParameterStoreError, Result,
+    StepInfo, StepResult,
 };
-use num::Num;
+use nalgebra::{SMatrix, SVector};
+use num::{Float, FromPrimitive, Num, Signed};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    any::Any,
+    f64::consts::{PI, TAU},
+};
 
 #[derive(Serialize, Deserialize)]
 pub struct AddParams<T> {
-    gains: Vec<T>,
+    pub(crate) gains: Vec<T>,
 }
 
 impl<T> From<Vec<T>> for AddParams<T> {
@@ -44,7 +52,6 @@ pub struct Add<T, const N: usize> {
 
 impl<T, const N: usize> Add<T, N>
 where
-    T: Default,
     Output<T>: Default,
 {
     pub fn new(name: &str, params: AddParams<T>) -> Self {
@@ -61,7 +68,7 @@ where
 
 impl<T, const N: usize> Add<T, N>
 where
-    T: Default + Serialize + DeserializeOwned,
+    T: Serialize + DeserializeOwned,
     Output<T>: Default,
 {
     pub fn from_store(
@@ -95,3 +102,1141 @@ where
         Ok(StepResult::Continue)
     }
 }
+
+/// Outputs the smallest of its inputs `u1` to `uN`
+#[derive(BlockIO)]
+pub struct Min<T, const N: usize> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input_arr)]
+    u: [Input<T>; N],
+
+    #[blockio(output)]
+    y: Output<T>,
+}
+
+impl<T, const N: usize> Min<T, N>
+where
+    Output<T>: Default,
+{
+    pub fn new(name: &str) -> Self {
+        assert!(N >= 1, "Min must have at least one input");
+
+        Min {
+            name: name.to_string(),
+            u: arr![|_| Input::<T>::default()],
+            y: Output::<T>::default(),
+        }
+    }
+}
+
+impl<T, const N: usize> Block for Min<T, N>
+where
+    T: PartialOrd + Clone + 'static,
+{
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        self.y.set(select(&self.u, |v, best| v < best));
+        Ok(StepResult::Continue)
+    }
+}
+
+/// Outputs the largest of its inputs `u1` to `uN`
+#[derive(BlockIO)]
+pub struct Max<T, const N: usize> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input_arr)]
+    u: [Input<T>; N],
+
+    #[blockio(output)]
+    y: Output<T>,
+}
+
+impl<T, const N: usize> Max<T, N>
+where
+    Output<T>: Default,
+{
+    pub fn new(name: &str) -> Self {
+        assert!(N >= 1, "Max must have at least one input");
+
+        Max {
+            name: name.to_string(),
+            u: arr![|_| Input::<T>::default()],
+            y: Output::<T>::default(),
+        }
+    }
+}
+
+impl<T, const N: usize> Block for Max<T, N>
+where
+    T: PartialOrd + Clone + 'static,
+{
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        self.y.set(select(&self.u, |v, best| v > best));
+        Ok(StepResult::Continue)
+    }
+}
+
+/// Value of the first input for which `better` is true against all the previous ones
+fn select<T: Clone + 'static>(inputs: &[Input<T>], better: fn(&T, &T) -> bool) -> T {
+    inputs
+        .iter()
+        .map(Input::get)
+        .reduce(|best, v| if better(&v, &best) { v } else { best })
+        .unwrap()
+}
+
+/// Applies a closure to the input on every step. The closure also receives the
+/// [`StepInfo`], so that the transform can depend on time.
+#[derive(BlockIO)]
+pub struct Map<T, U, F> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u: Input<T>,
+
+    #[blockio(output)]
+    y: Output<U>,
+
+    map: F,
+}
+
+impl<T, U, F> Map<T, U, F>
+where
+    Output<U>: Default,
+{
+    pub fn new(name: &str, map: F) -> Self {
+        Map {
+            name: name.to_string(),
+            u: Input::default(),
+            y: Output::default(),
+            map,
+        }
+    }
+}
+
+impl<T, U, F> Block for Map<T, U, F>
+where
+    T: Clone + 'static,
+    U: 'static,
+    F: Fn(T, StepInfo) -> U,
+{
+    fn step(&mut self, k: StepInfo) -> Result<StepResult> {
+        self.y.set((self.map)(self.u.get(), k));
+        Ok(StepResult::Continue)
+    }
+}
+
+/// Extracts a single field from a struct-valued input, so that blocks
+/// interested in one component of a bus signal can be connected to it.
+///
+/// ```ignore
+/// SelectField::new("velocity", |s: &CartState| s.vel)
+/// ```
+#[derive(BlockIO)]
+pub struct SelectField<T, U, F> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u: Input<T>,
+
+    #[blockio(output)]
+    y: Output<U>,
+
+    accessor: F,
+}
+
+impl<T, U, F> SelectField<T, U, F>
+where
+    Output<U>: Default,
+{
+    pub fn new(name: &str, accessor: F) -> Self {
+        SelectField {
+            name: name.to_string(),
+            u: Input::default(),
+            y: Output::default(),
+            accessor,
+        }
+    }
+}
+
+impl<T, U, F> Block for SelectField<T, U, F>
+where
+    T: Clone + 'static,
+    U: 'static,
+    F: Fn(&T) -> U,
+{
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        self.y.set((self.accessor)(&self.u.get()));
+        Ok(StepResult::Continue)
+    }
+}
+
+/// Outputs the absolute value of the input
+#[derive(BlockIO)]
+pub struct Abs<T> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u: Input<T>,
+
+    #[blockio(output)]
+    y: Output<T>,
+}
+
+impl<T> Abs<T>
+where
+    Output<T>: Default,
+{
+    pub fn new(name: &str) -> Self {
+        Abs {
+            name: name.to_string(),
+            u: Input::default(),
+            y: Output::default(),
+        }
+    }
+}
+
+impl<T> Block for Abs<T>
+where
+    T: Signed + Clone + 'static,
+{
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        self.y.set(self.u.get().abs());
+        Ok(StepResult::Continue)
+    }
+}
+
+/// Outputs -1 for negative inputs, 1 for positive inputs and 0 for zero, including
+/// negative zero
+#[derive(BlockIO)]
+pub struct Sign<T> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u: Input<T>,
+
+    #[blockio(output)]
+    y: Output<T>,
+}
+
+impl<T> Sign<T>
+where
+    Output<T>: Default,
+{
+    pub fn new(name: &str) -> Self {
+        Sign {
+            name: name.to_string(),
+            u: Input::default(),
+            y: Output::default(),
+        }
+    }
+}
+
+impl<T> Block for Sign<T>
+where
+    T: Signed + Clone + 'static,
+{
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        let u = self.u.get();
+
+        // Floats have a sign even when zero, which signum() would return
+        self.y.set(if u.is_zero() { T::zero() } else { u.signum() });
+        Ok(StepResult::Continue)
+    }
+}
+
+/// Outputs the sine of the input, in radians
+#[derive(BlockIO)]
+pub struct Sin {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u: Input<f64>,
+
+    #[blockio(output)]
+    y: Output<f64>,
+}
+
+impl Sin {
+    pub fn new(name: &str) -> Self {
+        Sin {
+            name: name.to_string(),
+            u: Input::default(),
+            y: Output::default(),
+        }
+    }
+}
+
+impl Block for Sin {
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        self.y.set(self.u.get().sin());
+        Ok(StepResult::Continue)
+    }
+}
+
+/// Outputs the cosine of the input, in radians
+#[derive(BlockIO)]
+pub struct Cos {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u: Input<f64>,
+
+    #[blockio(output)]
+    y: Output<f64>,
+}
+
+impl Cos {
+    pub fn new(name: &str) -> Self {
+        Cos {
+            name: name.to_string(),
+            u: Input::default(),
+            y: Output::default(),
+        }
+    }
+}
+
+impl Block for Cos {
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        self.y.set(self.u.get().cos());
+        Ok(StepResult::Continue)
+    }
+}
+
+/// Outputs the four-quadrant arctangent of `y / x`, in [-pi, pi]
+#[derive(BlockIO)]
+pub struct Atan2 {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    y: Input<f64>,
+
+    #[blockio(input)]
+    x: Input<f64>,
+
+    #[blockio(output)]
+    angle: Output<f64>,
+}
+
+impl Atan2 {
+    pub fn new(name: &str) -> Self {
+        Atan2 {
+            name: name.to_string(),
+            y: Input::default(),
+            x: Input::default(),
+            angle: Output::default(),
+        }
+    }
+}
+
+impl Block for Atan2 {
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        self.angle.set(self.y.get().atan2(self.x.get()));
+        Ok(StepResult::Continue)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AngleRange {
+    /// [-pi, pi)
+    Symmetric,
+    /// [0, 2pi)
+    Positive,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WrapAngleParams {
+    pub range: AngleRange,
+}
+
+impl From<AngleRange> for WrapAngleParams {
+    fn from(value: AngleRange) -> Self {
+        WrapAngleParams { range: value }
+    }
+}
+
+/// Wraps an angle in radians to the range selected in the parameters
+#[derive(BlockIO)]
+pub struct WrapAngle {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u: Input<f64>,
+
+    #[blockio(output)]
+    y: Output<f64>,
+
+    params: WrapAngleParams,
+}
+
+impl WrapAngle {
+    pub fn new(name: &str, params: WrapAngleParams) -> Self {
+        WrapAngle {
+            name: name.to_string(),
+            u: Input::default(),
+            y: Output::default(),
+            params,
+        }
+    }
+
+    pub fn from_store(
+        name: &str,
+        store: &mut ParameterStore,
+        default_params: WrapAngleParams,
+    ) -> Result<Self, ParameterStoreError> {
+        let params = store.get_block_params(name, default_params)?;
+
+        Ok(Self::new(name, params))
+    }
+}
+
+impl Block for WrapAngle {
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        let u = self.u.get();
+
+        self.y.set(match self.params.range {
+            AngleRange::Symmetric => wrap_two_pi(u + PI) - PI,
+            AngleRange::Positive => wrap_two_pi(u),
+        });
+        Ok(StepResult::Continue)
+    }
+
+    fn get_params(&self) -> Option<Value> {
+        Some(serde_json::to_value(&self.params).unwrap())
+    }
+
+    fn set_params(&mut self, params: Value) -> Result<()> {
+        self.params = parse_params(&self.name, params)?;
+        Ok(())
+    }
+}
+
+/// Wraps `angle` to [0, 2pi)
+fn wrap_two_pi(angle: f64) -> f64 {
+    let wrapped = angle.rem_euclid(TAU);
+
+    // Small negative angles are rounded up to exactly 2pi
+    if wrapped >= TAU {
+        0.0
+    } else {
+        wrapped
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MatVecMulParams<const R: usize, const C: usize> {
+    pub m: SMatrix<f64, R, C>,
+}
+
+impl<const R: usize, const C: usize> From<SMatrix<f64, R, C>> for MatVecMulParams<R, C> {
+    fn from(value: SMatrix<f64, R, C>) -> Self {
+        MatVecMulParams { m: value }
+    }
+}
+
+/// Multiplies the input by a constant matrix: y = M u.
+/// The dimensions are part of the type, so a matrix read from the parameter store with
+/// the wrong shape is rejected when the block is created.
+#[derive(BlockIO)]
+pub struct MatVecMul<const R: usize, const C: usize> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u: Input<SVector<f64, C>>,
+
+    #[blockio(output)]
+    y: Output<SVector<f64, R>>,
+
+    params: MatVecMulParams<R, C>,
+}
+
+impl<const R: usize, const C: usize> MatVecMul<R, C> {
+    pub fn new(name: &str, params: MatVecMulParams<R, C>) -> Self {
+        MatVecMul {
+            name: name.to_string(),
+            u: Input::default(),
+            y: Output::default(),
+            params,
+        }
+    }
+
+    pub fn from_store(
+        name: &str,
+        store: &mut ParameterStore,
+        default_params: MatVecMulParams<R, C>,
+    ) -> Result<Self, ParameterStoreError> {
+        let params = store.get_block_params(name, default_params)?;
+
+        Ok(Self::new(name, params))
+    }
+}
+
+impl<const R: usize, const C: usize> Block for MatVecMul<R, C> {
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        self.y.set(self.params.m * self.u.get());
+        Ok(StepResult::Continue)
+    }
+
+    fn get_params(&self) -> Option<Value> {
+        Some(serde_json::to_value(&self.params).unwrap())
+    }
+
+    fn set_params(&mut self, params: Value) -> Result<()> {
+        self.params = parse_params(&self.name, params)?;
+        Ok(())
+    }
+}
+
+/// Running minimum, maximum and mean of the input since the first step. Each output
+/// may be left unconnected if the statistic is not needed.
+#[derive(BlockIO)]
+pub struct Statistics<T> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u: Input<T>,
+
+    #[blockio(output, optional)]
+    y_min: Output<T>,
+
+    #[blockio(output, optional)]
+    y_max: Output<T>,
+
+    #[blockio(output, optional)]
+    y_mean: Output<T>,
+
+    min: T,
+    max: T,
+    mean: T,
+}
+
+impl<T> Statistics<T>
+where
+    T: Float + 'static,
+{
+    pub fn new(name: &str) -> Self {
+        Statistics {
+            name: name.to_string(),
+            u: Input::default(),
+            y_min: Output::default(),
+            y_max: Output::default(),
+            y_mean: Output::default(),
+            min: T::infinity(),
+            max: T::neg_infinity(),
+            mean: T::zero(),
+        }
+    }
+}
+
+impl<T> Block for Statistics<T>
+where
+    T: Float + FromPrimitive + 'static,
+{
+    fn step(&mut self, k: StepInfo) -> Result<StepResult> {
+        let u = self.u.get();
+        let count: T = FromPrimitive::from_usize(k.k).unwrap();
+
+        self.min = self.min.min(u);
+        self.max = self.max.max(u);
+        self.mean = self.mean + (u - self.mean) / count;
+
+        self.y_min.set(self.min);
+        self.y_max.set(self.max);
+        self.y_mean.set(self.mean);
+
+        Ok(StepResult::Continue)
+    }
+
+    fn reset(&mut self) {
+        self.min = T::infinity();
+        self.max = T::neg_infinity();
+        self.mean = T::zero();
+    }
+
+    fn save_state(&self) -> Option<BlockState> {
+        Some(Box::new((self.min, self.max, self.mean)))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) -> Result<()> {
+        (self.min, self.max, self.mean) = *downcast_state::<(T, T, T)>(&self.name, state)?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IntegrationMethod {
+    /// Adds `u * dt` on every step
+    Euler,
+    /// Adds the mean of the current and previous inputs times `dt` on every step
+    /// (Tustin's rule). The output is zero on the first step, as there is no previous
+    /// input yet.
+    Trapezoidal,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AccumulatorParams {
+    pub method: IntegrationMethod,
+}
+
+impl From<IntegrationMethod> for AccumulatorParams {
+    fn from(value: IntegrationMethod) -> Self {
+        AccumulatorParams { method: value }
+    }
+}
+
+/// Approximates the integral of the input with the method selected in the parameters.
+/// While `reset` is true the output is zero, and the sum restarts from the following
+/// step.
+#[derive(BlockIO)]
+pub struct Accumulator<T> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u: Input<T>,
+
+    /// Never resets if unconnected
+    #[blockio(input, optional)]
+    reset: Input<bool>,
+
+    #[blockio(output)]
+    y: Output<T>,
+
+    params: AccumulatorParams,
+    acc: T,
+    /// Input of the previous step, None on the first step and after a reset
+    prev: Option<T>,
+}
+
+impl<T> Accumulator<T>
+where
+    T: Float + 'static,
+{
+    pub fn new(name: &str, params: AccumulatorParams) -> Self {
+        Accumulator {
+            name: name.to_string(),
+            u: Input::default(),
+            reset: Input::with_default(false),
+            y: Output::default(),
+            params,
+            acc: T::zero(),
+            prev: None,
+        }
+    }
+
+    pub fn from_store(
+        name: &str,
+        store: &mut ParameterStore,
+        default_params: AccumulatorParams,
+    ) -> Result<Self, ParameterStoreError> {
+        let params = store.get_block_params(name, default_params)?;
+
+        Ok(Self::new(name, params))
+    }
+}
+
+impl<T> Block for Accumulator<T>
+where
+    T: Float + FromPrimitive + 'static,
+{
+    fn step(&mut self, k: StepInfo) -> Result<StepResult> {
+        let u = self.u.get();
+
+        if self.reset.get() {
+            self.acc = T::zero();
+        } else {
+            let dt: T = FromPrimitive::from_f64(k.dt).unwrap();
+            self.acc = match (self.params.method, self.prev) {
+                (IntegrationMethod::Euler, _) => self.acc + u * dt,
+                (IntegrationMethod::Trapezoidal, Some(prev)) => {
+                    self.acc + (prev + u) * dt / (T::one() + T::one())
+                }
+                (IntegrationMethod::Trapezoidal, None) => self.acc,
+            };
+        }
+
+        self.prev = Some(u);
+        self.y.set(self.acc);
+        Ok(StepResult::Continue)
+    }
+
+    fn reset(&mut self) {
+        self.acc = T::zero();
+        self.prev = None;
+    }
+
+    fn save_state(&self) -> Option<BlockState> {
+        Some(Box::new((self.acc, self.prev)))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) -> Result<()> {
+        (self.acc, self.prev) = *downcast_state::<(T, Option<T>)>(&self.name, state)?;
+        Ok(())
+    }
+
+    fn get_params(&self) -> Option<Value> {
+        Some(serde_json::to_value(&self.params).unwrap())
+    }
+
+    fn set_params(&mut self, params: Value) -> Result<()> {
+        self.params = parse_params(&self.name, params)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        producers::{Clock, Constant},
+        siso::Delay,
+        test_utils::{add_sequence, params, Probe},
+    };
+    use control_system::{ControlSystemBuilder, ControlSystemError};
+    use nalgebra::{Matrix2, Vector2};
+
+    /// The system of the `add` example: sums 1 to its previous output on every step
+    fn adder<T>(one: T, zero: T) -> ControlSystemBuilder
+    where
+        T: Clone + std::iter::Sum + Num + Serialize + DeserializeOwned + 'static,
+    {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(
+                Constant::new("const_1", one.clone().into()),
+                &[],
+                &[("y", "one")],
+            )
+            .unwrap();
+        builder
+            .add_block(
+                Delay::new("delay", vec![zero].into()),
+                &[("u", "sum")],
+                &[("y", "feedback")],
+            )
+            .unwrap();
+        builder
+            .add_block(
+                Add::<T, 2>::new("add", vec![one.clone(), one].into()),
+                &[("u1", "one"), ("u2", "feedback")],
+                &[("y", "sum")],
+            )
+            .unwrap();
+        builder
+    }
+
+    #[test]
+    fn simulate_adder() {
+        let mut system = adder(1.0, 0.0).build("adder", params(1.0)).unwrap();
+
+        let recorded = system.simulate(5, &["sum", "feedback"]).unwrap();
+        assert_eq!(recorded["sum"], [1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(recorded["feedback"], [0.0, 1.0, 2.0, 3.0, 4.0]);
+
+        assert!(matches!(
+            system.simulate(1, &["sum", "missing"]),
+            Err(ControlSystemError::UnknownSignalName(signal)) if signal == "missing"
+        ));
+
+        let mut system = adder(1, 0).build("adder", params(1.0)).unwrap();
+        assert!(matches!(
+            system.simulate(1, &["sum"]),
+            Err(ControlSystemError::TypeError { signal, .. }) if signal == "sum"
+        ));
+    }
+
+    #[test]
+    fn statistics_of_sequence() {
+        let values = [3.0, -1.0, 4.0, 1.0, -5.0, 9.0];
+
+        let mut builder = ControlSystemBuilder::default();
+        add_sequence(&mut builder, "u", &values);
+        builder
+            .add_block(
+                Statistics::<f64>::new("stats"),
+                &[("u", "u")],
+                &[("y_min", "min"), ("y_max", "max"), ("y_mean", "mean")],
+            )
+            .unwrap();
+
+        let mut recorded = vec![];
+        for signal in ["min", "max", "mean"] {
+            let (probe, values) = Probe::<f64>::new(&format!("probe_{signal}"));
+            builder.add_block(probe, &[("u", signal)], &[]).unwrap();
+            recorded.push(values);
+        }
+
+        let mut system = builder.build("test", params(1.0)).unwrap();
+        for _ in 0..values.len() {
+            system.step().unwrap();
+        }
+
+        assert_eq!(*recorded[0].borrow(), [3.0, -1.0, -1.0, -1.0, -5.0, -5.0]);
+        assert_eq!(*recorded[1].borrow(), [3.0, 3.0, 4.0, 4.0, 4.0, 9.0]);
+        assert!((recorded[2].borrow()[5] - 11.0 / 6.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn statistics_with_unused_outputs() {
+        let values = [3.0, -1.0, 4.0];
+
+        let mut builder = ControlSystemBuilder::default();
+        add_sequence(&mut builder, "u", &values);
+        builder
+            .add_block(
+                Statistics::<f64>::new("stats"),
+                &[("u", "u")],
+                &[("y_max", "max")],
+            )
+            .unwrap();
+        assert!(builder.validate().is_ok());
+
+        let recorded = builder
+            .build("test", params(1.0))
+            .unwrap()
+            .simulate(values.len(), &["max"])
+            .unwrap();
+
+        assert_eq!(recorded["max"], [3.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn accumulator_restarts_on_reset() {
+        let accumulate = |reset: bool| {
+            let (probe, values) = Probe::<f64>::new("probe");
+
+            let mut builder = ControlSystemBuilder::default();
+            add_sequence(&mut builder, "u", &[1.0; 6]);
+
+            let mut inputs = vec![("u", "u")];
+            if reset {
+                add_sequence(
+                    &mut builder,
+                    "reset",
+                    &[false, false, false, true, false, false],
+                );
+                inputs.push(("reset", "reset"));
+            }
+            builder
+                .add_block(
+                    Accumulator::<f64>::new("acc", IntegrationMethod::Euler.into()),
+                    &inputs,
+                    &[("y", "y")],
+                )
+                .unwrap();
+            builder.add_block(probe, &[("u", "y")], &[]).unwrap();
+
+            let mut system = builder.build("test", params(1.0)).unwrap();
+            for _ in 0..6 {
+                system.step().unwrap();
+            }
+            values.take()
+        };
+
+        assert_eq!(accumulate(false), [1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(accumulate(true), [1.0, 2.0, 3.0, 0.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn trapezoidal_integration_of_sine() {
+        let dt = 0.01;
+        let input: Vec<f64> = (0..1000).map(|i| (i as f64 * dt).sin()).collect();
+
+        let mut builder = ControlSystemBuilder::default();
+        add_sequence(&mut builder, "u", &input);
+        for method in [IntegrationMethod::Euler, IntegrationMethod::Trapezoidal] {
+            let name = format!("{method:?}");
+            builder
+                .add_block(
+                    Accumulator::<f64>::new(&name, method.into()),
+                    &[("u", "u")],
+                    &[("y", &name)],
+                )
+                .unwrap();
+        }
+
+        let recorded = builder
+            .build("test", params(dt))
+            .unwrap()
+            .simulate(input.len(), &["Euler", "Trapezoidal"])
+            .unwrap();
+
+        // Euler adds the input of the current step over the whole step, so its output
+        // approximates the integral up to the end of the step
+        let integral = |t: f64| 1.0 - t.cos();
+        let max_error = |y: &[f64], offset: f64| {
+            y.iter()
+                .enumerate()
+                .map(|(i, y)| (y - integral(i as f64 * dt + offset)).abs())
+                .fold(0.0, f64::max)
+        };
+        let euler = max_error(&recorded["Euler"], dt);
+        let trapezoidal = max_error(&recorded["Trapezoidal"], 0.0);
+
+        assert!(trapezoidal < 1e-4, "{trapezoidal}");
+        assert!(trapezoidal * 100.0 < euler, "{trapezoidal} vs {euler}");
+    }
+
+    #[derive(Clone)]
+    struct CartState {
+        position: f64,
+        velocity: f64,
+    }
+
+    #[test]
+    fn select_velocity_field() {
+        let mut builder = ControlSystemBuilder::default();
+        add_sequence(&mut builder, "velocity_ref", &[3.0, 2.5, 2.0, 1.5, 1.0]);
+        builder
+            .add_block(Clock::new("clock"), &[], &[("y", "t")])
+            .unwrap()
+            .add_block(
+                Map::new("cart", |t: f64, _: StepInfo| CartState {
+                    position: 3.0 * t - t * t / 2.0,
+                    velocity: 3.0 - t,
+                }),
+                &[("u", "t")],
+                &[("y", "state")],
+            )
+            .unwrap()
+            .add_block(
+                SelectField::new("select_velocity", |s: &CartState| s.velocity),
+                &[("u", "state")],
+                &[("y", "velocity")],
+            )
+            .unwrap()
+            .add_block(
+                SelectField::new("select_position", |s: &CartState| s.position),
+                &[("u", "state")],
+                &[("y", "position")],
+            )
+            .unwrap();
+
+        let recorded = builder
+            .build("test", params(0.5))
+            .unwrap()
+            .simulate(5, &["velocity", "velocity_ref", "position"])
+            .unwrap();
+
+        assert_eq!(recorded["velocity"], recorded["velocity_ref"]);
+        assert_eq!(recorded["position"], [0.0, 1.375, 2.5, 3.375, 4.0]);
+    }
+
+    #[test]
+    fn prefixed_adders_do_not_collide() {
+        let mut builder = ControlSystemBuilder::default();
+        for (prefix, increment) in [("left/", 1.0), ("right/", 2.0)] {
+            builder
+                .add_block_prefixed(
+                    prefix,
+                    Constant::new("const", increment.into()),
+                    &[],
+                    &[("y", "increment")],
+                )
+                .unwrap()
+                .add_block_prefixed(
+                    prefix,
+                    Delay::new("delay", vec![0.0].into()),
+                    &[("u", "sum")],
+                    &[("y", "feedback")],
+                )
+                .unwrap()
+                .add_block_prefixed(
+                    prefix,
+                    Add::<f64, 2>::new("add", vec![1.0, 1.0].into()),
+                    &[("u1", "increment"), ("u2", "feedback")],
+                    &[("y", "sum")],
+                )
+                .unwrap();
+        }
+
+        assert_eq!(
+            builder.block_names(),
+            [
+                "left/add",
+                "left/const",
+                "left/delay",
+                "right/add",
+                "right/const",
+                "right/delay"
+            ]
+        );
+
+        let recorded = builder
+            .build("test", params(1.0))
+            .unwrap()
+            .simulate(3, &["left/sum", "right/sum"])
+            .unwrap();
+        assert_eq!(recorded["left/sum"], [1.0, 2.0, 3.0]);
+        assert_eq!(recorded["right/sum"], [2.0, 4.0, 6.0]);
+    }
+
+    /// Output `y` of `block` after one step, with each input port connected to a signal
+    /// holding the given value
+    fn output<B: Block + 'static>(block: B, inputs: &[(&str, f64)]) -> f64 {
+        port_output(block, inputs, "y")
+    }
+
+    /// Value of the output port `port` of `block` after one step, with each input port
+    /// connected to a signal holding the given value
+    fn port_output<B, U, Y>(block: B, inputs: &[(&str, U)], port: &str) -> Y
+    where
+        B: Block + 'static,
+        U: Clone + 'static,
+        Y: Clone + 'static,
+    {
+        let (probe, values) = Probe::new("probe");
+
+        let mut builder = ControlSystemBuilder::default();
+        for (input, value) in inputs {
+            builder
+                .add_external_signal::<U>(input)
+                .unwrap()
+                .set_initial(input, value.clone())
+                .unwrap();
+        }
+
+        let connections: Vec<(&str, &str)> =
+            inputs.iter().map(|(input, _)| (*input, *input)).collect();
+        builder
+            .add_block(block, &connections, &[(port, "output")])
+            .unwrap();
+        builder.add_block(probe, &[("u", "output")], &[]).unwrap();
+
+        builder.build("test", params(1.0)).unwrap().step().unwrap();
+        values.take().remove(0)
+    }
+
+    #[test]
+    fn min_max_of_mixed_signs() {
+        let inputs = [("u1", 2.5), ("u2", -3.0), ("u3", 0.0), ("u4", -0.5)];
+
+        assert_eq!(output(Min::<f64, 4>::new("min"), &inputs), -3.0);
+        assert_eq!(output(Max::<f64, 4>::new("max"), &inputs), 2.5);
+        assert_eq!(output(Min::<f64, 1>::new("min"), &[("u1", -1.0)]), -1.0);
+        assert_eq!(
+            output(Max::<f64, 2>::new("max"), &[("u1", -4.0), ("u2", -2.0)]),
+            -2.0
+        );
+    }
+
+    #[test]
+    fn abs_and_sign() {
+        for (u, abs, sign) in [(-2.5, 2.5, -1.0), (0.0, 0.0, 0.0), (3.0, 3.0, 1.0)] {
+            assert_eq!(output(Abs::<f64>::new("abs"), &[("u", u)]), abs);
+            assert_eq!(output(Sign::<f64>::new("sign"), &[("u", u)]), sign);
+        }
+
+        let sign = output(Sign::<f64>::new("sign"), &[("u", -0.0)]);
+        assert!(sign == 0.0 && sign.is_sign_positive());
+    }
+
+    #[test]
+    fn wrap_angle_multiple_turns() {
+        let wrap =
+            |range: AngleRange, u: f64| output(WrapAngle::new("wrap", range.into()), &[("u", u)]);
+
+        let cases = [
+            (3.0 * PI, -PI, PI),
+            (-3.0 * PI, -PI, PI),
+            (3.5 * PI, -0.5 * PI, 1.5 * PI),
+            (-3.5 * PI, 0.5 * PI, 0.5 * PI),
+            (20.0 * PI + 1.0, 1.0, 1.0),
+            (-0.5, -0.5, TAU - 0.5),
+        ];
+        for (u, symmetric, positive) in cases {
+            assert!(
+                (wrap(AngleRange::Symmetric, u) - symmetric).abs() < 1e-12,
+                "{u}"
+            );
+            assert!(
+                (wrap(AngleRange::Positive, u) - positive).abs() < 1e-12,
+                "{u}"
+            );
+        }
+    }
+
+    const ANGLES: [f64; 7] = [0.0, PI / 6.0, PI / 4.0, PI / 2.0, 2.0, -PI / 3.0, PI];
+
+    #[test]
+    fn sin_and_cos() {
+        for u in ANGLES {
+            assert_eq!(output(Sin::new("sin"), &[("u", u)]), u.sin());
+            assert_eq!(output(Cos::new("cos"), &[("u", u)]), u.cos());
+        }
+
+        assert!((output(Sin::new("sin"), &[("u", PI / 6.0)]) - 0.5).abs() < 1e-12);
+        assert!((output(Cos::new("cos"), &[("u", PI / 3.0)]) - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn atan2_all_quadrants() {
+        let atan2 = |y: f64, x: f64| -> f64 {
+            port_output(Atan2::new("atan2"), &[("y", y), ("x", x)], "angle")
+        };
+
+        for angle in ANGLES {
+            let (y, x) = (2.0 * angle.sin(), 2.0 * angle.cos());
+            assert_eq!(atan2(y, x), y.atan2(x));
+            assert!((atan2(y, x) - angle).abs() < 1e-12, "{angle}");
+        }
+        assert_eq!(atan2(-1.0, -1.0), -0.75 * PI);
+    }
+
+    /// Rotation of the vector `u` by `angle` with a [`MatVecMul`]
+    fn rotate(u: Vector2<f64>, angle: f64) -> Vector2<f64> {
+        let (sin, cos) = angle.sin_cos();
+        let rotation = Matrix2::new(cos, -sin, sin, cos);
+
+        port_output(
+            MatVecMul::new("rotation", rotation.into()),
+            &[("u", u)],
+            "y",
+        )
+    }
+
+    #[test]
+    fn rotation_matrix() {
+        let y = rotate(Vector2::new(1.0, 2.0), PI / 2.0);
+        assert!((y - Vector2::new(-2.0, 1.0)).norm() < 1e-12, "{y}");
+
+        let y = rotate(Vector2::new(2.0, 0.0), PI / 3.0);
+        assert!((y - Vector2::new(1.0, 3f64.sqrt())).norm() < 1e-12, "{y}");
+    }
+
+    #[test]
+    fn map_with_time_dependent_gain() {
+        let (probe, values) = Probe::<f64>::new("probe");
+
+        let mut builder = ControlSystemBuilder::default();
+        add_sequence(&mut builder, "u", &[1.0, 2.0, 3.0, 4.0]);
+        builder
+            .add_block(
+                Map::new("gain", |u: f64, k: StepInfo| u * (1.0 + k.t)),
+                &[("u", "u")],
+                &[("y", "y")],
+            )
+            .unwrap();
+        builder.add_block(probe, &[("u", "y")], &[]).unwrap();
+
+        let mut system = builder.build("test", params(0.5)).unwrap();
+        for _ in 0..4 {
+            system.step().unwrap();
+        }
+
+        assert_eq!(*values.borrow(), [1.0, 3.0, 6.0, 10.0]);
+    }
+}
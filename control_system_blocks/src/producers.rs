@@ -1,7 +1,19 @@
+use arrayinit::arr;
 use control_system::{
-    io::Output, Block, BlockIO, ParameterStore, ParameterStoreError, Result, StepInfo, StepResult,
+    downcast_state, invalid_params, io::Output, parse_params, Block, BlockIO, BlockState,
+    ParameterStore, ParameterStoreError, Result, StepInfo, StepResult,
 };
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rand_distr::{Distribution, Normal, Uniform};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    any::Any,
+    fs, io,
+    path::{Path, PathBuf},
+    time::Instant,
+};
 
 #[derive(Serialize, Deserialize)]
 pub struct ConstantParams<T> {
@@ -27,7 +39,6 @@ pub struct Constant<T> {
 
 impl<T> Constant<T>
 where
-    T: Default,
     Output<T>: Default,
 {
     pub fn new(name: &str, params: ConstantParams<T>) -> Self {
@@ -41,7 +52,7 @@ where
 
 impl<T> Constant<T>
 where
-    T: Default + Serialize + DeserializeOwned + 'static,
+    T: Clone + Serialize + DeserializeOwned + 'static,
 {
     pub fn from_store(
         name: &str,
@@ -77,7 +88,6 @@ pub struct Generator<T, F> {
 
 impl<T, F> Generator<T, F>
 where
-    T: Default,
     Output<T>: Default,
 {
     pub fn new(name: &str, generator: F) -> Self {
@@ -99,3 +109,836 @@ where
         Ok(StepResult::Continue)
     }
 }
+
+/// Like [`Generator`], with a closure producing `N` values at once, written to the
+/// outputs `y1` to `yN`
+#[derive(BlockIO)]
+pub struct GeneratorN<T, const N: usize, F> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(output_arr)]
+    y: [Output<T>; N],
+
+    generator: F,
+}
+
+impl<T, const N: usize, F> GeneratorN<T, N, F>
+where
+    Output<T>: Default,
+{
+    pub fn new(name: &str, generator: F) -> Self {
+        GeneratorN {
+            name: name.to_string(),
+            y: arr![|_| Output::default()],
+            generator,
+        }
+    }
+}
+
+impl<T, const N: usize, F> Block for GeneratorN<T, N, F>
+where
+    T: 'static + Clone,
+    F: Fn() -> [T; N],
+{
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        for (y, v) in self.y.iter_mut().zip((self.generator)()) {
+            y.set(v);
+        }
+        Ok(StepResult::Continue)
+    }
+}
+
+/// Outputs the simulation time
+#[derive(BlockIO)]
+pub struct Clock {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(output)]
+    y: Output<f64>,
+}
+
+impl Clock {
+    pub fn new(name: &str) -> Self {
+        Clock {
+            name: name.to_string(),
+            y: Output::default(),
+        }
+    }
+}
+
+impl Block for Clock {
+    fn step(&mut self, k: StepInfo) -> Result<StepResult> {
+        self.y.set(k.t);
+        Ok(StepResult::Continue)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WaveParams {
+    pub amplitude: f64,
+    /// Frequency in Hz
+    pub frequency: f64,
+    /// Phase in radians
+    pub phase: f64,
+    pub offset: f64,
+}
+
+/// Outputs `offset + amplitude * sin(2*pi*frequency*t + phase)`
+#[derive(BlockIO)]
+pub struct SineWave {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(output)]
+    y: Output<f64>,
+
+    params: WaveParams,
+}
+
+impl SineWave {
+    pub fn new(name: &str, params: WaveParams) -> Self {
+        SineWave {
+            name: name.to_string(),
+            y: Output::default(),
+            params,
+        }
+    }
+
+    pub fn from_store(
+        name: &str,
+        store: &mut ParameterStore,
+        default_params: WaveParams,
+    ) -> Result<Self, ParameterStoreError> {
+        let params = store.get_block_params(name, default_params)?;
+
+        Ok(Self::new(name, params))
+    }
+}
+
+impl Block for SineWave {
+    fn step(&mut self, k: StepInfo) -> Result<StepResult> {
+        let p = &self.params;
+        let angle = 2.0 * std::f64::consts::PI * p.frequency * k.t + p.phase;
+
+        self.y.set(p.offset + p.amplitude * angle.sin());
+        Ok(StepResult::Continue)
+    }
+
+    fn get_params(&self) -> Option<Value> {
+        Some(serde_json::to_value(&self.params).unwrap())
+    }
+
+    fn set_params(&mut self, params: Value) -> Result<()> {
+        self.params = parse_params(&self.name, params)?;
+        Ok(())
+    }
+}
+
+/// Square wave with 50% duty cycle, in phase with the corresponding [`SineWave`]
+#[derive(BlockIO)]
+pub struct SquareWave {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(output)]
+    y: Output<f64>,
+
+    params: WaveParams,
+}
+
+impl SquareWave {
+    pub fn new(name: &str, params: WaveParams) -> Self {
+        SquareWave {
+            name: name.to_string(),
+            y: Output::default(),
+            params,
+        }
+    }
+
+    pub fn from_store(
+        name: &str,
+        store: &mut ParameterStore,
+        default_params: WaveParams,
+    ) -> Result<Self, ParameterStoreError> {
+        let params = store.get_block_params(name, default_params)?;
+
+        Ok(Self::new(name, params))
+    }
+}
+
+impl Block for SquareWave {
+    fn step(&mut self, k: StepInfo) -> Result<StepResult> {
+        let p = &self.params;
+        let cycles = p.frequency * k.t + p.phase / (2.0 * std::f64::consts::PI);
+
+        let y = if cycles.rem_euclid(1.0) < 0.5 {
+            p.amplitude
+        } else {
+            -p.amplitude
+        };
+
+        self.y.set(p.offset + y);
+        Ok(StepResult::Continue)
+    }
+
+    fn get_params(&self) -> Option<Value> {
+        Some(serde_json::to_value(&self.params).unwrap())
+    }
+
+    fn set_params(&mut self, params: Value) -> Result<()> {
+        self.params = parse_params(&self.name, params)?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RampParams {
+    pub slope: f64,
+    /// Time at which the output starts increasing
+    pub start_time: f64,
+    pub offset: f64,
+}
+
+/// Outputs `offset` until `start_time`, then increases with rate `slope`
+#[derive(BlockIO)]
+pub struct Ramp {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(output)]
+    y: Output<f64>,
+
+    params: RampParams,
+}
+
+impl Ramp {
+    pub fn new(name: &str, params: RampParams) -> Self {
+        Ramp {
+            name: name.to_string(),
+            y: Output::default(),
+            params,
+        }
+    }
+
+    pub fn from_store(
+        name: &str,
+        store: &mut ParameterStore,
+        default_params: RampParams,
+    ) -> Result<Self, ParameterStoreError> {
+        let params = store.get_block_params(name, default_params)?;
+
+        Ok(Self::new(name, params))
+    }
+}
+
+impl Block for Ramp {
+    fn step(&mut self, k: StepInfo) -> Result<StepResult> {
+        let p = &self.params;
+
+        self.y
+            .set(p.offset + p.slope * (k.t - p.start_time).max(0.0));
+        Ok(StepResult::Continue)
+    }
+
+    fn get_params(&self) -> Option<Value> {
+        Some(serde_json::to_value(&self.params).unwrap())
+    }
+
+    fn set_params(&mut self, params: Value) -> Result<()> {
+        self.params = parse_params(&self.name, params)?;
+        Ok(())
+    }
+}
+
+/// Outputs the step number `k`, the simulation time `t` and the wall-clock time elapsed
+/// since its previous step in seconds, ie. the time taken by a whole step of the
+/// system. The elapsed time is zero on the first step. Outputs that are not needed
+/// may be left unconnected.
+#[derive(BlockIO)]
+pub struct Diagnostics {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(output, optional)]
+    k: Output<usize>,
+
+    #[blockio(output, optional)]
+    t: Output<f64>,
+
+    #[blockio(output, optional)]
+    step_duration: Output<f64>,
+
+    last_step: Option<Instant>,
+}
+
+impl Diagnostics {
+    pub fn new(name: &str) -> Self {
+        Diagnostics {
+            name: name.to_string(),
+            k: Output::default(),
+            t: Output::default(),
+            step_duration: Output::default(),
+            last_step: None,
+        }
+    }
+}
+
+impl Block for Diagnostics {
+    fn step(&mut self, k: StepInfo) -> Result<StepResult> {
+        let now = Instant::now();
+        let elapsed = self
+            .last_step
+            .map_or(0.0, |last| now.duration_since(last).as_secs_f64());
+        self.last_step = Some(now);
+
+        self.k.set(k.k);
+        self.t.set(k.t);
+        self.step_duration.set(elapsed);
+        Ok(StepResult::Continue)
+    }
+
+    fn reset(&mut self) {
+        self.last_step = None;
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoiseDistribution {
+    Gaussian,
+    Uniform,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct NoiseParams {
+    pub distribution: NoiseDistribution,
+    pub mean: f64,
+    pub variance: f64,
+    /// Seed of the random number generator, for reproducible runs
+    pub seed: u64,
+}
+
+enum NoiseSampler {
+    Gaussian(Normal<f64>),
+    Uniform(Uniform<f64>),
+}
+
+/// Outputs random samples drawn from a Gaussian or uniform distribution
+#[derive(BlockIO)]
+pub struct NoiseSource {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(output)]
+    y: Output<f64>,
+
+    rng: ChaCha8Rng,
+    sampler: NoiseSampler,
+    seed: u64,
+}
+
+impl NoiseSource {
+    /// Fails if the variance is negative, or if the mean or the variance are not finite
+    pub fn new(name: &str, params: NoiseParams) -> Result<Self> {
+        if !params.mean.is_finite() || !params.variance.is_finite() {
+            return Err(invalid_params(
+                name,
+                "Noise mean and variance must be finite",
+            ));
+        }
+        if params.variance < 0.0 {
+            return Err(invalid_params(name, "Noise variance must not be negative"));
+        }
+
+        let sampler = match params.distribution {
+            NoiseDistribution::Gaussian => NoiseSampler::Gaussian(
+                Normal::new(params.mean, params.variance.sqrt())
+                    .map_err(|e| invalid_params(name, &e.to_string()))?,
+            ),
+            NoiseDistribution::Uniform => {
+                // Variance of a uniform distribution in [a, b] is (b - a)^2 / 12
+                let half_width = (3.0 * params.variance).sqrt();
+                NoiseSampler::Uniform(Uniform::new_inclusive(
+                    params.mean - half_width,
+                    params.mean + half_width,
+                ))
+            }
+        };
+
+        Ok(NoiseSource {
+            name: name.to_string(),
+            y: Output::default(),
+            rng: ChaCha8Rng::seed_from_u64(params.seed),
+            sampler,
+            seed: params.seed,
+        })
+    }
+
+    pub fn from_store(
+        name: &str,
+        store: &mut ParameterStore,
+        default_params: NoiseParams,
+    ) -> Result<Self> {
+        let params = store.get_block_params(name, default_params)?;
+
+        Self::new(name, params)
+    }
+}
+
+impl Block for NoiseSource {
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        let y = match &self.sampler {
+            NoiseSampler::Gaussian(d) => d.sample(&mut self.rng),
+            NoiseSampler::Uniform(d) => d.sample(&mut self.rng),
+        };
+
+        self.y.set(y);
+        Ok(StepResult::Continue)
+    }
+
+    /// Restarts the random sequence, so that every trial sees the same noise
+    fn reset(&mut self) {
+        self.rng = ChaCha8Rng::seed_from_u64(self.seed);
+    }
+
+    fn save_state(&self) -> Option<BlockState> {
+        Some(Box::new(self.rng.clone()))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) -> Result<()> {
+        self.rng = downcast_state::<ChaCha8Rng>(&self.name, state)?.clone();
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CsvInterpolation {
+    /// Output the sample closest in time
+    Nearest,
+    /// Interpolate linearly between the two samples around the current time
+    Linear,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CsvEnd {
+    /// Keep outputting the last sample
+    Hold,
+    /// Restart from the first sample
+    Loop,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CsvSourceParams {
+    /// File with one `time,value` sample per line, sorted by time. A header line is
+    /// allowed.
+    pub path: PathBuf,
+    pub interpolation: CsvInterpolation,
+    /// What to output after the time of the last sample
+    pub end: CsvEnd,
+}
+
+/// Replays samples recorded in a CSV file. Before the first sample, its value is
+/// output.
+#[derive(BlockIO)]
+pub struct CsvSource {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(output)]
+    y: Output<f64>,
+
+    params: CsvSourceParams,
+    times: Vec<f64>,
+    values: Vec<f64>,
+}
+
+impl CsvSource {
+    /// Loads the samples from the file, failing if it can't be read or is malformed
+    pub fn new(name: &str, params: CsvSourceParams) -> Result<Self> {
+        let (times, values) =
+            read_csv_samples(&params.path).map_err(|err| invalid_params(name, &err.to_string()))?;
+
+        Ok(CsvSource {
+            name: name.to_string(),
+            y: Output::default(),
+            params,
+            times,
+            values,
+        })
+    }
+
+    pub fn from_store(
+        name: &str,
+        store: &mut ParameterStore,
+        default_params: CsvSourceParams,
+    ) -> Result<Self> {
+        let params = store.get_block_params(name, default_params)?;
+
+        Self::new(name, params)
+    }
+
+    fn sample(&self, t: f64) -> f64 {
+        let first = self.times[0];
+        let last = self.times[self.times.len() - 1];
+
+        let t = match self.params.end {
+            CsvEnd::Loop if t > last && last > first => first + (t - first) % (last - first),
+            _ => t,
+        };
+
+        // Index of the first sample after t
+        let i = self.times.partition_point(|&ts| ts <= t);
+        if i == 0 {
+            return self.values[0];
+        } else if i == self.times.len() {
+            return self.values[i - 1];
+        }
+
+        let (t0, t1) = (self.times[i - 1], self.times[i]);
+        let (y0, y1) = (self.values[i - 1], self.values[i]);
+        match self.params.interpolation {
+            CsvInterpolation::Nearest if t - t0 < t1 - t => y0,
+            CsvInterpolation::Nearest => y1,
+            CsvInterpolation::Linear => y0 + (y1 - y0) * (t - t0) / (t1 - t0),
+        }
+    }
+}
+
+impl Block for CsvSource {
+    fn step(&mut self, k: StepInfo) -> Result<StepResult> {
+        self.y.set(self.sample(k.t));
+        Ok(StepResult::Continue)
+    }
+}
+
+fn read_csv_samples(path: &Path) -> io::Result<(Vec<f64>, Vec<f64>)> {
+    let invalid = |line: usize, reason: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}:{}: {}", path.display(), line + 1, reason),
+        )
+    };
+
+    let mut times = vec![];
+    let mut values = vec![];
+
+    for (n, line) in fs::read_to_string(path)?.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let [t, y] = fields[..] else {
+            return Err(invalid(n, "expected two columns, time and value"));
+        };
+
+        let (Ok(t), Ok(y)) = (t.parse::<f64>(), y.parse::<f64>()) else {
+            if n == 0 {
+                // Header
+                continue;
+            }
+            return Err(invalid(n, "could not parse the sample as numbers"));
+        };
+
+        if times.last().is_some_and(|&last| t < last) {
+            return Err(invalid(n, "samples are not sorted by time"));
+        }
+
+        times.push(t);
+        values.push(y);
+    }
+
+    if times.is_empty() {
+        return Err(invalid(0, "the file contains no samples"));
+    }
+
+    Ok((times, values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{params, Probe};
+    use control_system::{ControlSystemBuilder, ControlSystemError};
+
+    /// Output `y` of `block` over `steps` steps of `dt` seconds
+    fn output<B: Block + 'static>(block: B, dt: f64, steps: usize) -> Vec<f64> {
+        let (probe, values) = Probe::new("probe");
+
+        let mut builder = ControlSystemBuilder::default();
+        builder.add_block(block, &[], &[("y", "y")]).unwrap();
+        builder.add_block(probe, &[("u", "y")], &[]).unwrap();
+
+        let mut system = builder.build("test", params(dt)).unwrap();
+        for _ in 0..steps {
+            system.step().unwrap();
+        }
+
+        values.take()
+    }
+
+    #[test]
+    fn clock_outputs_time() {
+        let y = output(Clock::new("clock"), 0.1, 20);
+
+        for (i, t) in y.iter().enumerate() {
+            assert!((t - i as f64 * 0.1).abs() < 1e-12, "step {i}: {t}");
+        }
+    }
+
+    fn wave() -> WaveParams {
+        WaveParams {
+            amplitude: 2.0,
+            frequency: 1.0,
+            phase: 0.0,
+            offset: 1.0,
+        }
+    }
+
+    /// Samples of `y` at t = 0, 0.25, 0.5, 0.75 and 1, taken every 0.125 s
+    fn quarter_periods(y: Vec<f64>) -> Vec<f64> {
+        y.into_iter().step_by(2).collect()
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-12, "{actual:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn sine_wave_samples() {
+        let y = quarter_periods(output(SineWave::new("sine", wave()), 0.125, 9));
+        assert_close(&y, &[1.0, 3.0, 1.0, -1.0, 1.0]);
+
+        let params = WaveParams {
+            phase: std::f64::consts::FRAC_PI_2,
+            ..wave()
+        };
+        let y = quarter_periods(output(SineWave::new("sine", params), 0.125, 9));
+        assert_close(&y, &[3.0, 1.0, -1.0, 1.0, 3.0]);
+    }
+
+    #[test]
+    fn square_wave_samples() {
+        let y = output(SquareWave::new("square", wave()), 0.125, 9);
+        assert_close(&y, &[3.0, 3.0, 3.0, 3.0, -1.0, -1.0, -1.0, -1.0, 3.0]);
+    }
+
+    #[test]
+    fn ramp_samples() {
+        let params = RampParams {
+            slope: 2.0,
+            start_time: 0.5,
+            offset: 1.0,
+        };
+
+        let y = quarter_periods(output(Ramp::new("ramp", params), 0.125, 9));
+        assert_close(&y, &[1.0, 1.0, 1.0, 1.5, 2.0]);
+    }
+
+    fn noise(mean: f64, variance: f64) -> NoiseParams {
+        NoiseParams {
+            distribution: NoiseDistribution::Gaussian,
+            mean,
+            variance,
+            seed: 0,
+        }
+    }
+
+    #[test]
+    fn noise_is_reproducible() {
+        let params = NoiseParams {
+            seed: 42,
+            ..noise(1.0, 4.0)
+        };
+        let y = output(NoiseSource::new("noise", params.clone()).unwrap(), 1.0, 3);
+        assert_eq!(
+            y,
+            [1.9559624767020436, 3.6681412204636157, 0.5782666334579394]
+        );
+
+        let params = NoiseParams {
+            distribution: NoiseDistribution::Uniform,
+            ..params
+        };
+        let y = output(NoiseSource::new("noise", params).unwrap(), 1.0, 3);
+        assert_eq!(y, [2.260213787113897, 4.119599533950127, 0.497818908128552]);
+    }
+
+    #[test]
+    fn noise_mean_and_variance() {
+        for distribution in [NoiseDistribution::Gaussian, NoiseDistribution::Uniform] {
+            let params = NoiseParams {
+                distribution,
+                ..noise(1.0, 4.0)
+            };
+            let y = output(NoiseSource::new("noise", params).unwrap(), 1.0, 20000);
+
+            let n = y.len() as f64;
+            let mean = y.iter().sum::<f64>() / n;
+            let variance = y.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+
+            assert!((mean - 1.0).abs() < 0.05, "{distribution:?}: mean {mean}");
+            assert!(
+                (variance - 4.0).abs() < 0.2,
+                "{distribution:?}: variance {variance}"
+            );
+        }
+    }
+
+    /// [`CsvSource`] replaying samples with a peak of 10 at t = 1, from 0 at t = 0 to 0
+    /// at t = 3
+    fn csv_source(interpolation: CsvInterpolation, end: CsvEnd) -> CsvSource {
+        let path = std::env::temp_dir().join(format!(
+            "control_system_blocks_csv_{:?}_{:?}_{}.csv",
+            interpolation,
+            end,
+            std::process::id()
+        ));
+        fs::write(&path, "time,value\n0,0\n1,10\n3,0\n").unwrap();
+
+        let params = CsvSourceParams {
+            path: path.clone(),
+            interpolation,
+            end,
+        };
+        let source = CsvSource::new("csv", params);
+        fs::remove_file(&path).unwrap();
+        source.unwrap()
+    }
+
+    #[test]
+    fn csv_source_interpolation() {
+        let y = output(csv_source(CsvInterpolation::Linear, CsvEnd::Hold), 0.5, 9);
+        assert_eq!(y, [0.0, 5.0, 10.0, 7.5, 5.0, 2.5, 0.0, 0.0, 0.0]);
+
+        let y = output(csv_source(CsvInterpolation::Nearest, CsvEnd::Hold), 0.5, 9);
+        assert_eq!(y, [0.0, 10.0, 10.0, 10.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+
+        let y = output(csv_source(CsvInterpolation::Linear, CsvEnd::Loop), 0.5, 9);
+        assert_eq!(y, [0.0, 5.0, 10.0, 7.5, 5.0, 2.5, 0.0, 5.0, 10.0]);
+    }
+
+    #[test]
+    fn csv_source_rejects_invalid_files() {
+        let path = std::env::temp_dir().join(format!(
+            "control_system_blocks_csv_invalid_{}.csv",
+            std::process::id()
+        ));
+        let params = CsvSourceParams {
+            path: path.clone(),
+            interpolation: CsvInterpolation::Linear,
+            end: CsvEnd::Hold,
+        };
+
+        // Missing, then unsorted
+        let missing = CsvSource::new("csv", params.clone());
+        fs::write(&path, "0,0\n1,10\n0.5,0\n").unwrap();
+        let unsorted = CsvSource::new("csv", params);
+        fs::remove_file(&path).unwrap();
+
+        for result in [missing, unsorted] {
+            assert!(matches!(
+                result,
+                Err(ControlSystemError::InvalidBlockParams { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn generator_n_channels() {
+        // Ramp and sine sharing the same counter
+        let n = std::cell::Cell::new(0.0f64);
+        let generator = GeneratorN::new("gen", move || {
+            let x = n.get();
+            n.set(x + 1.0);
+            [x, (x * std::f64::consts::FRAC_PI_2).sin()]
+        });
+        let (ramp, ramp_values) = Probe::<f64>::new("probe_ramp");
+        let (sine, sine_values) = Probe::<f64>::new("probe_sine");
+
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(generator, &[], &[("y1", "ramp"), ("y2", "sine")])
+            .unwrap();
+        builder.add_block(ramp, &[("u", "ramp")], &[]).unwrap();
+        builder.add_block(sine, &[("u", "sine")], &[]).unwrap();
+
+        let mut system = builder.build("test", params(1.0)).unwrap();
+        for _ in 0..5 {
+            system.step().unwrap();
+        }
+
+        assert_eq!(*ramp_values.borrow(), [0.0, 1.0, 2.0, 3.0, 4.0]);
+        assert_close(&sine_values.borrow(), &[0.0, 1.0, 0.0, -1.0, 0.0]);
+    }
+
+    #[test]
+    fn failed_add_block_releases_its_outputs() {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Constant::new("b", 0.0.into()), &[], &[("y", "b")])
+            .unwrap();
+
+        // "a" is connected before the connection to "b" fails
+        let generator = GeneratorN::new("gen", || [1.0, 2.0]);
+        assert!(matches!(
+            builder.add_block(generator, &[], &[("y1", "a"), ("y2", "b")]),
+            Err(ControlSystemError::MultipleProducers { .. })
+        ));
+
+        builder
+            .add_block(Constant::new("a", 1.0.into()), &[], &[("y", "a")])
+            .unwrap();
+    }
+
+    #[test]
+    fn diagnostics_count_steps() {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(
+                Diagnostics::new("diag"),
+                &[],
+                &[("k", "k"), ("t", "t"), ("step_duration", "step_duration")],
+            )
+            .unwrap();
+        builder
+            .add_block(
+                crate::math::Map::new("k_f64", |k: usize, _: StepInfo| k as f64),
+                &[("u", "k")],
+                &[("y", "k_f64")],
+            )
+            .unwrap();
+
+        let recorded = builder
+            .build("test", params(0.5))
+            .unwrap()
+            .simulate(6, &["k_f64", "t", "step_duration"])
+            .unwrap();
+
+        assert_eq!(recorded["k_f64"], [1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(recorded["t"], [0.0, 0.5, 1.0, 1.5, 2.0, 2.5]);
+        assert_eq!(recorded["step_duration"][0], 0.0);
+        assert!(recorded["step_duration"].iter().all(|d| *d >= 0.0));
+    }
+
+    #[test]
+    fn noise_rejects_invalid_params() {
+        assert!(NoiseSource::new("noise", noise(0.0, 0.0)).is_ok());
+
+        for params in [
+            noise(0.0, -1.0),
+            noise(f64::NAN, 1.0),
+            noise(0.0, f64::INFINITY),
+        ] {
+            assert!(matches!(
+                NoiseSource::new("noise", params),
+                Err(ControlSystemError::InvalidBlockParams { .. })
+            ));
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use control_system::{invalid_params, parse_params, Block, BlockRegistry, Result};
+use serde_json::Value;
+
+use crate::{
+    consumers::Print,
+    math::{Add, AddParams},
+    producers::Constant,
+    siso::{Delay, DelayParameters, PID},
+};
+
+/// Registry of the built-in blocks, operating on `f64` signals: `Constant`, `Add`,
+/// `PID`, `Delay` and `Print`. The number of inputs of `Add` is the length of its
+/// `gains`, while `Print` has no parameters.
+pub fn builtin_registry() -> BlockRegistry {
+    let mut registry = BlockRegistry::new();
+
+    registry
+        .register_new("Constant", Constant::<f64>::new)
+        .register("PID", |name, params| {
+            Ok(Box::new(PID::<f64>::new(
+                name,
+                parse_params(name, params)?,
+            )?))
+        })
+        .register("Delay", new_delay)
+        .register("Add", new_add)
+        .register("Print", |name, _| Ok(Box::new(Print::<f64>::new(name))));
+
+    registry
+}
+
+fn new_add(name: &str, params: Value) -> Result<Box<dyn Block>> {
+    let params: AddParams<f64> = parse_params(name, params)?;
+
+    let block: Box<dyn Block> = match params.gains.len() {
+        1 => Box::new(Add::<f64, 1>::new(name, params)),
+        2 => Box::new(Add::<f64, 2>::new(name, params)),
+        3 => Box::new(Add::<f64, 3>::new(name, params)),
+        4 => Box::new(Add::<f64, 4>::new(name, params)),
+        5 => Box::new(Add::<f64, 5>::new(name, params)),
+        6 => Box::new(Add::<f64, 6>::new(name, params)),
+        7 => Box::new(Add::<f64, 7>::new(name, params)),
+        8 => Box::new(Add::<f64, 8>::new(name, params)),
+        n => {
+            return Err(invalid_params(
+                name,
+                &format!(
+                    "Add supports from 1 to 8 inputs, but {} gains were provided",
+                    n
+                ),
+            ))
+        }
+    };
+
+    Ok(block)
+}
+
+fn new_delay(name: &str, params: Value) -> Result<Box<dyn Block>> {
+    let params: DelayParameters<f64> = parse_params(name, params)?;
+
+    if params.initial_values.is_empty() {
+        return Err(invalid_params(
+            name,
+            "Delay must have at least one initial value",
+        ));
+    }
+
+    Ok(Box::new(Delay::new(name, params)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{params, Probe};
+    use control_system::{ControlSystemBuilder, ControlSystemError};
+    use serde_json::json;
+
+    fn create(type_name: &str, params: Value) -> Result<Box<dyn Block>> {
+        builtin_registry().create(type_name, "block", params)
+    }
+
+    #[test]
+    fn creates_builtin_blocks() {
+        assert!(create("Constant", json!({ "c": 1.0 })).is_ok());
+        assert!(create(
+            "PID",
+            json!({ "kp": 1.0, "ki": 0.0, "kd": 0.0, "acc0": 0.0 })
+        )
+        .is_ok());
+        assert!(create("Delay", json!({ "initial_values": [0.0] })).is_ok());
+        assert!(create("Add", json!({ "gains": [1.0, -1.0] })).is_ok());
+        assert!(create("Print", Value::Null).is_ok());
+
+        assert!(matches!(
+            create("Sine", Value::Null),
+            Err(ControlSystemError::UnknownBlockType(_))
+        ));
+        assert!(matches!(
+            create("Add", json!({ "gains": vec![1.0; 9] })),
+            Err(ControlSystemError::InvalidBlockParams { .. })
+        ));
+        assert!(matches!(
+            create("Constant", json!({ "value": 1.0 })),
+            Err(ControlSystemError::InvalidBlockParams { .. })
+        ));
+    }
+
+    #[test]
+    fn constant_from_json_params() {
+        let (probe, values) = Probe::<f64>::new("probe");
+
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_boxed_block(
+                create("Constant", json!({ "c": 2.5 })).unwrap(),
+                &[],
+                &[("y", "y")],
+            )
+            .unwrap();
+        builder.add_block(probe, &[("u", "y")], &[]).unwrap();
+
+        let mut system = builder.build("test", params(1.0)).unwrap();
+        system.step().unwrap();
+        system.step().unwrap();
+
+        assert_eq!(*values.borrow(), [2.5, 2.5]);
+    }
+
+    #[test]
+    fn adder_from_config_file() {
+        let config = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/adder.toml");
+        let mut builder = ControlSystemBuilder::from_config(&config, &builtin_registry()).unwrap();
+        let (probe, values) = Probe::<f64>::new("probe");
+        builder.add_block(probe, &[("u", "sum")], &[]).unwrap();
+
+        let mut system = builder.build("adder", params(1.0)).unwrap();
+        for _ in 0..5 {
+            system.step().unwrap();
+        }
+
+        assert_eq!(*values.borrow(), [1.0, 2.0, 3.0, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn config_file_roundtrip() {
+        let registry = builtin_registry();
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_registered_block(
+                &registry,
+                "Constant",
+                "reference",
+                json!({ "c": 1.0 }),
+                &[],
+                &[("y", "r")],
+            )
+            .unwrap()
+            .add_registered_block(
+                &registry,
+                "Add",
+                "error",
+                json!({ "gains": [1.0, -1.0] }),
+                &[("u1", "r"), ("u2", "x")],
+                &[("y", "e")],
+            )
+            .unwrap()
+            .add_registered_block(
+                &registry,
+                "PID",
+                "pid",
+                json!({ "kp": 0.5, "ki": 0.0, "kd": 0.0, "acc0": 0.0 }),
+                &[("u", "e")],
+                &[("y", "u")],
+            )
+            .unwrap()
+            .add_registered_block(
+                &registry,
+                "Delay",
+                "plant",
+                json!({ "initial_values": [0.0] }),
+                &[("u", "u")],
+                &[("y", "x")],
+            )
+            .unwrap();
+
+        let path = std::env::temp_dir().join(format!(
+            "control_system_blocks_roundtrip_{}.toml",
+            std::process::id()
+        ));
+        std::fs::write(&path, builder.to_config().unwrap()).unwrap();
+        let reloaded = ControlSystemBuilder::from_config(&path, &registry);
+        std::fs::remove_file(&path).unwrap();
+        let reloaded = reloaded.unwrap();
+
+        assert_eq!(reloaded.to_system_config(), builder.to_system_config());
+
+        let simulate = |mut builder: ControlSystemBuilder| {
+            let (probe, values) = Probe::<f64>::new("probe");
+            builder.add_block(probe, &[("u", "x")], &[]).unwrap();
+
+            let mut system = builder.build("loop", params(1.0)).unwrap();
+            for _ in 0..5 {
+                system.step().unwrap();
+            }
+            values.take()
+        };
+        let reloaded = simulate(reloaded);
+        assert_eq!(reloaded.len(), 5);
+        assert_eq!(reloaded, simulate(builder));
+    }
+
+    #[test]
+    fn rejects_invalid_params() {
+        let pid =
+            json!({ "kp": 1.0, "ki": 0.0, "kd": 0.0, "acc0": 0.0, "y_min": 1.0, "y_max": -1.0 });
+        let invalid = [
+            create("PID", pid),
+            create("Delay", json!({ "initial_values": [] })),
+        ];
+
+        for result in invalid {
+            assert!(matches!(
+                result,
+                Err(ControlSystemError::InvalidBlockParams { .. })
+            ));
+        }
+    }
+}
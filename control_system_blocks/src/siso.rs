@@ -1,9 +1,14 @@
 use control_system::{
+    downcast_state, invalid_params,
     io::{Input, Output},
-    Block, BlockIO, ParameterStore, ParameterStoreError, StepInfo, StepResult, Result
+    parse_params, Block, BlockIO, BlockState, ControlSystemError, ParameterStore,
+    ParameterStoreError, Result, StepInfo, StepResult,
 };
-use num::{zero, Float, FromPrimitive};
+use std::{any::Any, collections::VecDeque};
+
+use num::{one, zero, Float, FromPrimitive, Num};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
 
 #[derive(Serialize, Deserialize)]
 pub struct DelayParameters<T> {
@@ -39,26 +44,28 @@ pub struct Delay<T> {
 
     buffer: Vec<T>,
     index: usize,
+    initial_values: Vec<T>,
 }
 
 impl<T> Delay<T>
 where
-    T: Default + 'static,
+    T: Clone + 'static,
 {
     pub fn new(name: &str, params: DelayParameters<T>) -> Self {
         Delay {
             name: name.to_string(),
             u: Input::default(),
             y: Output::default(),
-            buffer: params.initial_values,
+            buffer: params.initial_values.clone(),
             index: 0,
+            initial_values: params.initial_values,
         }
     }
 }
 
 impl<T> Delay<T>
 where
-    T: Default + Serialize + DeserializeOwned + 'static,
+    T: Clone + Serialize + DeserializeOwned + 'static,
 {
     pub fn from_store(
         name: &str,
@@ -91,38 +98,148 @@ where
         Ok(StepResult::Continue)
     }
 
+    fn reset(&mut self) {
+        self.buffer.clone_from(&self.initial_values);
+        self.index = 0;
+    }
+
+    fn save_state(&self) -> Option<BlockState> {
+        Some(Box::new((self.buffer.clone(), self.index)))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) -> Result<()> {
+        let (buffer, index) = downcast_state::<(Vec<T>, usize)>(&self.name, state)?;
+
+        if buffer.len() != self.initial_values.len() || *index >= buffer.len() {
+            return Err(ControlSystemError::InvalidState {
+                blockname: self.name.clone(),
+                reason: format!(
+                    "expected a buffer of {} values and an index within it, got {} values and index {}",
+                    self.initial_values.len(),
+                    buffer.len(),
+                    index
+                ),
+            });
+        }
+
+        self.buffer.clone_from(buffer);
+        self.index = *index;
+        Ok(())
+    }
+
     fn delay(&self) -> u32 {
         self.buffer.len() as u32
     }
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct UnitDelayParams<T> {
+    /// Output on the first step
+    pub initial: T,
+}
+
+impl<T> From<T> for UnitDelayParams<T> {
+    fn from(value: T) -> Self {
+        UnitDelayParams { initial: value }
+    }
+}
 
+/// Delays the input by a single step (z^-1), outputting `initial` on the first step.
+/// Equivalent to a [`Delay`] with a single initial value.
+#[derive(BlockIO)]
+pub struct UnitDelay<T> {
+    #[blockio(block_name)]
+    name: String,
 
+    #[blockio(input)]
+    u: Input<T>,
 
-#[derive(Serialize, Deserialize)]
-pub struct PIDParams<T> {
-    pub kp: T,
-    pub ki: T,
-    pub kd: T,
+    #[blockio(output)]
+    y: Output<T>,
 
-    pub acc0: T,
+    state: T,
+    initial: T,
 }
-impl<T> Default for PIDParams<T>
+
+impl<T> UnitDelay<T>
 where
-    T: Float,
+    T: Clone + 'static,
 {
-    fn default() -> Self {
-        PIDParams {
-            kp: zero(),
-            ki: zero(),
-            kd: zero(),
-            acc0: zero(),
+    pub fn new(name: &str, params: UnitDelayParams<T>) -> Self {
+        UnitDelay {
+            name: name.to_string(),
+            u: Input::default(),
+            y: Output::default(),
+            state: params.initial.clone(),
+            initial: params.initial,
+        }
+    }
+}
+
+impl<T> UnitDelay<T>
+where
+    T: Clone + Serialize + DeserializeOwned + 'static,
+{
+    pub fn from_store(
+        name: &str,
+        store: &mut ParameterStore,
+        default_params: UnitDelayParams<T>,
+    ) -> Result<Self, ParameterStoreError> {
+        let params = store.get_block_params(name, default_params)?;
+
+        Ok(Self::new(name, params))
+    }
+}
+
+impl<T> Block for UnitDelay<T>
+where
+    T: 'static + Clone,
+{
+    fn step(&mut self, k: StepInfo) -> Result<StepResult> {
+        // Blocks with a delay run before the producer of their input, which still
+        // holds the value of the previous step
+        if k.k > 1 {
+            self.state = self.u.get();
         }
+
+        self.y.set(self.state.clone());
+
+        Ok(StepResult::Continue)
     }
+
+    fn reset(&mut self) {
+        self.state = self.initial.clone();
+    }
+
+    fn save_state(&self) -> Option<BlockState> {
+        Some(Box::new(self.state.clone()))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) -> Result<()> {
+        self.state = downcast_state::<T>(&self.name, state)?.clone();
+        Ok(())
+    }
+
+    fn delay(&self) -> u32 {
+        1
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SamplerParams {
+    /// Number of steps between two consecutive samples
+    pub divisor: usize,
 }
 
+impl From<usize> for SamplerParams {
+    fn from(value: usize) -> Self {
+        SamplerParams { divisor: value }
+    }
+}
+
+/// Zero-order hold: samples the input every `divisor` steps and holds it in between
 #[derive(BlockIO)]
-pub struct PID<T> {
+pub struct Sampler<T> {
     #[blockio(block_name)]
     name: String,
 
@@ -132,63 +249,1925 @@ pub struct PID<T> {
     #[blockio(output)]
     y: Output<T>,
 
-    params: PIDParams<T>,
-
-    acc: T,
-    last_err: T,
+    params: SamplerParams,
+    held: Option<T>,
 }
 
-impl<T> PID<T>
+impl<T> Sampler<T>
 where
-    T: Float,
-    Input<T>: Default,
-    Output<T>: Default,
+    T: Clone + 'static,
 {
-    pub fn new(name: &str, params: PIDParams<T>) -> Self {
-        PID {
+    /// Fails if `divisor` is 0
+    pub fn new(name: &str, params: SamplerParams) -> Result<Self> {
+        if params.divisor < 1 {
+            return Err(invalid_params(name, "Sampler divisor must be at least 1"));
+        }
+
+        Ok(Sampler {
             name: name.to_string(),
             u: Input::default(),
             y: Output::default(),
-            acc: params.acc0,
-            last_err: zero(),
             params,
+            held: None,
+        })
+    }
+
+    pub fn from_store(
+        name: &str,
+        store: &mut ParameterStore,
+        default_params: SamplerParams,
+    ) -> Result<Self> {
+        let params = store.get_block_params(name, default_params)?;
+
+        Self::new(name, params)
+    }
+}
+
+impl<T> Block for Sampler<T>
+where
+    T: 'static + Clone,
+{
+    fn step(&mut self, k: StepInfo) -> Result<StepResult> {
+        // Always sample on the first step, so that the output is never empty
+        if self.held.is_none() || k.k.is_multiple_of(self.params.divisor) {
+            self.held = Some(self.u.get());
         }
+
+        self.y.set(self.held.clone().unwrap());
+
+        Ok(StepResult::Continue)
+    }
+
+    fn reset(&mut self) {
+        self.held = None;
+    }
+
+    fn save_state(&self) -> Option<BlockState> {
+        Some(Box::new(self.held.clone()))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) -> Result<()> {
+        self.held = downcast_state::<Option<T>>(&self.name, state)?.clone();
+        Ok(())
+    }
+
+    fn get_params(&self) -> Option<Value> {
+        Some(serde_json::to_value(&self.params).unwrap())
     }
 }
 
-impl<T> PID<T>
+/// Captures the input on each rising edge of `trigger`, and holds it in between.
+/// The input is also captured on the first step, so that the output is never empty.
+#[derive(BlockIO)]
+pub struct SampleHold<T> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u: Input<T>,
+
+    #[blockio(input)]
+    trigger: Input<bool>,
+
+    #[blockio(output)]
+    y: Output<T>,
+
+    held: Option<T>,
+    last_trigger: bool,
+}
+
+impl<T> SampleHold<T>
 where
-    T: Float + Serialize + DeserializeOwned + 'static,
-    Input<T>: Default,
+    T: Clone + 'static,
+{
+    pub fn new(name: &str) -> Self {
+        SampleHold {
+            name: name.to_string(),
+            u: Input::default(),
+            trigger: Input::default(),
+            y: Output::default(),
+            held: None,
+            last_trigger: false,
+        }
+    }
+}
+
+impl<T> Block for SampleHold<T>
+where
+    T: 'static + Clone,
+{
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        let trigger = self.trigger.get();
+
+        if self.held.is_none() || (trigger && !self.last_trigger) {
+            self.held = Some(self.u.get());
+        }
+        self.last_trigger = trigger;
+
+        self.y.set(self.held.clone().unwrap());
+
+        Ok(StepResult::Continue)
+    }
+
+    fn reset(&mut self) {
+        self.held = None;
+        self.last_trigger = false;
+    }
+
+    fn save_state(&self) -> Option<BlockState> {
+        Some(Box::new((self.held.clone(), self.last_trigger)))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) -> Result<()> {
+        (self.held, self.last_trigger) =
+            downcast_state::<(Option<T>, bool)>(&self.name, state)?.clone();
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LowPassParams {
+    /// Cutoff frequency, in Hz
+    pub cutoff_hz: f64,
+}
+
+impl From<f64> for LowPassParams {
+    fn from(value: f64) -> Self {
+        LowPassParams { cutoff_hz: value }
+    }
+}
+
+/// Time constant of a first order filter of type `block` with the cutoff frequency in
+/// `params`, failing if it is not positive
+fn time_constant(name: &str, block: &str, params: &LowPassParams) -> Result<f64> {
+    if params.cutoff_hz.is_nan() || params.cutoff_hz <= 0.0 {
+        return Err(invalid_params(
+            name,
+            &format!("{} cutoff frequency must be positive", block),
+        ));
+    }
+
+    Ok(1.0 / (2.0 * std::f64::consts::PI * params.cutoff_hz))
+}
+
+/// Smoothing coefficient of a first order filter with time constant `rc`, sampled with period `dt`
+fn smoothing_factor<T: Float + FromPrimitive>(rc: f64, dt: f64) -> T {
+    FromPrimitive::from_f64(dt / (rc + dt)).unwrap()
+}
+
+/// First order low-pass filter. The filter coefficient is computed from the step
+/// size on each step, so the cutoff frequency is respected even with a variable dt.
+#[derive(BlockIO)]
+pub struct LowPass<T> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u: Input<T>,
+
+    #[blockio(output)]
+    y: Output<T>,
+
+    /// Time constant, in seconds
+    rc: f64,
+    state: T,
+}
+
+impl<T> LowPass<T>
+where
+    T: Float + 'static,
 {
+    /// Fails if the cutoff frequency is not positive
+    pub fn new(name: &str, params: LowPassParams) -> Result<Self> {
+        Ok(LowPass {
+            name: name.to_string(),
+            u: Input::default(),
+            y: Output::default(),
+            rc: time_constant(name, "LowPass", &params)?,
+            state: zero(),
+        })
+    }
+
     pub fn from_store(
         name: &str,
         store: &mut ParameterStore,
-        default_params: PIDParams<T>,
-    ) -> Result<Self, ParameterStoreError> {
+        default_params: LowPassParams,
+    ) -> Result<Self> {
         let params = store.get_block_params(name, default_params)?;
 
-        Ok(PID::new(name, params))
+        Self::new(name, params)
     }
 }
 
-impl<T> Block for PID<T>
+impl<T> Block for LowPass<T>
 where
-    T: Float + FromPrimitive + 'static + Clone,
+    T: Float + FromPrimitive + 'static,
 {
-    fn step(&mut self, stepinfo: StepInfo) -> Result<StepResult> {
-        let dt: T = FromPrimitive::from_f64(stepinfo.dt).unwrap();
+    fn step(&mut self, k: StepInfo) -> Result<StepResult> {
+        let alpha: T = smoothing_factor(self.rc, k.dt);
 
-        let err = self.u.get();
-        let der = (err - self.last_err) / dt;
-        let int = self.acc + err * dt;
+        self.state = self.state + alpha * (self.u.get() - self.state);
+        self.y.set(self.state);
 
-        self.y
-            .set(err * self.params.kp + der * self.params.kd + int * self.params.ki);
+        Ok(StepResult::Continue)
+    }
 
-        self.last_err = err;
-        self.acc = int;
+    fn reset(&mut self) {
+        self.state = zero();
+    }
+
+    fn save_state(&self) -> Option<BlockState> {
+        Some(Box::new(self.state))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) -> Result<()> {
+        self.state = *downcast_state::<T>(&self.name, state)?;
+        Ok(())
+    }
+}
+
+/// First order high-pass filter, using the same cutoff frequency parametrization and
+/// dt-adaptive coefficient as [`LowPass`]
+#[derive(BlockIO)]
+pub struct HighPass<T> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u: Input<T>,
+
+    #[blockio(output)]
+    y: Output<T>,
+
+    /// Time constant, in seconds
+    rc: f64,
+    state: T,
+    last_u: T,
+}
+
+impl<T> HighPass<T>
+where
+    T: Float + 'static,
+{
+    /// Fails if the cutoff frequency is not positive
+    pub fn new(name: &str, params: LowPassParams) -> Result<Self> {
+        Ok(HighPass {
+            name: name.to_string(),
+            u: Input::default(),
+            y: Output::default(),
+            rc: time_constant(name, "HighPass", &params)?,
+            state: zero(),
+            last_u: zero(),
+        })
+    }
+
+    pub fn from_store(
+        name: &str,
+        store: &mut ParameterStore,
+        default_params: LowPassParams,
+    ) -> Result<Self> {
+        let params = store.get_block_params(name, default_params)?;
+
+        Self::new(name, params)
+    }
+}
+
+impl<T> Block for HighPass<T>
+where
+    T: Float + FromPrimitive + 'static,
+{
+    fn step(&mut self, k: StepInfo) -> Result<StepResult> {
+        let alpha: T = T::one() - smoothing_factor(self.rc, k.dt);
+        let u = self.u.get();
+
+        self.state = alpha * (self.state + u - self.last_u);
+        self.last_u = u;
+        self.y.set(self.state);
 
         Ok(StepResult::Continue)
     }
+
+    fn reset(&mut self) {
+        self.state = zero();
+        self.last_u = zero();
+    }
+
+    fn save_state(&self) -> Option<BlockState> {
+        Some(Box::new((self.state, self.last_u)))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) -> Result<()> {
+        (self.state, self.last_u) = *downcast_state::<(T, T)>(&self.name, state)?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct FirParams<T> {
+    /// Filter taps, starting from the one applied to the current input
+    pub coefficients: Vec<T>,
+}
+
+impl<T> From<Vec<T>> for FirParams<T> {
+    fn from(value: Vec<T>) -> Self {
+        FirParams {
+            coefficients: value,
+        }
+    }
+}
+
+impl<T: Clone, const N: usize> From<[T; N]> for FirParams<T> {
+    fn from(value: [T; N]) -> Self {
+        FirParams {
+            coefficients: value.to_vec(),
+        }
+    }
+}
+
+/// Finite impulse response filter:
+///
+/// y(k) = b0 u(k) + b1 u(k-1) + ... + bn u(k-n)
+///
+/// Past inputs are considered to be zero before the first step. The current input
+/// feeds directly into the output, so this block does not break cycles.
+#[derive(BlockIO)]
+pub struct Fir<T> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u: Input<T>,
+
+    #[blockio(output)]
+    y: Output<T>,
+
+    params: FirParams<T>,
+    history: VecDeque<T>,
+}
+
+impl<T> Fir<T>
+where
+    T: Num + Clone + 'static,
+{
+    /// Fails if there are no coefficients
+    pub fn new(name: &str, params: FirParams<T>) -> Result<Self> {
+        if params.coefficients.is_empty() {
+            return Err(invalid_params(
+                name,
+                "Fir must have at least one coefficient",
+            ));
+        }
+
+        Ok(Fir {
+            name: name.to_string(),
+            u: Input::default(),
+            y: Output::default(),
+            history: vec![zero(); params.coefficients.len()].into(),
+            params,
+        })
+    }
+}
+
+impl<T> Fir<T>
+where
+    T: Num + Clone + Serialize + DeserializeOwned + 'static,
+{
+    pub fn from_store(
+        name: &str,
+        store: &mut ParameterStore,
+        default_params: FirParams<T>,
+    ) -> Result<Self> {
+        let params = store.get_block_params(name, default_params)?;
+
+        Self::new(name, params)
+    }
+}
+
+impl<T> Block for Fir<T>
+where
+    T: Num + Clone + Serialize + DeserializeOwned + 'static,
+{
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        self.history.pop_back();
+        self.history.push_front(self.u.get());
+
+        self.y.set(
+            self.history
+                .iter()
+                .zip(self.params.coefficients.iter())
+                .fold(zero(), |acc: T, (u, b)| acc + u.clone() * b.clone()),
+        );
+
+        Ok(StepResult::Continue)
+    }
+
+    fn reset(&mut self) {
+        self.history.iter_mut().for_each(|u| *u = zero());
+    }
+
+    fn save_state(&self) -> Option<BlockState> {
+        Some(Box::new(self.history.clone()))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) -> Result<()> {
+        let history = downcast_state::<VecDeque<T>>(&self.name, state)?;
+
+        if history.len() != self.params.coefficients.len() {
+            return Err(ControlSystemError::InvalidState {
+                blockname: self.name.clone(),
+                reason: format!(
+                    "expected {} past inputs, got {}",
+                    self.params.coefficients.len(),
+                    history.len()
+                ),
+            });
+        }
+
+        self.history.clone_from(history);
+        Ok(())
+    }
+
+    fn get_params(&self) -> Option<Value> {
+        Some(serde_json::to_value(&self.params).unwrap())
+    }
+}
+
+/// Normalized coefficients of a second order section (a0 = 1)
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct BiquadParams {
+    pub b0: f64,
+    pub b1: f64,
+    pub b2: f64,
+    pub a1: f64,
+    pub a2: f64,
+}
+
+impl BiquadParams {
+    /// Low-pass design with cutoff frequency `cutoff_hz` and quality factor `q`,
+    /// for the filter `name` sampled at `sample_rate_hz`
+    pub fn low_pass(name: &str, cutoff_hz: f64, q: f64, sample_rate_hz: f64) -> Result<Self> {
+        let (cos_w0, alpha) = Self::design_terms(name, cutoff_hz, q, sample_rate_hz)?;
+
+        Ok(Self::normalized(
+            [(1.0 - cos_w0) / 2.0, 1.0 - cos_w0, (1.0 - cos_w0) / 2.0],
+            [1.0 + alpha, -2.0 * cos_w0, 1.0 - alpha],
+        ))
+    }
+
+    /// High-pass design with cutoff frequency `cutoff_hz` and quality factor `q`,
+    /// for the filter `name` sampled at `sample_rate_hz`
+    pub fn high_pass(name: &str, cutoff_hz: f64, q: f64, sample_rate_hz: f64) -> Result<Self> {
+        let (cos_w0, alpha) = Self::design_terms(name, cutoff_hz, q, sample_rate_hz)?;
+
+        Ok(Self::normalized(
+            [(1.0 + cos_w0) / 2.0, -(1.0 + cos_w0), (1.0 + cos_w0) / 2.0],
+            [1.0 + alpha, -2.0 * cos_w0, 1.0 - alpha],
+        ))
+    }
+
+    /// Band-pass design with unit gain at the center frequency `center_hz`
+    /// and quality factor `q`, for the filter `name` sampled at `sample_rate_hz`
+    pub fn band_pass(name: &str, center_hz: f64, q: f64, sample_rate_hz: f64) -> Result<Self> {
+        let (cos_w0, alpha) = Self::design_terms(name, center_hz, q, sample_rate_hz)?;
+
+        Ok(Self::normalized(
+            [alpha, 0.0, -alpha],
+            [1.0 + alpha, -2.0 * cos_w0, 1.0 - alpha],
+        ))
+    }
+
+    /// Fails if the frequency is not between 0 and the Nyquist frequency, or if the
+    /// quality factor is not positive
+    fn design_terms(name: &str, freq_hz: f64, q: f64, sample_rate_hz: f64) -> Result<(f64, f64)> {
+        if !(freq_hz > 0.0 && freq_hz < sample_rate_hz / 2.0) {
+            return Err(invalid_params(
+                name,
+                &format!(
+                    "Biquad frequency must be between 0 and the Nyquist frequency ({} Hz), is {} Hz",
+                    sample_rate_hz / 2.0,
+                    freq_hz
+                ),
+            ));
+        }
+        if q.is_nan() || q <= 0.0 {
+            return Err(invalid_params(
+                name,
+                &format!("Biquad quality factor must be positive, is {}", q),
+            ));
+        }
+
+        let w0 = 2.0 * std::f64::consts::PI * freq_hz / sample_rate_hz;
+        Ok((w0.cos(), w0.sin() / (2.0 * q)))
+    }
+
+    fn normalized(b: [f64; 3], a: [f64; 3]) -> Self {
+        BiquadParams {
+            b0: b[0] / a[0],
+            b1: b[1] / a[0],
+            b2: b[2] / a[0],
+            a1: a[1] / a[0],
+            a2: a[2] / a[0],
+        }
+    }
+}
+
+/// Second order IIR filter, in direct form I:
+///
+/// y(k) = b0 u(k) + b1 u(k-1) + b2 u(k-2) - a1 y(k-1) - a2 y(k-2)
+#[derive(BlockIO)]
+pub struct Biquad<T> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u: Input<T>,
+
+    #[blockio(output)]
+    y: Output<T>,
+
+    b: [T; 3],
+    a: [T; 2],
+
+    u_hist: [T; 2],
+    y_hist: [T; 2],
+}
+
+impl<T> Biquad<T>
+where
+    T: Float + FromPrimitive + 'static,
+{
+    pub fn new(name: &str, params: BiquadParams) -> Self {
+        let c = |v: f64| -> T { FromPrimitive::from_f64(v).unwrap() };
+
+        Biquad {
+            name: name.to_string(),
+            u: Input::default(),
+            y: Output::default(),
+            b: [c(params.b0), c(params.b1), c(params.b2)],
+            a: [c(params.a1), c(params.a2)],
+            u_hist: [zero(); 2],
+            y_hist: [zero(); 2],
+        }
+    }
+
+    pub fn from_store(
+        name: &str,
+        store: &mut ParameterStore,
+        default_params: BiquadParams,
+    ) -> Result<Self, ParameterStoreError> {
+        let params = store.get_block_params(name, default_params)?;
+
+        Ok(Self::new(name, params))
+    }
+}
+
+impl<T> Block for Biquad<T>
+where
+    T: Float + 'static,
+{
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        let u = self.u.get();
+
+        let y = self.b[0] * u + self.b[1] * self.u_hist[0] + self.b[2] * self.u_hist[1]
+            - self.a[0] * self.y_hist[0]
+            - self.a[1] * self.y_hist[1];
+
+        self.u_hist = [u, self.u_hist[0]];
+        self.y_hist = [y, self.y_hist[0]];
+        self.y.set(y);
+
+        Ok(StepResult::Continue)
+    }
+
+    fn reset(&mut self) {
+        self.u_hist = [zero(); 2];
+        self.y_hist = [zero(); 2];
+    }
+
+    fn save_state(&self) -> Option<BlockState> {
+        Some(Box::new((self.u_hist, self.y_hist)))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) -> Result<()> {
+        (self.u_hist, self.y_hist) = *downcast_state::<([T; 2], [T; 2])>(&self.name, state)?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BacklashParams<T> {
+    /// Total width of the dead band
+    pub deadband: T,
+    /// Output before the input first moves past the dead band
+    pub initial: T,
+}
+
+/// Backlash (play) nonlinearity: the output only follows the input once the input
+/// has moved by more than half the dead band, holding its position otherwise
+#[derive(BlockIO)]
+pub struct Backlash<T> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u: Input<T>,
+
+    #[blockio(output)]
+    y: Output<T>,
+
+    half_width: T,
+    position: T,
+    initial: T,
+}
+
+impl<T> Backlash<T>
+where
+    T: Float + 'static,
+{
+    /// Fails if the dead band is negative
+    pub fn new(name: &str, params: BacklashParams<T>) -> Result<Self> {
+        if params.deadband.is_nan() || params.deadband < zero() {
+            return Err(invalid_params(
+                name,
+                "Backlash dead band must not be negative",
+            ));
+        }
+
+        Ok(Backlash {
+            name: name.to_string(),
+            u: Input::default(),
+            y: Output::default(),
+            half_width: params.deadband / (T::one() + T::one()),
+            position: params.initial,
+            initial: params.initial,
+        })
+    }
+}
+
+impl<T> Backlash<T>
+where
+    T: Float + Serialize + DeserializeOwned + 'static,
+{
+    pub fn from_store(
+        name: &str,
+        store: &mut ParameterStore,
+        default_params: BacklashParams<T>,
+    ) -> Result<Self> {
+        let params = store.get_block_params(name, default_params)?;
+
+        Self::new(name, params)
+    }
+}
+
+impl<T> Block for Backlash<T>
+where
+    T: Float + 'static,
+{
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        let u = self.u.get();
+
+        if u > self.position + self.half_width {
+            self.position = u - self.half_width;
+        } else if u < self.position - self.half_width {
+            self.position = u + self.half_width;
+        }
+
+        self.y.set(self.position);
+
+        Ok(StepResult::Continue)
+    }
+
+    fn reset(&mut self) {
+        self.position = self.initial;
+    }
+
+    fn save_state(&self) -> Option<BlockState> {
+        Some(Box::new(self.position))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) -> Result<()> {
+        self.position = *downcast_state::<T>(&self.name, state)?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PIDParams<T> {
+    pub kp: T,
+    pub ki: T,
+    pub kd: T,
+
+    pub acc0: T,
+
+    /// Limits the output is clamped to, if any
+    pub y_min: Option<T>,
+    pub y_max: Option<T>,
+}
+impl<T> Default for PIDParams<T>
+where
+    T: Float,
+{
+    fn default() -> Self {
+        PIDParams {
+            kp: zero(),
+            ki: zero(),
+            kd: zero(),
+            acc0: zero(),
+            y_min: None,
+            y_max: None,
+        }
+    }
+}
+
+impl<T: Float> PIDParams<T> {
+    /// Checks that the output limits of block `name` are consistent
+    fn validate(&self, name: &str) -> Result<()> {
+        if let (Some(y_min), Some(y_max)) = (self.y_min, self.y_max) {
+            if y_min > y_max {
+                return Err(invalid_params(
+                    name,
+                    "PID y_min must not be greater than y_max",
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(BlockIO)]
+pub struct PID<T> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u: Input<T>,
+
+    /// Feedforward term, added to the output before clamping. Zero if unconnected
+    #[blockio(input, optional)]
+    ff: Input<T>,
+
+    #[blockio(output)]
+    y: Output<T>,
+
+    /// Whether the output was clamped to one of the limits in this step
+    #[blockio(output, optional)]
+    y_saturated: Output<bool>,
+
+    params: PIDParams<T>,
+
+    acc: T,
+    last_err: T,
+}
+
+impl<T> PID<T>
+where
+    T: Float,
+    Input<T>: Default,
+    Output<T>: Default,
+{
+    /// Fails if `y_min` is greater than `y_max`
+    pub fn new(name: &str, params: PIDParams<T>) -> Result<Self> {
+        params.validate(name)?;
+
+        Ok(PID {
+            name: name.to_string(),
+            u: Input::default(),
+            ff: Input::with_default(zero()),
+            y: Output::default(),
+            y_saturated: Output::<bool>::default(),
+            acc: params.acc0,
+            last_err: zero(),
+            params,
+        })
+    }
+}
+
+impl<T> PID<T>
+where
+    T: Float + Serialize + DeserializeOwned + 'static,
+    Input<T>: Default,
+{
+    pub fn from_store(
+        name: &str,
+        store: &mut ParameterStore,
+        default_params: PIDParams<T>,
+    ) -> Result<Self> {
+        let params = store.get_block_params(name, default_params)?;
+
+        PID::new(name, params)
+    }
+}
+
+impl<T> Block for PID<T>
+where
+    T: Float + FromPrimitive + 'static + Clone + Serialize + DeserializeOwned,
+{
+    fn step(&mut self, stepinfo: StepInfo) -> Result<StepResult> {
+        let dt: T = FromPrimitive::from_f64(stepinfo.dt).unwrap();
+
+        let err = self.u.get();
+        let der = (err - self.last_err) / dt;
+        let int = self.acc + err * dt;
+
+        let y = err * self.params.kp + der * self.params.kd + int * self.params.ki + self.ff.get();
+        let clamped = match (self.params.y_min, self.params.y_max) {
+            (Some(y_min), _) if y < y_min => y_min,
+            (_, Some(y_max)) if y > y_max => y_max,
+            _ => y,
+        };
+
+        self.y.set(clamped);
+        self.y_saturated.set(clamped != y);
+
+        self.last_err = err;
+        self.acc = int;
+
+        Ok(StepResult::Continue)
+    }
+
+    fn reset(&mut self) {
+        self.acc = self.params.acc0;
+        self.last_err = zero();
+    }
+
+    fn save_state(&self) -> Option<BlockState> {
+        Some(Box::new((self.acc, self.last_err)))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) -> Result<()> {
+        (self.acc, self.last_err) = *downcast_state::<(T, T)>(&self.name, state)?;
+        Ok(())
+    }
+
+    fn get_params(&self) -> Option<Value> {
+        Some(serde_json::to_value(&self.params).unwrap())
+    }
+
+    fn set_params(&mut self, params: Value) -> Result<()> {
+        let params: PIDParams<T> = parse_params(&self.name, params)?;
+        params.validate(&self.name)?;
+
+        self.params = params;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PIDVelocityParams<T> {
+    pub kp: T,
+    pub ki: T,
+    pub kd: T,
+}
+
+impl<T> Default for PIDVelocityParams<T>
+where
+    T: Float,
+{
+    fn default() -> Self {
+        PIDVelocityParams {
+            kp: zero(),
+            ki: zero(),
+            kd: zero(),
+        }
+    }
+}
+
+/// Velocity form of the [`PID`]: outputs the change of the command in each step
+/// instead of the command itself. Summing the output gives the same command as a
+/// [`PID`] with the same gains and no integral initial value.
+#[derive(BlockIO)]
+pub struct PIDVelocity<T> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u: Input<T>,
+
+    #[blockio(output)]
+    y: Output<T>,
+
+    params: PIDVelocityParams<T>,
+
+    /// Errors in the two previous steps
+    last_err: [T; 2],
+}
+
+impl<T> PIDVelocity<T>
+where
+    T: Float,
+    Input<T>: Default,
+    Output<T>: Default,
+{
+    pub fn new(name: &str, params: PIDVelocityParams<T>) -> Self {
+        PIDVelocity {
+            name: name.to_string(),
+            u: Input::default(),
+            y: Output::default(),
+            params,
+            last_err: [zero(); 2],
+        }
+    }
+}
+
+impl<T> PIDVelocity<T>
+where
+    T: Float + Serialize + DeserializeOwned + 'static,
+    Input<T>: Default,
+{
+    pub fn from_store(
+        name: &str,
+        store: &mut ParameterStore,
+        default_params: PIDVelocityParams<T>,
+    ) -> Result<Self, ParameterStoreError> {
+        let params = store.get_block_params(name, default_params)?;
+
+        Ok(PIDVelocity::new(name, params))
+    }
+}
+
+impl<T> Block for PIDVelocity<T>
+where
+    T: Float + FromPrimitive + Serialize + DeserializeOwned + 'static,
+{
+    fn step(&mut self, stepinfo: StepInfo) -> Result<StepResult> {
+        let dt: T = FromPrimitive::from_f64(stepinfo.dt).unwrap();
+        let two: T = FromPrimitive::from_f64(2.0).unwrap();
+
+        let err = self.u.get();
+        let [e1, e2] = self.last_err;
+
+        let p = err - e1;
+        let i = err * dt;
+        let d = (err - e1 * two + e2) / dt;
+
+        self.y
+            .set(p * self.params.kp + i * self.params.ki + d * self.params.kd);
+
+        self.last_err = [err, e1];
+
+        Ok(StepResult::Continue)
+    }
+
+    fn reset(&mut self) {
+        self.last_err = [zero(); 2];
+    }
+
+    fn save_state(&self) -> Option<BlockState> {
+        Some(Box::new(self.last_err))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) -> Result<()> {
+        self.last_err = *downcast_state::<[T; 2]>(&self.name, state)?;
+        Ok(())
+    }
+
+    fn get_params(&self) -> Option<Value> {
+        Some(serde_json::to_value(&self.params).unwrap())
+    }
+
+    fn set_params(&mut self, params: Value) -> Result<()> {
+        self.params = parse_params(&self.name, params)?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PID2Params<T> {
+    pub kp: T,
+    pub ki: T,
+    pub kd: T,
+
+    pub acc0: T,
+
+    /// Weight of the reference in the proportional term
+    pub beta: T,
+    /// Weight of the reference in the derivative term
+    pub gamma: T,
+}
+
+impl<T> Default for PID2Params<T>
+where
+    T: Float,
+{
+    fn default() -> Self {
+        PID2Params {
+            kp: zero(),
+            ki: zero(),
+            kd: zero(),
+            acc0: zero(),
+            beta: one(),
+            gamma: one(),
+        }
+    }
+}
+
+/// [`PID`] computing the error from the reference `r` and the measurement `y_meas`,
+/// with setpoint weighting on the proportional and derivative terms. With `beta` and
+/// `gamma` equal to 1 it behaves like a [`PID`] fed with `r - y_meas`.
+#[derive(BlockIO)]
+pub struct PID2<T> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    r: Input<T>,
+
+    #[blockio(input)]
+    y_meas: Input<T>,
+
+    #[blockio(output)]
+    y: Output<T>,
+
+    params: PID2Params<T>,
+
+    acc: T,
+    /// Error of the derivative term in the previous step
+    last_d_err: T,
+}
+
+impl<T> PID2<T>
+where
+    T: Float,
+    Input<T>: Default,
+    Output<T>: Default,
+{
+    pub fn new(name: &str, params: PID2Params<T>) -> Self {
+        PID2 {
+            name: name.to_string(),
+            r: Input::default(),
+            y_meas: Input::default(),
+            y: Output::default(),
+            acc: params.acc0,
+            last_d_err: zero(),
+            params,
+        }
+    }
+}
+
+impl<T> PID2<T>
+where
+    T: Float + Serialize + DeserializeOwned + 'static,
+    Input<T>: Default,
+{
+    pub fn from_store(
+        name: &str,
+        store: &mut ParameterStore,
+        default_params: PID2Params<T>,
+    ) -> Result<Self, ParameterStoreError> {
+        let params = store.get_block_params(name, default_params)?;
+
+        Ok(PID2::new(name, params))
+    }
+}
+
+impl<T> Block for PID2<T>
+where
+    T: Float + FromPrimitive + Serialize + DeserializeOwned + 'static,
+{
+    fn step(&mut self, stepinfo: StepInfo) -> Result<StepResult> {
+        let dt: T = FromPrimitive::from_f64(stepinfo.dt).unwrap();
+
+        let r = self.r.get();
+        let y_meas = self.y_meas.get();
+
+        let p_err = self.params.beta * r - y_meas;
+        let d_err = self.params.gamma * r - y_meas;
+
+        let der = (d_err - self.last_d_err) / dt;
+        let int = self.acc + (r - y_meas) * dt;
+
+        self.y
+            .set(p_err * self.params.kp + der * self.params.kd + int * self.params.ki);
+
+        self.last_d_err = d_err;
+        self.acc = int;
+
+        Ok(StepResult::Continue)
+    }
+
+    fn reset(&mut self) {
+        self.acc = self.params.acc0;
+        self.last_d_err = zero();
+    }
+
+    fn save_state(&self) -> Option<BlockState> {
+        Some(Box::new((self.acc, self.last_d_err)))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) -> Result<()> {
+        (self.acc, self.last_d_err) = *downcast_state::<(T, T)>(&self.name, state)?;
+        Ok(())
+    }
+
+    fn get_params(&self) -> Option<Value> {
+        Some(serde_json::to_value(&self.params).unwrap())
+    }
+
+    fn set_params(&mut self, params: Value) -> Result<()> {
+        self.params = parse_params(&self.name, params)?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SaturationParams<T> {
+    pub min: T,
+    pub max: T,
+}
+
+impl<T: Float> SaturationParams<T> {
+    /// Checks that the limits of block `name` are consistent
+    fn validate(&self, name: &str) -> Result<()> {
+        if self.min > self.max {
+            return Err(invalid_params(
+                name,
+                "Saturation min must not be greater than max",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Clamps the input between `min` and `max`
+#[derive(BlockIO)]
+pub struct Saturation<T> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u: Input<T>,
+
+    #[blockio(output)]
+    y: Output<T>,
+
+    /// Part of the input cut by the limits, `u - y`, eg. for back-calculation
+    /// anti-windup
+    #[blockio(output, optional)]
+    residual: Output<T>,
+
+    params: SaturationParams<T>,
+}
+
+impl<T> Saturation<T>
+where
+    T: Float,
+    Output<T>: Default,
+{
+    /// Fails if `min` is greater than `max`
+    pub fn new(name: &str, params: SaturationParams<T>) -> Result<Self> {
+        params.validate(name)?;
+
+        Ok(Saturation {
+            name: name.to_string(),
+            u: Input::default(),
+            y: Output::default(),
+            residual: Output::default(),
+            params,
+        })
+    }
+}
+
+impl<T> Saturation<T>
+where
+    T: Float + Serialize + DeserializeOwned,
+    Output<T>: Default,
+{
+    pub fn from_store(
+        name: &str,
+        store: &mut ParameterStore,
+        default_params: SaturationParams<T>,
+    ) -> Result<Self> {
+        let params = store.get_block_params(name, default_params)?;
+
+        Saturation::new(name, params)
+    }
+}
+
+impl<T> Block for Saturation<T>
+where
+    T: Float + Serialize + DeserializeOwned + 'static,
+{
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        let u = self.u.get();
+        let y = u.max(self.params.min).min(self.params.max);
+
+        self.y.set(y);
+        self.residual.set(u - y);
+
+        Ok(StepResult::Continue)
+    }
+
+    fn get_params(&self) -> Option<Value> {
+        Some(serde_json::to_value(&self.params).unwrap())
+    }
+
+    fn set_params(&mut self, params: Value) -> Result<()> {
+        let params: SaturationParams<T> = parse_params(&self.name, params)?;
+        params.validate(&self.name)?;
+
+        self.params = params;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::{Cell, RefCell},
+        rc::Rc,
+    };
+
+    use super::*;
+    use crate::{
+        math::Add,
+        producers::{Constant, Generator},
+        test_utils::{add_sequence, params, response, Probe},
+    };
+    use control_system::{ControlSystem, ControlSystemBuilder, ControlSystemError};
+
+    #[test]
+    fn sampler_holds_input_between_samples() {
+        let sampler = Sampler::<f64>::new("sampler", 3.into()).unwrap();
+
+        let y = response(sampler, 1.0, 9, |t| t);
+        assert_eq!(y, [0.0, 0.0, 2.0, 2.0, 2.0, 5.0, 5.0, 5.0, 8.0]);
+    }
+
+    #[test]
+    fn low_pass_step_response() {
+        let cutoff_hz = 2.0;
+        let tau = 1.0 / (2.0 * std::f64::consts::PI * cutoff_hz);
+        let dt = 1e-4;
+        let steps = (tau / dt).round() as usize;
+
+        let low_pass = LowPass::<f64>::new("lp", cutoff_hz.into()).unwrap();
+        let y = response(low_pass, dt, 10 * steps, |_| 1.0);
+
+        // After one time constant
+        assert!(
+            (y[steps - 1] - (1.0 - (-1.0f64).exp())).abs() < 1e-3,
+            "{}",
+            y[steps - 1]
+        );
+        assert!((y[10 * steps - 1] - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn high_pass_rejects_dc() {
+        let high_pass = HighPass::<f64>::new("hp", 2.0.into()).unwrap();
+        let y = response(high_pass, 1e-4, 20000, |_| 1.0);
+
+        // The step passes through, then decays with the time constant of the filter
+        assert!((y[0] - 1.0).abs() < 1e-2, "{}", y[0]);
+        assert!(y.windows(2).all(|w| w[1] < w[0]));
+        assert!(y[19999].abs() < 1e-6, "{}", y[19999]);
+    }
+
+    #[test]
+    fn fir_matches_convolution() {
+        let coefficients = [0.5, -0.25, 1.0, 0.125];
+        let input = |t: f64| (1.3 * t).sin() + 0.1 * t;
+        let fir = Fir::new("fir", coefficients.into()).unwrap();
+
+        let y = response(fir, 1.0, 12, input);
+
+        for (k, y) in y.iter().enumerate() {
+            let expected: f64 = coefficients
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j <= k)
+                .map(|(j, b)| b * input((k - j) as f64))
+                .sum();
+            assert!((y - expected).abs() < 1e-12, "step {k}: {y} != {expected}");
+        }
+    }
+
+    /// Steady state gain of `params` at DC and at the Nyquist frequency, measured
+    /// feeding a constant and an alternating input
+    fn biquad_gains(params: BiquadParams) -> (f64, f64) {
+        let dc = response(Biquad::<f64>::new("dc", params), 1.0, 2000, |_| 1.0);
+        let nyquist = response(Biquad::<f64>::new("nyquist", params), 1.0, 2000, |t| {
+            if (t as usize).is_multiple_of(2) {
+                1.0
+            } else {
+                -1.0
+            }
+        });
+
+        (dc[1999], nyquist[1999].abs())
+    }
+
+    #[test]
+    fn biquad_gain_at_dc_and_nyquist() {
+        let cases = [
+            (
+                BiquadParams::low_pass("biquad", 10.0, 0.7, 100.0).unwrap(),
+                (1.0, 0.0),
+            ),
+            (
+                BiquadParams::high_pass("biquad", 10.0, 0.7, 100.0).unwrap(),
+                (0.0, 1.0),
+            ),
+            (
+                BiquadParams::band_pass("biquad", 10.0, 0.7, 100.0).unwrap(),
+                (0.0, 0.0),
+            ),
+        ];
+
+        for (p, (dc, nyquist)) in cases {
+            // H(z) evaluated in z = 1 and z = -1
+            let h_dc = (p.b0 + p.b1 + p.b2) / (1.0 + p.a1 + p.a2);
+            let h_nyquist = (p.b0 - p.b1 + p.b2) / (1.0 - p.a1 + p.a2);
+            assert!((h_dc - dc).abs() < 1e-12, "{p:?}");
+            assert!((h_nyquist.abs() - nyquist).abs() < 1e-12, "{p:?}");
+
+            let (measured_dc, measured_nyquist) = biquad_gains(p);
+            assert!((measured_dc - dc).abs() < 1e-9, "{p:?}: {measured_dc}");
+            assert!(
+                (measured_nyquist - nyquist).abs() < 1e-9,
+                "{p:?}: {measured_nyquist}"
+            );
+        }
+    }
+
+    #[test]
+    fn backlash_lags_at_reversals() {
+        let params = BacklashParams {
+            deadband: 2.0,
+            initial: 0.0,
+        };
+        let backlash = Backlash::new("backlash", params).unwrap();
+
+        // Up to 4 and back down to 0
+        let y = response(backlash, 1.0, 9, |t| 4.0 - (t - 4.0).abs());
+        assert_eq!(y, [0.0, 0.0, 1.0, 2.0, 3.0, 3.0, 3.0, 2.0, 1.0]);
+    }
+
+    #[test]
+    fn unit_delay_in_feedback_loop() {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(
+                UnitDelay::new("delay", 0.0.into()),
+                &[("u", "x")],
+                &[("y", "x_prev")],
+            )
+            .unwrap();
+        builder
+            .add_block(Constant::new("one", 1.0.into()), &[], &[("y", "one")])
+            .unwrap();
+        builder
+            .add_block(
+                Add::<f64, 2>::new("increment", [1.0, 1.0].into()),
+                &[("u1", "x_prev"), ("u2", "one")],
+                &[("y", "x")],
+            )
+            .unwrap();
+
+        let (probe_x, x) = Probe::<f64>::new("probe_x");
+        let (probe_x_prev, x_prev) = Probe::<f64>::new("probe_x_prev");
+        builder.add_block(probe_x, &[("u", "x")], &[]).unwrap();
+        builder
+            .add_block(probe_x_prev, &[("u", "x_prev")], &[])
+            .unwrap();
+
+        let mut system = builder.build("test", params(1.0)).unwrap();
+        for _ in 0..4 {
+            system.step().unwrap();
+        }
+
+        assert_eq!(*x.borrow(), [1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(*x_prev.borrow(), [0.0, 1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn pid_saturation_flag() {
+        let pid_params = PIDParams {
+            kp: 1.0,
+            y_min: Some(-1.0),
+            y_max: Some(1.0),
+            ..Default::default()
+        };
+        let errors = [-2.0, -0.5, 0.5, 1.0, 2.0, 0.0];
+        let k = Cell::new(0);
+        let (y, y_values) = Probe::<f64>::new("y");
+        let (saturated, saturated_values) = Probe::<bool>::new("saturated");
+
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(
+                Generator::new("error", move || {
+                    k.set(k.get() + 1);
+                    errors[k.get() - 1]
+                }),
+                &[],
+                &[("y", "e")],
+            )
+            .unwrap();
+        builder
+            .add_block(
+                PID::new("pid", pid_params).unwrap(),
+                &[("u", "e")],
+                &[("y", "y"), ("y_saturated", "saturated")],
+            )
+            .unwrap();
+        builder.add_block(y, &[("u", "y")], &[]).unwrap();
+        builder
+            .add_block(saturated, &[("u", "saturated")], &[])
+            .unwrap();
+
+        let mut system = builder.build("test", params(1.0)).unwrap();
+        for _ in 0..6 {
+            system.step().unwrap();
+        }
+
+        assert_eq!(*y_values.borrow(), [-1.0, -0.5, 0.5, 1.0, 1.0, 0.0]);
+        assert_eq!(
+            *saturated_values.borrow(),
+            [true, false, false, false, true, false]
+        );
+    }
+
+    #[test]
+    fn pid_velocity_sums_to_pid() {
+        let input = |t: f64| t.sin() + 0.5;
+        let pid = PID::new(
+            "pid",
+            PIDParams {
+                kp: 2.0,
+                ki: 0.5,
+                kd: 0.1,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let velocity = PIDVelocity::new(
+            "pid",
+            PIDVelocityParams {
+                kp: 2.0,
+                ki: 0.5,
+                kd: 0.1,
+            },
+        );
+
+        let y = response(pid, 0.1, 50, input);
+        let dy = response(velocity, 0.1, 50, input);
+
+        let mut sum = 0.0;
+        for (y, dy) in y.iter().zip(dy) {
+            sum += dy;
+            assert!((sum - y).abs() < 1e-9, "{sum} != {y}");
+        }
+    }
+
+    #[test]
+    fn pid_feedforward_offsets_output() {
+        let pid_params = || PIDParams {
+            kp: 2.0,
+            ki: 0.5,
+            ..Default::default()
+        };
+        let without_ff = response(PID::new("pid", pid_params()).unwrap(), 0.1, 20, f64::sin);
+
+        let k = Cell::new(0);
+        let (probe, with_ff) = Probe::<f64>::new("probe");
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(
+                Generator::new("input", move || {
+                    k.set(k.get() + 1);
+                    ((k.get() - 1) as f64 * 0.1).sin()
+                }),
+                &[],
+                &[("y", "u")],
+            )
+            .unwrap();
+        builder
+            .add_block(Constant::new("ff", 3.0.into()), &[], &[("y", "ff")])
+            .unwrap();
+        builder
+            .add_block(
+                PID::new("pid", pid_params()).unwrap(),
+                &[("u", "u"), ("ff", "ff")],
+                &[("y", "y")],
+            )
+            .unwrap();
+        builder.add_block(probe, &[("u", "y")], &[]).unwrap();
+
+        let mut system = builder.build("test", params(0.1)).unwrap();
+        for _ in 0..20 {
+            system.step().unwrap();
+        }
+
+        assert_eq!(with_ff.borrow().len(), without_ff.len());
+        for (with_ff, without_ff) in with_ff.borrow().iter().zip(without_ff) {
+            assert!((with_ff - without_ff - 3.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn pid2_matches_add_and_pid() {
+        let k_r = Cell::new(0);
+        let k_y = Cell::new(0);
+        let (probe_pid, y_pid) = Probe::<f64>::new("probe_pid");
+        let (probe_pid2, y_pid2) = Probe::<f64>::new("probe_pid2");
+
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(
+                Generator::new("reference", move || {
+                    k_r.set(k_r.get() + 1);
+                    ((k_r.get() - 1) as f64 * 0.1).sin()
+                }),
+                &[],
+                &[("y", "r")],
+            )
+            .unwrap();
+        builder
+            .add_block(
+                Generator::new("measurement", move || {
+                    k_y.set(k_y.get() + 1);
+                    0.5 * (k_y.get() - 1) as f64 * 0.1
+                }),
+                &[],
+                &[("y", "y_meas")],
+            )
+            .unwrap();
+
+        builder
+            .add_block(
+                Add::<f64, 2>::new("error", [1.0, -1.0].into()),
+                &[("u1", "r"), ("u2", "y_meas")],
+                &[("y", "e")],
+            )
+            .unwrap();
+        let pid = PIDParams {
+            kp: 2.0,
+            ki: 0.5,
+            kd: 0.1,
+            ..Default::default()
+        };
+        builder
+            .add_block(
+                PID::new("pid", pid).unwrap(),
+                &[("u", "e")],
+                &[("y", "y_pid")],
+            )
+            .unwrap();
+
+        let pid2 = PID2Params {
+            kp: 2.0,
+            ki: 0.5,
+            kd: 0.1,
+            ..Default::default()
+        };
+        builder
+            .add_block(
+                PID2::new("pid2", pid2),
+                &[("r", "r"), ("y_meas", "y_meas")],
+                &[("y", "y_pid2")],
+            )
+            .unwrap();
+        builder
+            .add_block(probe_pid, &[("u", "y_pid")], &[])
+            .unwrap();
+        builder
+            .add_block(probe_pid2, &[("u", "y_pid2")], &[])
+            .unwrap();
+
+        let mut system = builder.build("test", params(0.1)).unwrap();
+        for _ in 0..50 {
+            system.step().unwrap();
+        }
+
+        assert_eq!(y_pid.borrow().len(), 50);
+        for (y_pid, y_pid2) in y_pid.borrow().iter().zip(y_pid2.borrow().iter()) {
+            assert!((y_pid - y_pid2).abs() < 1e-9, "{y_pid} != {y_pid2}");
+        }
+    }
+
+    #[test]
+    fn saturation_residual() {
+        let (y, y_values) = Probe::<f64>::new("probe_y");
+        let (residual, residual_values) = Probe::<f64>::new("probe_residual");
+
+        let mut builder = ControlSystemBuilder::default();
+        add_sequence(&mut builder, "u", &[-3.0, -1.0, 0.5, 1.0, 2.5]);
+        builder
+            .add_block(
+                Saturation::new(
+                    "sat",
+                    SaturationParams {
+                        min: -1.0,
+                        max: 1.0,
+                    },
+                )
+                .unwrap(),
+                &[("u", "u")],
+                &[("y", "y"), ("residual", "residual")],
+            )
+            .unwrap();
+        builder.add_block(y, &[("u", "y")], &[]).unwrap();
+        builder
+            .add_block(residual, &[("u", "residual")], &[])
+            .unwrap();
+
+        let mut system = builder.build("test", params(1.0)).unwrap();
+        for _ in 0..5 {
+            system.step().unwrap();
+        }
+
+        assert_eq!(*y_values.borrow(), [-1.0, -1.0, 0.5, 1.0, 1.0]);
+        assert_eq!(*residual_values.borrow(), [-2.0, 0.0, 0.0, 0.0, 1.5]);
+    }
+
+    #[test]
+    fn two_step_delay_in_feedback_loop() {
+        let (x, x_values) = Probe::<f64>::new("probe_x");
+        let (y, y_values) = Probe::<f64>::new("probe_y");
+
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(
+                Delay::new("delay", [10.0, 20.0].into()),
+                &[("u", "y")],
+                &[("y", "x")],
+            )
+            .unwrap();
+        // y = 2x + 1
+        builder
+            .add_block(Constant::new("one", 1.0.into()), &[], &[("y", "one")])
+            .unwrap();
+        builder
+            .add_block(
+                Add::<f64, 2>::new("plant", [2.0, 1.0].into()),
+                &[("u1", "x"), ("u2", "one")],
+                &[("y", "y")],
+            )
+            .unwrap();
+        builder.add_block(x, &[("u", "x")], &[]).unwrap();
+        builder.add_block(y, &[("u", "y")], &[]).unwrap();
+
+        let mut system = builder.build("test", params(1.0)).unwrap();
+        for _ in 0..6 {
+            system.step().unwrap();
+        }
+
+        // x[k] = y[k - 2], starting from the initial values
+        assert_eq!(*x_values.borrow(), [10.0, 20.0, 21.0, 41.0, 43.0, 83.0]);
+        assert_eq!(*y_values.borrow(), [21.0, 41.0, 43.0, 83.0, 87.0, 167.0]);
+    }
+
+    #[test]
+    fn delay_runs_before_its_producer() {
+        let (probe, values) = Probe::<f64>::new("probe");
+
+        let mut builder = ControlSystemBuilder::default();
+        add_sequence(&mut builder, "u", &[1.0, 2.0, 3.0, 4.0]);
+        // Named to sort before the producer of its input
+        builder
+            .add_block(
+                UnitDelay::new("a_delay", UnitDelayParams { initial: 0.0 }),
+                &[("u", "u")],
+                &[("y", "y")],
+            )
+            .unwrap();
+        builder.add_block(probe, &[("u", "y")], &[]).unwrap();
+
+        let mut system = builder.build("test", params(1.0)).unwrap();
+        for _ in 0..4 {
+            system.step().unwrap();
+        }
+
+        assert_eq!(*values.borrow(), [0.0, 1.0, 2.0, 3.0]);
+    }
+
+    /// System with a [`PID`] named "pid" with the parameters `pid_params`, whose input
+    /// is a constant error of 1, along with the values of its output
+    fn pid_system(pid_params: PIDParams<f64>) -> (ControlSystem, Rc<RefCell<Vec<f64>>>) {
+        let (probe, values) = Probe::new("probe");
+
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Constant::new("error", 1.0.into()), &[], &[("y", "e")])
+            .unwrap();
+        builder
+            .add_block(
+                PID::new("pid", pid_params).unwrap(),
+                &[("u", "e")],
+                &[("y", "y")],
+            )
+            .unwrap();
+        builder.add_block(probe, &[("u", "y")], &[]).unwrap();
+
+        (builder.build("test", params(1.0)).unwrap(), values)
+    }
+
+    /// Output values of the next `steps` steps of `system`
+    fn run(system: &mut ControlSystem, values: &RefCell<Vec<f64>>, steps: usize) -> Vec<f64> {
+        for _ in 0..steps {
+            system.step().unwrap();
+        }
+        values.take()
+    }
+
+    #[test]
+    fn pid_params_at_runtime() {
+        let (system, _) = pid_system(PIDParams {
+            kp: 2.0,
+            ..Default::default()
+        });
+
+        let params = system.block_params("pid").unwrap().unwrap();
+        assert_eq!(params["kp"], 2.0);
+        assert_eq!(params["ki"], 0.0);
+
+        assert_eq!(system.block_params("error").unwrap(), None);
+        assert!(matches!(
+            system.block_params("missing"),
+            Err(ControlSystemError::UnknownBlock(_))
+        ));
+    }
+
+    #[test]
+    fn pid_retuned_mid_run() {
+        let pid_params = |ki| PIDParams {
+            ki,
+            ..Default::default()
+        };
+        let (mut system, values) = pid_system(pid_params(1.0));
+        assert_eq!(run(&mut system, &values, 3), [1.0, 2.0, 3.0]);
+
+        // The integral keeps accumulating, now with the new gain
+        let retuned = serde_json::to_value(pid_params(2.0)).unwrap();
+        system.set_block_params("pid", retuned.clone()).unwrap();
+        assert_eq!(run(&mut system, &values, 2), [8.0, 10.0]);
+
+        assert!(matches!(
+            system.set_block_params("missing", retuned),
+            Err(ControlSystemError::UnknownBlock(name)) if name == "missing"
+        ));
+        assert!(system
+            .set_block_params("pid", serde_json::json!({ "kp": "fast" }))
+            .is_err());
+        assert_eq!(run(&mut system, &values, 1), [12.0]);
+    }
+
+    #[test]
+    fn sample_hold_on_rising_edges() {
+        let trigger = [false, true, true, false, true, false, false, true];
+        let (probe, values) = Probe::<f64>::new("probe");
+
+        let mut builder = ControlSystemBuilder::default();
+        add_sequence(&mut builder, "u", &[0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]);
+        add_sequence(&mut builder, "trigger", &trigger);
+        builder
+            .add_block(
+                SampleHold::<f64>::new("hold"),
+                &[("u", "u"), ("trigger", "trigger")],
+                &[("y", "y")],
+            )
+            .unwrap();
+        builder.add_block(probe, &[("u", "y")], &[]).unwrap();
+
+        let mut system = builder.build("test", params(1.0)).unwrap();
+        for _ in 0..trigger.len() {
+            system.step().unwrap();
+        }
+
+        // The first step samples the input even without an edge
+        assert_eq!(*values.borrow(), [0.0, 1.0, 1.0, 1.0, 4.0, 4.0, 4.0, 7.0]);
+    }
+
+    /// Signal type without serde support
+    #[derive(Clone, Debug, PartialEq)]
+    struct Point {
+        x: i32,
+    }
+
+    #[test]
+    fn delay_state_roundtrip_without_serde() {
+        let mut delay = Delay::new("delay", vec![Point { x: 1 }, Point { x: 2 }].into());
+        let state = delay.save_state().unwrap();
+
+        delay.buffer[0] = Point { x: 5 };
+        delay.index = 1;
+        delay.load_state(state.as_ref()).unwrap();
+
+        assert_eq!(delay.buffer, vec![Point { x: 1 }, Point { x: 2 }]);
+        assert_eq!(delay.index, 0);
+    }
+
+    #[test]
+    fn delay_rejects_mismatched_state() {
+        let mut delay = Delay::new("delay", vec![1.0, 2.0].into());
+
+        assert!(delay.load_state(&(vec![1.0], 0usize)).is_err());
+        assert!(delay.load_state(&(vec![1.0, 2.0], 2usize)).is_err());
+        assert!(delay.load_state(&1.0).is_err());
+    }
+
+    #[test]
+    fn sampler_rejects_zero_divisor() {
+        assert!(Sampler::<f64>::new("sampler", 1.into()).is_ok());
+        assert!(matches!(
+            Sampler::<f64>::new("sampler", 0.into()),
+            Err(ControlSystemError::InvalidBlockParams { .. })
+        ));
+    }
+
+    #[test]
+    fn low_pass_rejects_invalid_cutoff() {
+        assert!(LowPass::<f64>::new("lp", 1.0.into()).is_ok());
+
+        for cutoff in [0.0, -1.0, f64::NAN] {
+            assert!(matches!(
+                LowPass::<f64>::new("lp", cutoff.into()),
+                Err(ControlSystemError::InvalidBlockParams { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn high_pass_rejects_invalid_cutoff() {
+        assert!(HighPass::<f64>::new("hp", 1.0.into()).is_ok());
+        assert!(HighPass::<f64>::new("hp", 0.0.into()).is_err());
+    }
+
+    #[test]
+    fn fir_rejects_empty_coefficients() {
+        assert!(Fir::<f64>::new("fir", [1.0].into()).is_ok());
+        assert!(matches!(
+            Fir::<f64>::new("fir", Vec::new().into()),
+            Err(ControlSystemError::InvalidBlockParams { .. })
+        ));
+    }
+
+    #[test]
+    fn biquad_design_rejects_invalid_frequency() {
+        assert!(BiquadParams::low_pass("biquad", 10.0, 0.7, 100.0).is_ok());
+
+        for params in [
+            BiquadParams::low_pass("biquad", 60.0, 0.7, 100.0),
+            BiquadParams::high_pass("biquad", 0.0, 0.7, 100.0),
+            BiquadParams::band_pass("biquad", 10.0, 0.0, 100.0),
+            BiquadParams::band_pass("biquad", f64::NAN, 0.7, 100.0),
+        ] {
+            assert!(matches!(
+                params,
+                Err(ControlSystemError::InvalidBlockParams { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn backlash_rejects_negative_deadband() {
+        let params = |deadband| BacklashParams {
+            deadband,
+            initial: 0.0,
+        };
+
+        assert!(Backlash::new("backlash", params(0.0)).is_ok());
+        assert!(matches!(
+            Backlash::new("backlash", params(-1.0)),
+            Err(ControlSystemError::InvalidBlockParams { .. })
+        ));
+    }
+
+    #[test]
+    fn pid_rejects_inverted_limits() {
+        let params = |y_min, y_max| PIDParams {
+            kp: 1.0,
+            y_min: Some(y_min),
+            y_max: Some(y_max),
+            ..Default::default()
+        };
+
+        assert!(PID::new("pid", params(-1.0, 1.0)).is_ok());
+        assert!(matches!(
+            PID::new("pid", params(1.0, -1.0)),
+            Err(ControlSystemError::InvalidBlockParams { .. })
+        ));
+
+        let mut pid = PID::new("pid", params(-1.0, 1.0)).unwrap();
+        let inverted = serde_json::to_value(params(1.0, -1.0)).unwrap();
+        assert!(pid.set_params(inverted).is_err());
+        assert_eq!(pid.params.y_max, Some(1.0));
+    }
+
+    #[test]
+    fn saturation_rejects_inverted_limits() {
+        assert!(matches!(
+            Saturation::new("sat", SaturationParams { min: 1.0, max: 0.0 }),
+            Err(ControlSystemError::InvalidBlockParams { .. })
+        ));
+    }
+
+    #[test]
+    fn stateless_block_rejects_state() {
+        let mut sat = Saturation::new("sat", SaturationParams { min: 0.0, max: 1.0 }).unwrap();
+
+        assert!(sat.save_state().is_none());
+        assert!(sat.load_state(&1.0).is_err());
+    }
 }
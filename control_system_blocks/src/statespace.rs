@@ -0,0 +1,1153 @@
+use std::{any::Any, marker::PhantomData};
+
+use control_system::{
+    downcast_state, invalid_params,
+    io::{Input, Output},
+    numeric::ode::{ODESolver, RungeKutta4},
+    Block, BlockIO, BlockState, ControlSystemError, ParameterStore, ParameterStoreError, Result,
+    StepInfo, StepResult,
+};
+use nalgebra::{DMatrix, DVector, SMatrix, SVector};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct StateSpaceParams {
+    pub a: DMatrix<f64>,
+    pub b: DMatrix<f64>,
+    pub c: DMatrix<f64>,
+    pub d: DMatrix<f64>,
+
+    /// Initial state
+    pub x0: DVector<f64>,
+}
+
+impl StateSpaceParams {
+    /// Checks that the matrices of block `name` describe a consistent system
+    fn validate(&self, name: &str) -> Result<()> {
+        let n = self.a.nrows();
+        let m = self.b.ncols();
+        let p = self.c.nrows();
+
+        check_shape(name, "A", self.a.shape(), (n, n))?;
+        check_shape(name, "B", self.b.shape(), (n, m))?;
+        check_shape(name, "C", self.c.shape(), (p, n))?;
+        check_shape(name, "D", self.d.shape(), (p, m))?;
+        check_len(name, "x0", self.x0.len(), n)
+    }
+}
+
+/// Discrete-time linear system:
+///
+/// x(k+1) = A x(k) + B u(k)
+/// y(k) = C x(k) + D u(k)
+#[derive(BlockIO)]
+pub struct StateSpace {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u: Input<DVector<f64>>,
+
+    #[blockio(output)]
+    y: Output<DVector<f64>>,
+
+    params: StateSpaceParams,
+    x: DVector<f64>,
+}
+
+impl StateSpace {
+    /// Fails if the dimensions of the matrices and of the initial state don't match
+    pub fn new(name: &str, params: StateSpaceParams) -> Result<Self> {
+        params.validate(name)?;
+
+        Ok(StateSpace {
+            name: name.to_string(),
+            u: Input::default(),
+            y: Output::default(),
+            x: params.x0.clone(),
+            params,
+        })
+    }
+
+    pub fn from_store(
+        name: &str,
+        store: &mut ParameterStore,
+        default_params: StateSpaceParams,
+    ) -> Result<Self> {
+        let params = store.get_block_params(name, default_params)?;
+
+        Self::new(name, params)
+    }
+}
+
+impl Block for StateSpace {
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        let u = self.u.get();
+        let m = self.params.b.ncols();
+
+        if u.len() != m {
+            return Err(ControlSystemError::Other(
+                format!(
+                    "Block '{}': B has {} columns, the input has {} elements",
+                    self.name,
+                    m,
+                    u.len()
+                )
+                .into(),
+            ));
+        }
+
+        self.y.set(&self.params.c * &self.x + &self.params.d * &u);
+        self.x = &self.params.a * &self.x + &self.params.b * &u;
+
+        Ok(StepResult::Continue)
+    }
+
+    fn reset(&mut self) {
+        self.x.clone_from(&self.params.x0);
+    }
+
+    fn save_state(&self) -> Option<BlockState> {
+        Some(Box::new(self.x.clone()))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) -> Result<()> {
+        let x = downcast_state::<DVector<f64>>(&self.name, state)?;
+        if x.len() != self.params.x0.len() {
+            return Err(ControlSystemError::InvalidState {
+                blockname: self.name.clone(),
+                reason: format!(
+                    "expected a state of {} elements, got {}",
+                    self.params.x0.len(),
+                    x.len()
+                ),
+            });
+        }
+
+        self.x.clone_from(x);
+        Ok(())
+    }
+
+    fn get_params(&self) -> Option<Value> {
+        Some(serde_json::to_value(&self.params).unwrap())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct KalmanFilterParams {
+    pub a: DMatrix<f64>,
+    pub b: DMatrix<f64>,
+    pub c: DMatrix<f64>,
+
+    /// Covariance of the process noise
+    pub q: DMatrix<f64>,
+    /// Covariance of the measurement noise
+    pub r: DMatrix<f64>,
+
+    /// Initial state estimate
+    pub x0: DVector<f64>,
+    /// Covariance of the initial state estimate
+    pub p0: DMatrix<f64>,
+}
+
+impl KalmanFilterParams {
+    /// Checks that the matrices of block `name` describe a consistent system
+    fn validate(&self, name: &str) -> Result<()> {
+        let n = self.a.nrows();
+        let m = self.b.ncols();
+        let p = self.c.nrows();
+
+        check_shape(name, "A", self.a.shape(), (n, n))?;
+        check_shape(name, "B", self.b.shape(), (n, m))?;
+        check_shape(name, "C", self.c.shape(), (p, n))?;
+        check_shape(name, "Q", self.q.shape(), (n, n))?;
+        check_shape(name, "R", self.r.shape(), (p, p))?;
+        check_len(name, "x0", self.x0.len(), n)?;
+        check_shape(name, "P0", self.p0.shape(), (n, n))
+    }
+}
+
+/// Discrete-time Kalman filter estimating the state of:
+///
+/// x(k+1) = A x(k) + B u(k) + w(k), w ~ N(0, Q)
+/// y(k) = C x(k) + v(k), v ~ N(0, R)
+///
+/// Every step corrects the predicted state with the measurement `y`, outputs the
+/// corrected estimate and predicts the state of the next step.
+#[derive(BlockIO)]
+pub struct KalmanFilter {
+    #[blockio(block_name)]
+    name: String,
+
+    /// Zero if unconnected
+    #[blockio(input, optional)]
+    u: Input<DVector<f64>>,
+
+    #[blockio(input)]
+    y: Input<DVector<f64>>,
+
+    #[blockio(output)]
+    x_hat: Output<DVector<f64>>,
+
+    params: KalmanFilterParams,
+    /// State estimate and its covariance, predicted for the current step
+    x: DVector<f64>,
+    p: DMatrix<f64>,
+}
+
+impl KalmanFilter {
+    /// Fails if the dimensions of the matrices and of the initial estimate don't match
+    pub fn new(name: &str, params: KalmanFilterParams) -> Result<Self> {
+        params.validate(name)?;
+
+        Ok(KalmanFilter {
+            name: name.to_string(),
+            u: Input::with_default(DVector::zeros(params.b.ncols())),
+            y: Input::default(),
+            x_hat: Output::default(),
+            x: params.x0.clone(),
+            p: params.p0.clone(),
+            params,
+        })
+    }
+
+    pub fn from_store(
+        name: &str,
+        store: &mut ParameterStore,
+        default_params: KalmanFilterParams,
+    ) -> Result<Self> {
+        let params = store.get_block_params(name, default_params)?;
+
+        Self::new(name, params)
+    }
+}
+
+impl Block for KalmanFilter {
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        let KalmanFilterParams { a, b, c, q, r, .. } = &self.params;
+        let u = self.u.get();
+        let y = self.y.get();
+
+        if u.len() != b.ncols() || y.len() != c.nrows() {
+            return Err(ControlSystemError::Other(
+                format!(
+                    "Block '{}': B has {} columns and C {} rows, the input has {} elements and the measurement {}",
+                    self.name,
+                    b.ncols(),
+                    c.nrows(),
+                    u.len(),
+                    y.len()
+                )
+                .into(),
+            ));
+        }
+
+        // Update
+        let s = c * &self.p * c.transpose() + r;
+        let s_inv = s.try_inverse().ok_or_else(|| {
+            ControlSystemError::Other(
+                format!(
+                    "Block '{}': the covariance of the innovation is singular",
+                    self.name
+                )
+                .into(),
+            )
+        })?;
+        let k = &self.p * c.transpose() * s_inv;
+
+        self.x += &k * (y - c * &self.x);
+        // Joseph form, keeping the covariance symmetric and positive
+        let i_kc = DMatrix::identity(self.x.len(), self.x.len()) - &k * c;
+        self.p = &i_kc * &self.p * i_kc.transpose() + &k * r * k.transpose();
+
+        self.x_hat.set(self.x.clone());
+
+        // Predict
+        self.x = a * &self.x + b * u;
+        self.p = a * &self.p * a.transpose() + q;
+
+        Ok(StepResult::Continue)
+    }
+
+    fn reset(&mut self) {
+        self.x.clone_from(&self.params.x0);
+        self.p.clone_from(&self.params.p0);
+    }
+
+    fn save_state(&self) -> Option<BlockState> {
+        Some(Box::new((self.x.clone(), self.p.clone())))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) -> Result<()> {
+        let (x, p) = downcast_state::<(DVector<f64>, DMatrix<f64>)>(&self.name, state)?;
+        let n = self.params.x0.len();
+        if x.len() != n || p.shape() != (n, n) {
+            return Err(ControlSystemError::InvalidState {
+                blockname: self.name.clone(),
+                reason: format!(
+                    "expected a state of {} elements and a {}x{} covariance, got {} elements and a {}x{} covariance",
+                    n,
+                    n,
+                    n,
+                    x.len(),
+                    p.nrows(),
+                    p.ncols()
+                ),
+            });
+        }
+
+        self.x.clone_from(x);
+        self.p.clone_from(p);
+        Ok(())
+    }
+
+    fn get_params(&self) -> Option<Value> {
+        Some(serde_json::to_value(&self.params).unwrap())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LuenbergerObserverParams {
+    pub a: DMatrix<f64>,
+    pub b: DMatrix<f64>,
+    pub c: DMatrix<f64>,
+    /// Observer gain
+    pub l: DMatrix<f64>,
+
+    /// Initial state estimate
+    pub x0: DVector<f64>,
+}
+
+impl LuenbergerObserverParams {
+    /// Checks that the matrices of block `name` describe a consistent system, and that
+    /// the estimation error converges, ie. A - L C is stable
+    fn validate(&self, name: &str) -> Result<()> {
+        let n = self.a.nrows();
+        let m = self.b.ncols();
+        let p = self.c.nrows();
+
+        check_shape(name, "A", self.a.shape(), (n, n))?;
+        check_shape(name, "B", self.b.shape(), (n, m))?;
+        check_shape(name, "C", self.c.shape(), (p, n))?;
+        check_shape(name, "L", self.l.shape(), (n, p))?;
+        check_len(name, "x0", self.x0.len(), n)?;
+
+        let spectral_radius = (&self.a - &self.l * &self.c)
+            .complex_eigenvalues()
+            .iter()
+            .map(|e| e.norm())
+            .fold(0.0, f64::max);
+        if spectral_radius.is_nan() || spectral_radius >= 1.0 {
+            return Err(invalid_params(
+                name,
+                &format!(
+                    "A - L C must be stable, its spectral radius is {}",
+                    spectral_radius
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Discrete-time Luenberger observer estimating the state of:
+///
+/// x(k+1) = A x(k) + B u(k)
+/// y(k) = C x(k)
+///
+/// Every step outputs the current estimate, then updates it with:
+///
+/// x_hat(k+1) = A x_hat(k) + B u(k) + L (y(k) - C x_hat(k))
+#[derive(BlockIO)]
+pub struct LuenbergerObserver {
+    #[blockio(block_name)]
+    name: String,
+
+    /// Zero if unconnected
+    #[blockio(input, optional)]
+    u: Input<DVector<f64>>,
+
+    #[blockio(input)]
+    y: Input<DVector<f64>>,
+
+    #[blockio(output)]
+    x_hat: Output<DVector<f64>>,
+
+    params: LuenbergerObserverParams,
+    x: DVector<f64>,
+}
+
+impl LuenbergerObserver {
+    /// Fails if the dimensions of the matrices and of the initial estimate don't match,
+    /// or if the estimation error would not converge
+    pub fn new(name: &str, params: LuenbergerObserverParams) -> Result<Self> {
+        params.validate(name)?;
+
+        Ok(LuenbergerObserver {
+            name: name.to_string(),
+            u: Input::with_default(DVector::zeros(params.b.ncols())),
+            y: Input::default(),
+            x_hat: Output::default(),
+            x: params.x0.clone(),
+            params,
+        })
+    }
+
+    pub fn from_store(
+        name: &str,
+        store: &mut ParameterStore,
+        default_params: LuenbergerObserverParams,
+    ) -> Result<Self> {
+        let params = store.get_block_params(name, default_params)?;
+
+        Self::new(name, params)
+    }
+}
+
+impl Block for LuenbergerObserver {
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        let LuenbergerObserverParams { a, b, c, l, .. } = &self.params;
+        let u = self.u.get();
+        let y = self.y.get();
+
+        if u.len() != b.ncols() || y.len() != c.nrows() {
+            return Err(ControlSystemError::Other(
+                format!(
+                    "Block '{}': B has {} columns and C {} rows, the input has {} elements and the measurement {}",
+                    self.name,
+                    b.ncols(),
+                    c.nrows(),
+                    u.len(),
+                    y.len()
+                )
+                .into(),
+            ));
+        }
+
+        self.x_hat.set(self.x.clone());
+
+        let innovation = y - c * &self.x;
+        self.x = a * &self.x + b * u + l * innovation;
+
+        Ok(StepResult::Continue)
+    }
+
+    fn reset(&mut self) {
+        self.x.clone_from(&self.params.x0);
+    }
+
+    fn save_state(&self) -> Option<BlockState> {
+        Some(Box::new(self.x.clone()))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) -> Result<()> {
+        let x = downcast_state::<DVector<f64>>(&self.name, state)?;
+        if x.len() != self.params.x0.len() {
+            return Err(ControlSystemError::InvalidState {
+                blockname: self.name.clone(),
+                reason: format!(
+                    "expected a state of {} elements, got {}",
+                    self.params.x0.len(),
+                    x.len()
+                ),
+            });
+        }
+
+        self.x.clone_from(x);
+        Ok(())
+    }
+
+    fn get_params(&self) -> Option<Value> {
+        Some(serde_json::to_value(&self.params).unwrap())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct StateFeedbackParams {
+    /// Gain, with a column for each state and a row for each control
+    pub k: DMatrix<f64>,
+}
+
+impl From<DMatrix<f64>> for StateFeedbackParams {
+    fn from(value: DMatrix<f64>) -> Self {
+        StateFeedbackParams { k: value }
+    }
+}
+
+/// Full-state feedback controller:
+///
+/// u = -K (x - x_ref)
+///
+/// Fails if the state does not have as many elements as K has columns.
+#[derive(BlockIO)]
+pub struct StateFeedback {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    x: Input<DVector<f64>>,
+
+    /// Zero if unconnected
+    #[blockio(input, optional)]
+    x_ref: Input<DVector<f64>>,
+
+    #[blockio(output)]
+    u: Output<DVector<f64>>,
+
+    params: StateFeedbackParams,
+}
+
+impl StateFeedback {
+    pub fn new(name: &str, params: StateFeedbackParams) -> Self {
+        StateFeedback {
+            name: name.to_string(),
+            x: Input::default(),
+            x_ref: Input::with_default(DVector::zeros(params.k.ncols())),
+            u: Output::default(),
+            params,
+        }
+    }
+
+    pub fn from_store(
+        name: &str,
+        store: &mut ParameterStore,
+        default_params: StateFeedbackParams,
+    ) -> Result<Self, ParameterStoreError> {
+        let params = store.get_block_params(name, default_params)?;
+
+        Ok(Self::new(name, params))
+    }
+}
+
+impl Block for StateFeedback {
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        let x = self.x.get();
+        let x_ref = self.x_ref.get();
+        let n = self.params.k.ncols();
+
+        if x.len() != n || x_ref.len() != n {
+            return Err(ControlSystemError::Other(
+                format!(
+                    "Block '{}': K has {} columns, the state has {} elements and the reference {}",
+                    self.name,
+                    n,
+                    x.len(),
+                    x_ref.len()
+                )
+                .into(),
+            ));
+        }
+
+        self.u.set(-&self.params.k * (x - x_ref));
+        Ok(StepResult::Continue)
+    }
+
+    fn get_params(&self) -> Option<Value> {
+        Some(serde_json::to_value(&self.params).unwrap())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ContinuousStateSpaceParams<const N: usize, const M: usize, const P: usize> {
+    pub a: SMatrix<f64, N, N>,
+    pub b: SMatrix<f64, N, M>,
+    pub c: SMatrix<f64, P, N>,
+    pub d: SMatrix<f64, P, M>,
+
+    /// Initial state, with N elements
+    pub initial_state: Vec<f64>,
+}
+
+/// Continuous-time linear system, integrated over each step with the solver `S`:
+///
+/// dx/dt = A x + B u
+/// y = C x + D u
+#[derive(BlockIO)]
+pub struct ContinuousStateSpace<const N: usize, const M: usize, const P: usize, S = RungeKutta4> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u: Input<SVector<f64, M>>,
+
+    #[blockio(output)]
+    y: Output<SVector<f64, P>>,
+
+    params: ContinuousStateSpaceParams<N, M, P>,
+    x: SVector<f64, N>,
+
+    solver: PhantomData<S>,
+}
+
+impl<const N: usize, const M: usize, const P: usize, S> ContinuousStateSpace<N, M, P, S> {
+    /// Fails if the initial state does not have N elements
+    pub fn new(name: &str, params: ContinuousStateSpaceParams<N, M, P>) -> Result<Self> {
+        check_len(name, "initial_state", params.initial_state.len(), N)?;
+
+        Ok(ContinuousStateSpace {
+            name: name.to_string(),
+            u: Input::default(),
+            y: Output::default(),
+            x: SVector::from_column_slice(&params.initial_state),
+            params,
+            solver: PhantomData,
+        })
+    }
+
+    pub fn from_store(
+        name: &str,
+        store: &mut ParameterStore,
+        default_params: ContinuousStateSpaceParams<N, M, P>,
+    ) -> Result<Self> {
+        let params = store.get_block_params(name, default_params)?;
+
+        Self::new(name, params)
+    }
+}
+
+impl<const N: usize, const M: usize, const P: usize, S> Block for ContinuousStateSpace<N, M, P, S>
+where
+    S: ODESolver<f64>,
+{
+    fn step(&mut self, k: StepInfo) -> Result<StepResult> {
+        let u = self.u.get();
+
+        self.y.set(self.params.c * self.x + self.params.d * u);
+
+        let a = &self.params.a;
+        let bu = self.params.b * u;
+        self.x = S::solve(|_, x| a * x + bu, k.t, k.dt, self.x);
+
+        Ok(StepResult::Continue)
+    }
+
+    fn reset(&mut self) {
+        self.x = SVector::from_column_slice(&self.params.initial_state);
+    }
+
+    fn save_state(&self) -> Option<BlockState> {
+        Some(Box::new(self.x))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) -> Result<()> {
+        self.x = *downcast_state::<SVector<f64, N>>(&self.name, state)?;
+        Ok(())
+    }
+
+    fn get_params(&self) -> Option<Value> {
+        Some(serde_json::to_value(&self.params).unwrap())
+    }
+}
+
+/// Fails if `matrix` of block `name` does not have the `expected` shape
+fn check_shape(
+    name: &str,
+    matrix: &str,
+    shape: (usize, usize),
+    expected: (usize, usize),
+) -> Result<()> {
+    if shape != expected {
+        return Err(invalid_params(
+            name,
+            &format!("{} must be {:?}, is {:?}", matrix, expected, shape),
+        ));
+    }
+    Ok(())
+}
+
+/// Fails if `vector` of block `name` does not have `expected` elements
+fn check_len(name: &str, vector: &str, len: usize, expected: usize) -> Result<()> {
+    if len != expected {
+        return Err(invalid_params(
+            name,
+            &format!("{} must have {} elements, has {}", vector, expected, len),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        math::Map,
+        producers::{Clock, Constant},
+        test_utils::{params, Probe},
+    };
+    use control_system::{
+        numeric, AlgebraicLoopParams, ControlSystemBuilder, ControlSystemParameters,
+    };
+
+    /// Output `y` of `block` over `steps` steps of `dt` seconds with the constant
+    /// input `u`
+    fn response<U, Y, B>(block: B, u: U, dt: f64, steps: usize) -> Vec<Y>
+    where
+        U: Clone + 'static,
+        Y: Clone + 'static,
+        B: Block + 'static,
+    {
+        let (probe, values) = Probe::<Y>::new("probe");
+
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Constant::new("u", u.into()), &[], &[("y", "u")])
+            .unwrap();
+        builder
+            .add_block(block, &[("u", "u")], &[("y", "y")])
+            .unwrap();
+        builder.add_block(probe, &[("u", "y")], &[]).unwrap();
+
+        let mut system = builder.build("test", params(dt)).unwrap();
+        for _ in 0..steps {
+            system.step().unwrap();
+        }
+
+        values.take()
+    }
+
+    #[test]
+    fn state_space_step_response() {
+        // Double integrator, x1(k) = k (k - 1) / 2 and x2(k) = k for a unit step
+        let params = StateSpaceParams {
+            a: DMatrix::from_row_slice(2, 2, &[1.0, 1.0, 0.0, 1.0]),
+            b: DMatrix::from_row_slice(2, 1, &[0.0, 1.0]),
+            c: DMatrix::identity(2, 2),
+            d: DMatrix::zeros(2, 1),
+            x0: DVector::zeros(2),
+        };
+        let ss = StateSpace::new("ss", params).unwrap();
+
+        let y: Vec<DVector<f64>> = response(ss, DVector::from_element(1, 1.0), 1.0, 6);
+        let x1: Vec<f64> = y.iter().map(|y| y[0]).collect();
+        let x2: Vec<f64> = y.iter().map(|y| y[1]).collect();
+        assert_eq!(x1, [0.0, 0.0, 1.0, 3.0, 6.0, 10.0]);
+        assert_eq!(x2, [0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn continuous_state_space_matches_matrix_exponential() {
+        // Poles in -1 and -2
+        let a = SMatrix::<f64, 2, 2>::new(0.0, 1.0, -2.0, -3.0);
+        let b = SMatrix::<f64, 2, 1>::new(0.0, 1.0);
+        let c = SMatrix::<f64, 1, 2>::new(1.0, 0.0);
+        let params = ContinuousStateSpaceParams {
+            a,
+            b,
+            c,
+            d: SMatrix::zeros(),
+            initial_state: vec![0.0, 0.0],
+        };
+        let css = ContinuousStateSpace::<2, 1, 1>::new("css", params).unwrap();
+
+        let dt = 0.01;
+        let y: Vec<SVector<f64, 1>> = response(css, SVector::<f64, 1>::new(1.0), dt, 300);
+
+        // Response to a unit step from rest: x(t) = A^-1 (exp(A t) - I) B
+        let a_inv = a.try_inverse().unwrap();
+        for (i, y) in y.iter().enumerate() {
+            let t = i as f64 * dt;
+            let x = a_inv * ((a * t).exp() - SMatrix::<f64, 2, 2>::identity()) * b;
+            let expected = (c * x)[0];
+
+            assert!(
+                (y[0] - expected).abs() < 1e-9,
+                "t = {t}: {} != {expected}",
+                y[0]
+            );
+        }
+    }
+
+    #[test]
+    fn continuous_state_space_initial_state_from_store() {
+        let path = std::env::temp_dir().join(format!(
+            "control_system_blocks_css_store_{}.toml",
+            std::process::id()
+        ));
+        std::fs::write(&path, "[test.blocks.css]\ninitial_state = [2.0, 3.0]\n").unwrap();
+
+        // Double integrator measuring position and velocity
+        let default_params = ContinuousStateSpaceParams::<2, 1, 2> {
+            a: SMatrix::<f64, 2, 2>::new(0.0, 1.0, 0.0, 0.0),
+            b: SMatrix::<f64, 2, 1>::new(0.0, 1.0),
+            c: SMatrix::identity(),
+            d: SMatrix::zeros(),
+            initial_state: vec![0.0, 0.0],
+        };
+        let mut store = ParameterStore::new(&path, "test").unwrap();
+        let css =
+            ContinuousStateSpace::<2, 1, 2>::from_store("css", &mut store, default_params).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let y: Vec<SVector<f64, 2>> = response(css, SVector::<f64, 1>::new(0.0), 0.1, 2);
+        assert_eq!(y[0], SVector::<f64, 2>::new(2.0, 3.0));
+
+        // Moving at constant velocity
+        assert!((y[1][0] - 2.3).abs() < 1e-12);
+        assert_eq!(y[1][1], 3.0);
+    }
+
+    /// Position and velocity estimated by `block` over `steps` steps of `dt` seconds,
+    /// from the measurements `y(t)` of the position
+    fn estimate<B: Block + 'static>(
+        block: B,
+        dt: f64,
+        steps: usize,
+        y: impl Fn(f64) -> f64 + 'static,
+    ) -> (Vec<f64>, Vec<f64>) {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Clock::new("clock"), &[], &[("y", "t")])
+            .unwrap();
+        builder
+            .add_block(
+                Map::new("measure", move |t: f64, _: StepInfo| {
+                    DVector::from_element(1, y(t))
+                }),
+                &[("u", "t")],
+                &[("y", "y")],
+            )
+            .unwrap();
+        builder
+            .add_block(block, &[("y", "y")], &[("x_hat", "x_hat")])
+            .unwrap();
+        for i in 0..2 {
+            let name = format!("x_hat{i}");
+            builder
+                .add_block(
+                    Map::new(&name, move |x: DVector<f64>, _: StepInfo| x[i]),
+                    &[("u", "x_hat")],
+                    &[("y", &name)],
+                )
+                .unwrap();
+        }
+
+        let mut recorded = builder
+            .build("test", params(dt))
+            .unwrap()
+            .simulate(steps, &["x_hat0", "x_hat1"])
+            .unwrap();
+        (
+            recorded.remove("x_hat0").unwrap(),
+            recorded.remove("x_hat1").unwrap(),
+        )
+    }
+
+    /// Model of a point moving at constant velocity, with its position measured
+    fn constant_velocity(dt: f64) -> (DMatrix<f64>, DMatrix<f64>, DMatrix<f64>) {
+        (
+            DMatrix::from_row_slice(2, 2, &[1.0, dt, 0.0, 1.0]),
+            DMatrix::zeros(2, 1),
+            DMatrix::from_row_slice(1, 2, &[1.0, 0.0]),
+        )
+    }
+
+    #[test]
+    fn kalman_filter_converges_on_noisy_measurements() {
+        let dt = 0.1;
+        let (a, b, c) = constant_velocity(dt);
+        let params = KalmanFilterParams {
+            a,
+            b,
+            c,
+            q: DMatrix::identity(2, 2) * 1e-8,
+            // Variance of the uniform noise below
+            r: DMatrix::from_element(1, 1, 1.0 / 12.0),
+            x0: DVector::zeros(2),
+            p0: DMatrix::identity(2, 2) * 100.0,
+        };
+        let kf = KalmanFilter::new("kf", params).unwrap();
+
+        // Deterministic noise, uniformly spread over [-0.5, 0.5]
+        let noise = |t: f64| ((t * 1e3).sin() * 43758.5453).fract().abs() - 0.5;
+        let (position, velocity) = estimate(kf, dt, 500, move |t| 1.0 + 2.0 * t + noise(t));
+
+        assert!(
+            (position[499] - (1.0 + 2.0 * 49.9)).abs() < 0.1,
+            "{}",
+            position[499]
+        );
+        assert!((velocity[499] - 2.0).abs() < 0.01, "{}", velocity[499]);
+    }
+
+    #[test]
+    fn luenberger_observer_error_decays() {
+        // Both poles of A - L C in 0.5
+        let dt = 0.1;
+        let (a, b, c) = constant_velocity(dt);
+        let params = LuenbergerObserverParams {
+            a,
+            b,
+            c,
+            l: DMatrix::from_row_slice(2, 1, &[1.0, 0.25 / dt]),
+            x0: DVector::zeros(2),
+        };
+        let obs = LuenbergerObserver::new("obs", params).unwrap();
+
+        let (position, velocity) = estimate(obs, dt, 50, |t| 1.0 + 2.0 * t);
+        let errors: Vec<f64> = (0..50)
+            .map(|i| {
+                let position_error = position[i] - (1.0 + 2.0 * i as f64 * dt);
+                position_error.hypot(velocity[i] - 2.0)
+            })
+            .collect();
+
+        assert!((errors[0] - 5f64.sqrt()).abs() < 1e-12);
+        for i in 10..50 {
+            assert!(errors[i] < errors[i - 1], "{errors:?}");
+        }
+        assert!(errors[49] < 1e-9, "{errors:?}");
+    }
+
+    #[test]
+    fn state_feedback_stabilizes_inverted_pendulum() {
+        // Pendulum linearized around the upright position, angle and velocity measured
+        let dt = 0.01;
+        let a = DMatrix::from_row_slice(2, 2, &[1.0, dt, 9.81 * dt, 1.0]);
+        let b = DMatrix::from_row_slice(2, 1, &[0.0, dt]);
+        assert!(a.complex_eigenvalues().iter().any(|p| p.re > 1.0));
+
+        let k = numeric::lqr(&a, &b, &DMatrix::identity(2, 2), &DMatrix::identity(1, 1)).unwrap();
+
+        let plant_x0 = DVector::from_row_slice(&[0.1, 0.0]);
+        let plant = StateSpaceParams {
+            a,
+            b,
+            c: DMatrix::identity(2, 2),
+            d: DMatrix::zeros(2, 1),
+            x0: plant_x0.clone(),
+        };
+
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(
+                StateSpace::new("plant", plant).unwrap(),
+                &[("u", "u")],
+                &[("y", "x")],
+            )
+            .unwrap();
+        // The output of the plant does not depend on its input in the same step, so the
+        // loop is solved in two iterations
+        builder
+            .add_block(
+                StateFeedback::new("controller", k.into()),
+                &[("x", "x")],
+                &[("u", "u")],
+            )
+            .unwrap();
+        builder
+            .add_block(
+                Map::new("angle", |x: DVector<f64>, _: StepInfo| x[0]),
+                &[("u", "x")],
+                &[("y", "angle")],
+            )
+            .unwrap();
+
+        // Read by the first block of the loop to run, before being produced
+        builder
+            .set_initial("x", plant_x0.clone())
+            .unwrap()
+            .set_initial("u", DVector::<f64>::zeros(1))
+            .unwrap();
+
+        let params = ControlSystemParameters::new(dt).with_algebraic_loops(AlgebraicLoopParams {
+            max_iter: 10,
+            tolerance: 1e-12,
+        });
+        let recorded = builder
+            .build("test", params)
+            .unwrap()
+            .simulate(1000, &["angle"])
+            .unwrap();
+
+        assert_eq!(recorded["angle"][0], 0.1);
+        assert!(recorded["angle"].iter().all(|angle| angle.abs() <= 0.1));
+        assert!(
+            recorded["angle"][999].abs() < 1e-3,
+            "{}",
+            recorded["angle"][999]
+        );
+    }
+
+    /// Integrator with unit gain, y = x
+    fn integrator() -> StateSpaceParams {
+        StateSpaceParams {
+            a: DMatrix::from_element(1, 1, 1.0),
+            b: DMatrix::from_element(1, 1, 1.0),
+            c: DMatrix::from_element(1, 1, 1.0),
+            d: DMatrix::zeros(1, 1),
+            x0: DVector::zeros(1),
+        }
+    }
+
+    #[test]
+    fn state_space_rejects_inconsistent_dimensions() {
+        assert!(StateSpace::new("ss", integrator()).is_ok());
+
+        let params = StateSpaceParams {
+            b: DMatrix::zeros(2, 1),
+            ..integrator()
+        };
+        assert!(matches!(
+            StateSpace::new("ss", params),
+            Err(ControlSystemError::InvalidBlockParams { .. })
+        ));
+
+        let params = StateSpaceParams {
+            x0: DVector::zeros(2),
+            ..integrator()
+        };
+        assert!(StateSpace::new("ss", params).is_err());
+    }
+
+    #[test]
+    fn state_space_rejects_wrong_input_length() {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_external_signal::<DVector<f64>>("u")
+            .unwrap()
+            .set_initial("u", DVector::from_element(2, 1.0))
+            .unwrap();
+        builder
+            .add_block(
+                StateSpace::new("ss", integrator()).unwrap(),
+                &[("u", "u")],
+                &[("y", "y")],
+            )
+            .unwrap();
+        let mut system = builder.build("ss", params(1.0)).unwrap();
+
+        assert!(system.step().is_err());
+    }
+
+    #[test]
+    fn continuous_state_space_rejects_wrong_initial_state() {
+        let params = |initial_state| ContinuousStateSpaceParams::<2, 1, 1> {
+            a: SMatrix::zeros(),
+            b: SMatrix::zeros(),
+            c: SMatrix::zeros(),
+            d: SMatrix::zeros(),
+            initial_state,
+        };
+
+        assert!(ContinuousStateSpace::<2, 1, 1>::new("css", params(vec![0.0, 0.0])).is_ok());
+        assert!(matches!(
+            ContinuousStateSpace::<2, 1, 1>::new("css", params(vec![0.0])),
+            Err(ControlSystemError::InvalidBlockParams { .. })
+        ));
+    }
+
+    /// Filter of a constant scalar state, measured directly
+    fn constant_estimator() -> KalmanFilterParams {
+        KalmanFilterParams {
+            a: DMatrix::from_element(1, 1, 1.0),
+            b: DMatrix::zeros(1, 1),
+            c: DMatrix::from_element(1, 1, 1.0),
+            q: DMatrix::zeros(1, 1),
+            r: DMatrix::from_element(1, 1, 1.0),
+            x0: DVector::zeros(1),
+            p0: DMatrix::from_element(1, 1, 1.0),
+        }
+    }
+
+    #[test]
+    fn kalman_filter_rejects_inconsistent_dimensions() {
+        assert!(KalmanFilter::new("kf", constant_estimator()).is_ok());
+
+        let params = KalmanFilterParams {
+            r: DMatrix::zeros(2, 2),
+            ..constant_estimator()
+        };
+        assert!(matches!(
+            KalmanFilter::new("kf", params),
+            Err(ControlSystemError::InvalidBlockParams { .. })
+        ));
+    }
+
+    #[test]
+    fn kalman_filter_rejects_wrong_measurement_length() {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_external_signal::<DVector<f64>>("y")
+            .unwrap()
+            .set_initial("y", DVector::from_element(2, 1.0))
+            .unwrap();
+        builder
+            .add_block(
+                KalmanFilter::new("kf", constant_estimator()).unwrap(),
+                &[("y", "y")],
+                &[("x_hat", "x_hat")],
+            )
+            .unwrap();
+        let mut system = builder
+            .build("kf", ControlSystemParameters::new(1.0))
+            .unwrap();
+
+        assert!(system.step().is_err());
+    }
+
+    #[test]
+    fn kalman_filter_checks_restored_state() {
+        let mut kf = KalmanFilter::new("kf", constant_estimator()).unwrap();
+        let state = kf.save_state().unwrap();
+
+        kf.x = DVector::from_element(1, 3.0);
+        kf.load_state(state.as_ref()).unwrap();
+        assert_eq!(kf.x, DVector::zeros(1));
+
+        let wrong_state = (DVector::<f64>::zeros(2), DMatrix::<f64>::zeros(2, 2));
+        assert!(kf.load_state(&wrong_state).is_err());
+        let wrong_covariance = (DVector::<f64>::zeros(1), DMatrix::<f64>::zeros(1, 2));
+        assert!(kf.load_state(&wrong_covariance).is_err());
+    }
+
+    fn observer(l: f64) -> LuenbergerObserverParams {
+        LuenbergerObserverParams {
+            a: DMatrix::from_element(1, 1, 1.0),
+            b: DMatrix::zeros(1, 1),
+            c: DMatrix::from_element(1, 1, 1.0),
+            l: DMatrix::from_element(1, 1, l),
+            x0: DVector::zeros(1),
+        }
+    }
+
+    #[test]
+    fn luenberger_observer_rejects_invalid_params() {
+        assert!(LuenbergerObserver::new("obs", observer(0.5)).is_ok());
+
+        // A - L C = 1, the estimation error never decreases
+        assert!(matches!(
+            LuenbergerObserver::new("obs", observer(0.0)),
+            Err(ControlSystemError::InvalidBlockParams { .. })
+        ));
+
+        let params = LuenbergerObserverParams {
+            x0: DVector::zeros(3),
+            ..observer(0.5)
+        };
+        assert!(LuenbergerObserver::new("obs", params).is_err());
+    }
+
+    #[test]
+    fn luenberger_observer_checks_restored_state() {
+        let mut obs = LuenbergerObserver::new("obs", observer(0.5)).unwrap();
+        let state = obs.save_state().unwrap();
+
+        obs.x = DVector::from_element(1, 3.0);
+        obs.load_state(state.as_ref()).unwrap();
+        assert_eq!(obs.x, DVector::zeros(1));
+        assert!(obs.load_state(&DVector::<f64>::zeros(2)).is_err());
+    }
+
+    #[test]
+    fn state_space_checks_restored_state() {
+        let mut ss = StateSpace::new("ss", integrator()).unwrap();
+        let state = ss.save_state().unwrap();
+
+        ss.x = DVector::from_element(1, 3.0);
+        ss.load_state(state.as_ref()).unwrap();
+        assert_eq!(ss.x, DVector::zeros(1));
+        assert!(ss.load_state(&DVector::<f64>::zeros(2)).is_err());
+    }
+}
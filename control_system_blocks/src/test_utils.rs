@@ -0,0 +1,99 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use control_system::{
+    io::Input, Block, BlockIO, ControlSystemBuilder, ControlSystemParameters, Result, StepInfo,
+    StepResult,
+};
+
+use crate::producers::Generator;
+
+/// Records every value of its input
+#[derive(BlockIO)]
+pub struct Probe<T> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u: Input<T>,
+
+    values: Rc<RefCell<Vec<T>>>,
+}
+
+impl<T: 'static> Probe<T> {
+    /// Returns the probe along with the values it will record
+    pub fn new(name: &str) -> (Self, Rc<RefCell<Vec<T>>>) {
+        let values = Rc::new(RefCell::new(vec![]));
+
+        let probe = Probe {
+            name: name.to_string(),
+            u: Input::default(),
+            values: values.clone(),
+        };
+
+        (probe, values)
+    }
+}
+
+impl<T> Block for Probe<T>
+where
+    T: Clone + 'static,
+{
+    fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+        self.values.borrow_mut().push(self.u.get());
+        Ok(StepResult::Continue)
+    }
+}
+
+/// Adds a block writing `values` to `signal` on successive steps
+pub fn add_sequence<T: Clone + 'static>(
+    builder: &mut ControlSystemBuilder,
+    signal: &str,
+    values: &[T],
+) {
+    let values = values.to_vec();
+    let k = Cell::new(0);
+    let generator = Generator::new(&format!("input_{signal}"), move || {
+        k.set(k.get() + 1);
+        values[k.get() - 1].clone()
+    });
+
+    builder.add_block(generator, &[], &[("y", signal)]).unwrap();
+}
+
+pub fn params(dt: f64) -> ControlSystemParameters {
+    ControlSystemParameters::new(dt)
+}
+
+/// Output `y` of `block` over `steps` steps of `dt` seconds, with the input `u`
+/// set to `input(t)`
+pub fn response<B: Block + 'static>(
+    block: B,
+    dt: f64,
+    steps: usize,
+    input: impl Fn(f64) -> f64 + 'static,
+) -> Vec<f64> {
+    let k = Cell::new(0usize);
+    let generator = Generator::new("input", move || {
+        let t = k.get() as f64 * dt;
+        k.set(k.get() + 1);
+        input(t)
+    });
+    let (probe, values) = Probe::new("probe");
+
+    let mut builder = ControlSystemBuilder::default();
+    builder.add_block(generator, &[], &[("y", "u")]).unwrap();
+    builder
+        .add_block(block, &[("u", "u")], &[("y", "y")])
+        .unwrap();
+    builder.add_block(probe, &[("u", "y")], &[]).unwrap();
+
+    let mut system = builder.build("test", params(dt)).unwrap();
+    for _ in 0..steps {
+        system.step().unwrap();
+    }
+
+    values.take()
+}
@@ -1,63 +1,77 @@
-use core::panic;
+use std::collections::HashMap;
 
-use proc_macro2::{TokenStream, TokenTree, Ident};
+use proc_macro2::{Ident, Span, TokenStream, TokenTree};
 use quote::quote;
-use syn::{parse_macro_input, Attribute, Data, DeriveInput, Fields, Meta, MetaList};
+use syn::{
+    parse_macro_input, parse_quote, spanned::Spanned, Attribute, Data, DeriveInput, Error, Fields,
+    Lit, Meta, MetaList, Type,
+};
 
 #[derive(Clone, Debug)]
 enum BlockIOAttribute {
     Name,
-    Input { name: Option<String>, is_arr: bool },
-    Output { name: Option<String>, is_arr: bool },
+    Skip,
+    Input(PortAttribute),
+    Output(PortAttribute),
+}
+
+#[derive(Clone, Debug, Default)]
+struct PortAttribute {
+    name: Option<String>,
+    is_arr: bool,
+    optional: bool,
+    /// Index of the first element of an array port
+    base: Option<usize>,
+    /// Name of the elements of an array port, with `{name}` and `{index}` placeholders
+    format: Option<String>,
 }
 
 impl BlockIOAttribute {
-    fn from_attribute(attr: Attribute) -> Option<Self> {
+    fn from_attribute(attr: Attribute) -> syn::Result<Option<Self>> {
+        let span = attr.path().span();
         match attr.meta {
             Meta::List(MetaList { path, tokens, .. }) => {
                 if let Some(seg) = path.segments.first() {
                     if seg.ident == "blockio" {
-                        Self::parse_tokens(tokens)
+                        Self::parse_tokens(tokens, span)
                     } else {
-                        None
+                        Ok(None)
                     }
                 } else {
-                    None
+                    Ok(None)
                 }
             }
-            _ => None,
+            // `#[blockio]` or `#[blockio = ...]`, which would otherwise be ignored
+            meta if meta.path().is_ident("blockio") => Err(Error::new(
+                span,
+                "expected a list of arguments, eg. '#[blockio(input)]'",
+            )),
+            _ => Ok(None),
         }
     }
 
-    fn parse_tokens(tokens: TokenStream) -> Option<Self> {
+    fn parse_tokens(tokens: TokenStream, span: Span) -> syn::Result<Option<Self>> {
         let mut out: Option<Self> = None;
-        let set = |dest: &mut Option<Self>, v: Self| {
-            if dest.is_some() {
-                panic!("Invalid tokens provided to 'blockio' attribute")
-            }
-            *dest = Some(v);
-        };
 
+        #[derive(PartialEq, Eq, Clone, Copy)]
         enum State {
             Ident,
-            IOField(IOType, IOFieldState),
-        }
-
-        #[derive(Clone, Copy)]
-        enum IOType {
-            Input,
-            InputArr,
-            Output,
-            OutputArr,
+            IOField(IOFieldState),
         }
 
         #[derive(PartialEq, Eq, Clone, Copy)]
         enum IOFieldState {
             Sep,
-            NameKey,
-            Equals,
-            Literal,
-            Done,
+            Key,
+            Equals(Key),
+            Literal(Key),
+        }
+
+        #[derive(PartialEq, Eq, Clone, Copy)]
+        enum Key {
+            Name,
+            Base,
+            Format,
         }
 
         let mut state = State::Ident;
@@ -65,191 +79,327 @@ impl BlockIOAttribute {
         for token in tokens {
             match state {
                 State::Ident => match token {
-                    TokenTree::Ident(ident) => match ident.to_string().as_str() {
-                        "block_name" => set(&mut out, BlockIOAttribute::Name),
-                        "input" => {
-                            state = State::IOField(IOType::Input, IOFieldState::Sep);
-                            set(
-                                &mut out,
-                                BlockIOAttribute::Input {
-                                    name: None,
-                                    is_arr: false,
-                                },
-                            );
-                        }
-                        "output" => {
-                            state = State::IOField(IOType::Output, IOFieldState::Sep);
-                            set(
-                                &mut out,
-                                BlockIOAttribute::Output {
-                                    name: None,
-                                    is_arr: false,
-                                },
-                            );
-                        }
-                        "input_arr" => {
-                            state = State::IOField(IOType::InputArr, IOFieldState::Sep);
-                            set(
-                                &mut out,
-                                BlockIOAttribute::Input {
-                                    name: None,
-                                    is_arr: true,
-                                },
-                            );
+                    TokenTree::Ident(ident) => {
+                        let port = PortAttribute {
+                            is_arr: ident.to_string().ends_with("_arr"),
+                            ..Default::default()
+                        };
+                        let attr = match ident.to_string().as_str() {
+                            "block_name" => BlockIOAttribute::Name,
+                            "skip" => BlockIOAttribute::Skip,
+                            "input" | "input_arr" => BlockIOAttribute::Input(port),
+                            "output" | "output_arr" => BlockIOAttribute::Output(port),
+                            _ => {
+                                return Err(Error::new(
+                                    ident.span(),
+                                    format!(
+                                        "unknown 'blockio' attribute '{}', expected one of: {}",
+                                        ident,
+                                        "block_name, skip, input, input_arr, output, output_arr"
+                                    ),
+                                ))
+                            }
+                        };
+
+                        if !matches!(attr, BlockIOAttribute::Name | BlockIOAttribute::Skip) {
+                            state = State::IOField(IOFieldState::Sep);
                         }
-                        "output_arr" => {
-                            state = State::IOField(IOType::OutputArr, IOFieldState::Sep);
-                            set(
-                                &mut out,
-                                BlockIOAttribute::Output {
-                                    name: None,
-                                    is_arr: true,
-                                },
-                            );
+                        if out.is_some() {
+                            return Err(Error::new(
+                                ident.span(),
+                                "only one 'blockio' attribute kind can be specified",
+                            ));
                         }
-                        _ => panic!("Unrecognized identifier in 'blockio' attribute: {}", ident),
-                    },
-                    _ => {
-                        panic!("Missing identifier in 'blockio' attribute")
+                        out = Some(attr);
+                    }
+                    token => {
+                        return Err(Error::new(
+                            token.span(),
+                            "expected an identifier in 'blockio' attribute",
+                        ))
                     }
                 },
-                State::IOField(io, iostate) => {
+                State::IOField(iostate) => {
+                    let port = match out.as_mut() {
+                        Some(BlockIOAttribute::Input(port) | BlockIOAttribute::Output(port)) => {
+                            port
+                        }
+                        _ => unreachable!(),
+                    };
+
                     match iostate {
                         IOFieldState::Sep => match token {
-                            TokenTree::Punct(punct) => {
-                                if punct.as_char() == ',' {
-                                    state = State::IOField(io, IOFieldState::NameKey);
-                                } else {
-                                    panic!("Unexpected separator in 'blockio' attribute. Expeting ','.");
-                                }
+                            TokenTree::Punct(punct) if punct.as_char() == ',' => {
+                                state = State::IOField(IOFieldState::Key);
                             }
-                            _ => panic!(
-                                "Unexpected token in 'blockio' attribute. Expecting Punct(',')."
-                            ),
+                            token => return Err(Error::new(token.span(), "expected ','")),
                         },
-                        IOFieldState::NameKey => match token {
+                        IOFieldState::Key => match token {
                             TokenTree::Ident(ident) => {
-                                if ident == "name" {
-                                    state = State::IOField(io, IOFieldState::Equals);
-                                } else {
-                                    panic!("Unexpected ident in 'blockio' attribute '{}'. Expeting 'name'.", ident);
+                                let key = match ident.to_string().as_str() {
+                                    "name" => Key::Name,
+                                    "base" => Key::Base,
+                                    "format" => Key::Format,
+                                    "optional" => {
+                                        port.optional = true;
+                                        state = State::IOField(IOFieldState::Sep);
+                                        continue;
+                                    }
+                                    _ => return Err(Error::new(
+                                        ident.span(),
+                                        format!(
+                                            "unknown key '{}' in 'blockio' attribute, expected one of: {}",
+                                            ident, "name, optional, base, format"
+                                        ),
+                                    )),
+                                };
+
+                                if key != Key::Name && !port.is_arr {
+                                    return Err(Error::new(
+                                        ident.span(),
+                                        format!("'{}' is only supported on array ports", ident),
+                                    ));
                                 }
+                                state = State::IOField(IOFieldState::Equals(key));
                             }
-                            _ => {
-                                panic!("Unexpected token in 'blockio' attribute. Expeting 'name'.")
+                            token => {
+                                return Err(Error::new(
+                                    token.span(),
+                                    "expected one of: name, optional, base, format",
+                                ))
                             }
                         },
-                        IOFieldState::Equals => match token {
-                            TokenTree::Punct(punct) => {
-                                if punct.as_char() == '=' {
-                                    state = State::IOField(io, IOFieldState::Literal);
-                                } else {
-                                    panic!("Unexpected separator in 'blockio' attribute. Expeting '='.");
-                                }
+                        IOFieldState::Equals(key) => match token {
+                            TokenTree::Punct(punct) if punct.as_char() == '=' => {
+                                state = State::IOField(IOFieldState::Literal(key));
                             }
-                            _ => panic!(
-                                "Unexpected token in 'blockio' attribute. Expecting Punct('=')."
-                            ),
+                            token => return Err(Error::new(token.span(), "expected '='")),
                         },
-                        IOFieldState::Literal => match token {
-                            TokenTree::Literal(literal) => match io {
-                                IOType::Input => {
-                                    out = Some(BlockIOAttribute::Input {
-                                        name: Some(literal.to_string()),
-                                        is_arr: false,
-                                    })
-                                }
-                                IOType::Output => {
-                                    out = Some(BlockIOAttribute::Output {
-                                        name: Some(literal.to_string()),
-                                        is_arr: false,
-                                    })
+                        IOFieldState::Literal(key) => match token {
+                            TokenTree::Literal(literal) => {
+                                match (key, Lit::new(literal)) {
+                                    (Key::Name, Lit::Str(lit)) => port.name = Some(lit.value()),
+                                    (Key::Format, Lit::Str(lit)) => port.format = Some(lit.value()),
+                                    (Key::Base, Lit::Int(lit)) => {
+                                        port.base = Some(lit.base10_parse()?)
+                                    }
+                                    (Key::Base, lit) => {
+                                        return Err(Error::new(
+                                            lit.span(),
+                                            "expected an integer literal",
+                                        ))
+                                    }
+                                    (_, lit) => {
+                                        return Err(Error::new(
+                                            lit.span(),
+                                            "expected a string literal",
+                                        ))
+                                    }
                                 }
-                                IOType::InputArr => {
-                                    out = Some(BlockIOAttribute::Input {
-                                        name: Some(literal.to_string()),
-                                        is_arr: true,
-                                    })
-                                }
-                                IOType::OutputArr => {
-                                    out = Some(BlockIOAttribute::Output {
-                                        name: Some(literal.to_string()),
-                                        is_arr: true,
-                                    })
-                                }
-                            },
-                            _ => panic!(
-                                "Unexpected token in 'blockio' attribute. Expecting Literal."
-                            ),
+                                state = State::IOField(IOFieldState::Sep);
+                            }
+                            token => return Err(Error::new(token.span(), "expected a literal")),
                         },
-                        _ => {}
                     }
                 }
             }
         }
 
-        if let State::IOField(_, state) = state {
-            if state != IOFieldState::Sep && state != IOFieldState::Done {
-                panic!("Incorrect syntax for 'blockio' attribute");
+        if let State::IOField(state) = state {
+            if state != IOFieldState::Sep {
+                return Err(Error::new(span, "incomplete 'blockio' attribute"));
             }
         }
 
-        out
+        if out.is_none() {
+            return Err(Error::new(span, "empty 'blockio' attribute"));
+        }
+
+        Ok(out)
     }
 }
 
-fn parse_attributes(attrs: &[Attribute]) -> Option<BlockIOAttribute> {
+fn parse_attributes(attrs: &[Attribute]) -> syn::Result<Option<BlockIOAttribute>> {
     let mut out: Option<BlockIOAttribute> = None;
     for attr in attrs {
-        let parsed = BlockIOAttribute::from_attribute(attr.clone());
-        if parsed.is_some() && out.is_some() {
-            panic!("Conflicting 'blockio' attributes found");
+        let parsed = BlockIOAttribute::from_attribute(attr.clone())?;
+        if parsed.is_some() {
+            if out.is_some() {
+                return Err(Error::new(
+                    attr.path().span(),
+                    "conflicting 'blockio' attributes on the same field",
+                ));
+            }
+            out = parsed;
         }
-        out = parsed;
     }
-    out
+    Ok(out)
+}
+
+/// Name of the `i`-th element of an array port
+fn quote_element_name(name: &str, port: &PortAttribute) -> TokenStream {
+    let base = port.base.unwrap_or(1);
+    let format = port
+        .format
+        .clone()
+        .unwrap_or_else(|| "{name}{index}".to_string());
+
+    quote! {
+        #format.replace("{name}", #name).replace("{index}", &(i + #base).to_string())
+    }
 }
 
-fn quote_map_insert(ident: Ident, name: String, is_arr: bool) -> TokenStream {
-    if is_arr {
+fn quote_map_insert(
+    ident: &Ident,
+    name: &str,
+    port: &PortAttribute,
+    is_input: bool,
+) -> TokenStream {
+    // Inputs also learn the name of their port, to report it when read unconnected
+    let signal = |port_name: TokenStream| {
+        if is_input {
+            quote! { port_signal_mut(&#port_name) }
+        } else {
+            quote! { get_signal_mut() }
+        }
+    };
+
+    if port.is_arr {
+        let element_name = quote_element_name(name, port);
+        let signal = signal(quote! { element_name });
         quote! {
             for (i, s) in self.#ident.iter_mut().enumerate() {
-                assert!(hm.insert(format!("{}{}", #name, i + 1).to_string(), s.get_signal_mut()).is_none(), "Duplicate IO name: {}", #name);
+                let element_name = #element_name;
+                assert!(hm.insert(element_name.clone(), s.#signal).is_none(), "Duplicate IO name: {}", #name);
+            }
+        }
+    } else {
+        let signal = signal(quote! { #name });
+        quote! {
+            assert!(hm.insert(#name.to_string(), self.#ident.#signal).is_none(), "Duplicate IO name: {}", #name);
+        }
+    }
+}
+
+fn quote_type_insert(ident: &Ident, name: &str, port: &PortAttribute) -> TokenStream {
+    if port.is_arr {
+        let element_name = quote_element_name(name, port);
+        quote! {
+            for (i, s) in self.#ident.iter().enumerate() {
+                hm.insert(#element_name, s.signal_type());
+            }
+        }
+    } else {
+        quote! {
+            hm.insert(#name.to_string(), self.#ident.signal_type());
+        }
+    }
+}
+
+fn quote_name_push(ident: &Ident, name: &str, port: &PortAttribute) -> TokenStream {
+    if port.is_arr {
+        let element_name = quote_element_name(name, port);
+        quote! {
+            for i in 0..self.#ident.len() {
+                names.push(#element_name);
             }
         }
-    }else{
+    } else {
         quote! {
-            assert!(hm.insert(#name.to_string(), self.#ident.get_signal_mut()).is_none(), "Duplicate IO name: {}", #name);
+            names.push(#name.to_string());
         }
     }
 }
 
+/// Checks that a port name is not already used by another field.
+/// Array ports are only checked at runtime, as the names depend on their length.
+fn check_duplicate_port(
+    names: &mut HashMap<String, Ident>,
+    ident: &Ident,
+    name: &str,
+    port: &PortAttribute,
+) -> syn::Result<()> {
+    if port.is_arr {
+        return Ok(());
+    }
+
+    if let Some(other) = names.insert(name.to_string(), ident.clone()) {
+        return Err(Error::new(
+            ident.span(),
+            format!(
+                "duplicate port name '{}', already used by field '{}'",
+                name, other
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
 #[proc_macro_derive(BlockIO, attributes(blockio))]
 pub fn derive(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let ast = parse_macro_input!(tokens as DeriveInput);
-    
+
+    derive_blockio(ast)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn derive_blockio(ast: DeriveInput) -> syn::Result<TokenStream> {
     let datastruct = match ast.data {
         Data::Struct(s) => s,
-        Data::Enum(..) => panic!("Enums are not supported!"),
-        Data::Union(..) => panic!("Unions are not supported!"),
+        Data::Enum(..) => {
+            return Err(Error::new(
+                ast.ident.span(),
+                format!(
+                    "BlockIO cannot be derived for enum '{}': wrap it in a struct and mark the enum field with #[blockio(skip)]",
+                    ast.ident
+                ),
+            ))
+        }
+        Data::Union(..) => {
+            return Err(Error::new(
+                ast.ident.span(),
+                format!("BlockIO cannot be derived for union '{}'", ast.ident),
+            ))
+        }
     };
 
     let fields: Vec<_> = match datastruct.fields {
         Fields::Named(named_fields) => named_fields.named.iter().cloned().collect(),
-        _ => panic!("Only named struct fields are supported"),
+        fields => {
+            return Err(Error::new(
+                fields.span(),
+                "BlockIO can only be derived for structs with named fields",
+            ))
+        }
     };
 
     let mut name: Option<TokenStream> = None;
     let mut input_map: Vec<TokenStream> = vec![];
     let mut output_map: Vec<TokenStream> = vec![];
+    let mut input_port_names: Vec<TokenStream> = vec![];
+    let mut output_port_names: Vec<TokenStream> = vec![];
+    let mut optional_inputs: Vec<TokenStream> = vec![];
+    let mut optional_outputs: Vec<TokenStream> = vec![];
+    let mut input_types: Vec<TokenStream> = vec![];
+    let mut input_bounds: Vec<Type> = vec![];
+    let mut input_names: HashMap<String, Ident> = HashMap::new();
+    let mut output_names: HashMap<String, Ident> = HashMap::new();
 
     for field in fields {
         let ident = field.ident.unwrap();
-        if let Some(attr) = parse_attributes(&field.attrs) {
+        let ty = field.ty;
+
+        if let Some(attr) = parse_attributes(&field.attrs)? {
             match attr {
+                BlockIOAttribute::Skip => {}
                 BlockIOAttribute::Name => {
                     if name.is_some() {
-                        panic!("Duplicate field with attribute 'name'");
+                        return Err(Error::new(
+                            ident.span(),
+                            "only one field can be marked with #[blockio(block_name)]",
+                        ));
                     }
 
                     name = Some(quote! {
@@ -258,21 +408,49 @@ pub fn derive(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
                         }
                     });
                 }
-                BlockIOAttribute::Input { name, is_arr } => {
-                    let name = name.unwrap_or(ident.to_string());
-                    
-                    input_map.push(quote_map_insert(ident, name, is_arr));
+                BlockIOAttribute::Input(port) => {
+                    let name = port.name.clone().unwrap_or(ident.to_string());
+                    check_duplicate_port(&mut input_names, &ident, &name, &port)?;
+
+                    if port.optional {
+                        optional_inputs.push(quote_name_push(&ident, &name, &port));
+                    }
+                    input_port_names.push(quote_name_push(&ident, &name, &port));
+                    input_map.push(quote_map_insert(&ident, &name, &port, true));
+                    input_types.push(quote_type_insert(&ident, &name, &port));
+                    input_bounds.push(ty);
                 }
-                BlockIOAttribute::Output { name, is_arr } => {
-                    let name = name.unwrap_or(ident.to_string());
-                    output_map.push(quote_map_insert(ident, name, is_arr));
+                BlockIOAttribute::Output(port) => {
+                    let name = port.name.clone().unwrap_or(ident.to_string());
+                    check_duplicate_port(&mut output_names, &ident, &name, &port)?;
+
+                    if port.optional {
+                        optional_outputs.push(quote_name_push(&ident, &name, &port));
+                    }
+                    output_port_names.push(quote_name_push(&ident, &name, &port));
+                    output_map.push(quote_map_insert(&ident, &name, &port, false));
                 }
             }
         }
     }
 
+    if name.is_none() {
+        return Err(Error::new(
+            ast.ident.span(),
+            "missing a field marked with #[blockio(block_name)]",
+        ));
+    }
+
     let struct_ident = ast.ident;
-    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let mut generics = ast.generics;
+
+    // Input types are only known for 'static values
+    let where_predicates = &mut generics.make_where_clause().predicates;
+    for ty in input_bounds {
+        where_predicates.push(parse_quote!(#ty: 'static));
+    }
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
     let tokens = quote! {
         impl #impl_generics BlockIO for #struct_ident #ty_generics #where_clause {
@@ -295,8 +473,53 @@ pub fn derive(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
 
                 hm
             }
+
+            fn input_port_names(&self) -> ::std::vec::Vec<::std::string::String> {
+                #![allow(unused_mut, clippy::let_and_return)]
+                let mut names = ::std::vec::Vec::new();
+
+                #( #input_port_names )*
+
+                names
+            }
+
+            fn output_port_names(&self) -> ::std::vec::Vec<::std::string::String> {
+                #![allow(unused_mut, clippy::let_and_return)]
+                let mut names = ::std::vec::Vec::new();
+
+                #( #output_port_names )*
+
+                names
+            }
+
+            fn optional_inputs(&self) -> ::std::vec::Vec<::std::string::String> {
+                #![allow(unused_mut, clippy::let_and_return)]
+                let mut names = ::std::vec::Vec::new();
+
+                #( #optional_inputs )*
+
+                names
+            }
+
+            fn optional_outputs(&self) -> ::std::vec::Vec<::std::string::String> {
+                #![allow(unused_mut, clippy::let_and_return)]
+                let mut names = ::std::vec::Vec::new();
+
+                #( #optional_outputs )*
+
+                names
+            }
+
+            fn input_types(&self) -> ::std::collections::HashMap<::std::string::String, ::control_system::io::SignalType> {
+                #![allow(unused_mut, clippy::let_and_return)]
+                let mut hm = ::std::collections::HashMap::new();
+
+                #( #input_types )*
+
+                hm
+            }
         }
     };
 
-    tokens.into()
+    Ok(tokens)
 }
@@ -12,6 +12,7 @@ use control_system::blocks::{
     siso::{Delay, PIDParams, PID},
 };
 use control_system::{
+    invalid_params,
     io::{Input, Output},
     numeric::ode::{ODESolver, RungeKutta4},
     Block, ControlSystemParameters, ParameterStore, StepInfo, StepResult,
@@ -24,8 +25,8 @@ use serde::{Deserialize, Serialize};
 #[derive(Serialize, Deserialize)]
 struct CartParams {
     mass: f64,
-    pos0: f64,
-    vel0: f64,
+    /// Initial position and velocity
+    initial_state: Vec<f64>,
 }
 
 #[derive(BlockIO)]
@@ -66,22 +67,32 @@ impl Block for Cart {
 }
 
 impl Cart {
-    fn new(params: CartParams) -> Self {
-        Cart {
+    fn new(params: CartParams) -> control_system::Result<Self> {
+        if params.initial_state.len() != 2 {
+            return Err(invalid_params(
+                "cart",
+                &format!(
+                    "initial_state must have 2 elements, has {}",
+                    params.initial_state.len()
+                ),
+            ));
+        }
+
+        Ok(Cart {
             name: "cart".to_string(),
-            state: Vector2::new(params.pos0, params.vel0),
+            state: Vector2::from_column_slice(&params.initial_state),
             params,
             u_force: Input::default(),
             y_pos: Output::default(),
             y_vel: Output::default(),
             y_acc: Output::default(),
-        }
+        })
     }
 
     fn from_store(store: &mut ParameterStore, default_params: CartParams) -> Result<Self> {
         let params = store.get_block_params("cart", default_params)?;
 
-        Ok(Self::new(params))
+        Ok(Self::new(params)?)
     }
 }
 
@@ -112,8 +123,7 @@ fn run_control_system(signals_snd: Sender<PlotSignals>) -> Result<()> {
             &mut store,
             CartParams {
                 mass: 1.0,
-                pos0: 0.0,
-                vel0: 0.0,
+                initial_state: vec![0.0, 0.0],
             },
         )?,
         &[("u_force", "/force")],
@@ -209,10 +219,7 @@ fn run_control_system(signals_snd: Sender<PlotSignals>) -> Result<()> {
     let mut cs = builder.build_from_store(
         "cart",
         &mut store,
-        ControlSystemParameters {
-            dt: 0.01,
-            max_iter: 1000,
-        },
+        ControlSystemParameters::new(0.01).with_max_iter(1000),
     )?;
 
     store.save()?;
@@ -223,7 +230,7 @@ fn run_control_system(signals_snd: Sender<PlotSignals>) -> Result<()> {
         .expect("Could not send signals to GUI");
 
     // Execute
-    while cs.step()? != StepResult::Stop {}
+    while !cs.step()?.is_stop() {}
 
     Ok(())
 }
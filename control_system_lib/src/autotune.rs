@@ -0,0 +1,329 @@
+use std::f64::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ControlSystem, ControlSystemError, Result};
+
+/// Settings of [`relay_autotune`]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RelayAutotuneParams {
+    /// Signal holding the setpoint of the loop
+    pub reference: String,
+    /// Signal holding the measured output of the plant
+    pub measurement: String,
+    /// External `f64` signal driving the plant, written by the relay in place of the
+    /// controller
+    pub control: String,
+
+    /// The relay outputs `bias + amplitude` or `bias - amplitude`
+    pub amplitude: f64,
+    pub bias: f64,
+    /// The relay only switches once the error leaves the band `[-hysteresis, hysteresis]`,
+    /// to avoid chattering on noisy measurements
+    pub hysteresis: f64,
+
+    /// Number of consecutive oscillation periods averaged in the estimate
+    pub cycles: usize,
+    /// Largest relative spread of the period and amplitude over the last `cycles`
+    /// periods for the oscillation to be considered steady
+    pub tolerance: f64,
+    /// Steps after which the identification is abandoned
+    pub max_steps: usize,
+}
+
+impl RelayAutotuneParams {
+    pub fn new(reference: &str, measurement: &str, control: &str, amplitude: f64) -> Self {
+        RelayAutotuneParams {
+            reference: reference.to_string(),
+            measurement: measurement.to_string(),
+            control: control.to_string(),
+            amplitude,
+            bias: 0.0,
+            hysteresis: 0.0,
+            cycles: 3,
+            tolerance: 0.01,
+            max_steps: 100_000,
+        }
+    }
+
+    pub fn with_bias(mut self, bias: f64) -> Self {
+        self.bias = bias;
+        self
+    }
+
+    pub fn with_hysteresis(mut self, hysteresis: f64) -> Self {
+        self.hysteresis = hysteresis;
+        self
+    }
+
+    pub fn with_cycles(mut self, cycles: usize) -> Self {
+        self.cycles = cycles;
+        self
+    }
+
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+}
+
+/// Ultimate gain and period identified by [`relay_autotune`], with the corresponding
+/// Ziegler-Nichols gains, in the parallel form `kp e + ki ∫e + kd de/dt`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RelayAutotuneResult {
+    pub ultimate_gain: f64,
+    /// Period of the oscillation, in seconds
+    pub ultimate_period: f64,
+
+    pub kp: f64,
+    pub ki: f64,
+    pub kd: f64,
+}
+
+impl RelayAutotuneResult {
+    fn ziegler_nichols(ultimate_gain: f64, ultimate_period: f64) -> Self {
+        let kp = 0.6 * ultimate_gain;
+        let ti = ultimate_period / 2.0;
+        let td = ultimate_period / 8.0;
+
+        RelayAutotuneResult {
+            ultimate_gain,
+            ultimate_period,
+            kp,
+            ki: kp / ti,
+            kd: kp * td,
+        }
+    }
+}
+
+/// Identifies the ultimate gain and period of a single loop with a relay experiment.
+///
+/// The relay replaces the controller of the loop: at each step it writes `params.control`
+/// depending on the sign of `reference - measurement`, until the measurement settles in
+/// a steady oscillation. The ultimate gain is then estimated from the amplitude of the
+/// oscillation with the describing function of the relay, `4 amplitude / (π a)`.
+///
+/// The system is stepped from its current state and is left where the experiment
+/// ended: use [`ControlSystem::reset`] before running it again.
+pub fn relay_autotune(
+    system: &mut ControlSystem,
+    params: &RelayAutotuneParams,
+) -> Result<RelayAutotuneResult> {
+    let reference = system.float_signal(&params.reference)?.clone();
+    let measurement = system.float_signal(&params.measurement)?.clone();
+
+    let control = system
+        .get_signal(&params.control)
+        .ok_or_else(|| ControlSystemError::UnknownSignalName(params.control.clone()))?
+        .clone();
+    if let Some(producer) = control.producer() {
+        return Err(ControlSystemError::AutotuneFailed(format!(
+            "control signal '{}' is produced by block '{}', it must be an external signal",
+            params.control, producer
+        )));
+    }
+
+    let mut relay_high = true;
+    let mut last_error = None;
+    let mut outputs = vec![];
+    // Times the relay switched high, with the index of the output at that step
+    let mut switches: Vec<(f64, usize)> = vec![];
+
+    for _ in 0..params.max_steps {
+        let step = system.step_info();
+        let u = if relay_high {
+            params.bias + params.amplitude
+        } else {
+            params.bias - params.amplitude
+        };
+        control.try_set(u)?;
+
+        let result = system.step()?;
+
+        let y = measurement.try_float_value()?;
+        let e = reference.try_float_value()? - y;
+        outputs.push(y);
+
+        let mut switched_high = false;
+        if relay_high && e < -params.hysteresis {
+            relay_high = false;
+        } else if !relay_high && e > params.hysteresis {
+            relay_high = true;
+            switched_high = true;
+
+            // Interpolate the instant the error crossed the band between the two steps
+            let t = match last_error {
+                Some(last_e) => step.t - step.dt * (e - params.hysteresis) / (e - last_e),
+                None => step.t,
+            };
+            switches.push((t, outputs.len() - 1));
+        }
+        last_error = Some(e);
+
+        // The oscillation is checked once per period, when the relay switches high
+        if switched_high && switches.len() > params.cycles {
+            let last = &switches[switches.len() - 1 - params.cycles..];
+            let periods: Vec<f64> = last.windows(2).map(|w| w[1].0 - w[0].0).collect();
+            let amplitudes: Vec<f64> = last
+                .windows(2)
+                .map(|w| half_range(&outputs[w[0].1..w[1].1]))
+                .collect();
+
+            if is_steady(&periods, params.tolerance) && is_steady(&amplitudes, params.tolerance) {
+                let a = mean(&amplitudes);
+                if a <= 0.0 {
+                    return Err(ControlSystemError::AutotuneFailed(format!(
+                        "signal '{}' does not oscillate",
+                        params.measurement
+                    )));
+                }
+
+                return Ok(RelayAutotuneResult::ziegler_nichols(
+                    4.0 * params.amplitude / (PI * a),
+                    mean(&periods),
+                ));
+            }
+        }
+
+        if result.is_stop() {
+            break;
+        }
+    }
+
+    Err(ControlSystemError::AutotuneFailed(format!(
+        "no steady oscillation of signal '{}' after {} relay switches",
+        params.measurement,
+        switches.len()
+    )))
+}
+
+fn half_range(values: &[f64]) -> f64 {
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    (max - min) / 2.0
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Whether all the values are within `tolerance` of each other, relative to their mean
+fn is_steady(values: &[f64], tolerance: f64) -> bool {
+    2.0 * half_range(values) <= tolerance * mean(values).abs()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+    use crate::{
+        io::{Input, Output},
+        Block, BlockIO, ControlSystemBuilder, ControlSystemParameters, StepInfo, StepResult,
+    };
+
+    /// Second order plant `1 / (s + 1)^2` with an input delay of `delay` steps,
+    /// integrated with forward Euler
+    #[derive(BlockIO)]
+    struct Plant {
+        #[blockio(block_name)]
+        name: String,
+
+        #[blockio(input)]
+        u: Input<f64>,
+
+        #[blockio(output)]
+        y: Output<f64>,
+
+        x: [f64; 2],
+        delayed: VecDeque<f64>,
+    }
+
+    impl Plant {
+        fn new(name: &str, delay: usize) -> Self {
+            Plant {
+                name: name.to_string(),
+                u: Input::default(),
+                y: Output::default(),
+                x: [0.0; 2],
+                delayed: VecDeque::from(vec![0.0; delay]),
+            }
+        }
+    }
+
+    impl Block for Plant {
+        fn step(&mut self, k: StepInfo) -> Result<StepResult> {
+            self.delayed.push_back(self.u.get());
+            let u = self.delayed.pop_front().unwrap();
+
+            self.x[0] += (u - self.x[0]) * k.dt;
+            self.x[1] += (self.x[0] - self.x[1]) * k.dt;
+            self.y.set(self.x[1]);
+
+            Ok(StepResult::Continue)
+        }
+    }
+
+    /// Frequency at which the phase of `e^(-delay s) / (s + 1)^2` is -180°
+    fn phase_crossover(delay: f64) -> f64 {
+        let phase = |w: f64| -2.0 * w.atan() - delay * w;
+
+        let (mut low, mut high) = (0.0, 100.0);
+        while high - low > 1e-12 {
+            let w = (low + high) / 2.0;
+            if phase(w) > -PI {
+                low = w;
+            } else {
+                high = w;
+            }
+        }
+        low
+    }
+
+    #[test]
+    fn relay_identifies_ultimate_period() {
+        let dt = 0.001;
+        let delay = 0.5;
+
+        let mut builder = ControlSystemBuilder::default();
+        builder.add_external_signal::<f64>("r").unwrap();
+        builder.add_external_signal::<f64>("u").unwrap();
+        builder
+            .add_block(
+                Plant::new("plant", (delay / dt) as usize),
+                &[("u", "u")],
+                &[("y", "y")],
+            )
+            .unwrap();
+        builder.set_initial("r", 0.0).unwrap();
+        let mut system = builder
+            .build("relay", ControlSystemParameters::new(dt))
+            .unwrap();
+
+        let params = RelayAutotuneParams::new("r", "y", "u", 1.0);
+        let result = relay_autotune(&mut system, &params).unwrap();
+
+        let w = phase_crossover(delay);
+        let period = 2.0 * PI / w;
+        let ultimate_gain = 1.0 + w * w;
+
+        // The describing function is an approximation, good to a few percent on a
+        // low-pass plant
+        assert!(
+            (result.ultimate_period - period).abs() < 0.03 * period,
+            "{} != {period}",
+            result.ultimate_period
+        );
+        assert!(
+            (result.ultimate_gain - ultimate_gain).abs() < 0.1 * ultimate_gain,
+            "{} != {ultimate_gain}",
+            result.ultimate_gain
+        );
+        assert_eq!(result.kp, 0.6 * result.ultimate_gain);
+    }
+}
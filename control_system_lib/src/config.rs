@@ -0,0 +1,287 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{BlockRegistry, ControlSystemBuilder, ControlSystemError, Result};
+
+/// Topology of a control system: its blocks, the signals connected to their ports and
+/// the external signals written from the outside, with their type
+///
+/// In TOML:
+/// ```toml
+/// [external_signals]
+/// setpoint = "f64"
+///
+/// [[blocks]]
+/// type = "Constant"
+/// name = "const_1"
+/// params = { c = 1.0 }
+/// outputs = { y = "one" }
+///
+/// [[blocks]]
+/// type = "Print"
+/// name = "print"
+/// inputs = { u = "one" }
+/// ```
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct SystemConfig {
+    /// Type of each external signal, see [`ControlSystemBuilder::add_external_signal`].
+    /// Only primitive numeric types and `bool` are supported.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub external_signals: BTreeMap<String, String>,
+    #[serde(default)]
+    pub blocks: Vec<BlockConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BlockConfig {
+    /// Name the block type is registered with in the [`BlockRegistry`]
+    #[serde(rename = "type")]
+    pub block_type: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub params: Value,
+    /// Signal connected to each input port
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub inputs: BTreeMap<String, String>,
+    /// Signal connected to each output port
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub outputs: BTreeMap<String, String>,
+}
+
+impl ControlSystemBuilder {
+    /// Creates the blocks described by the TOML file at `path` with `registry`, and
+    /// connects them. See [`SystemConfig`] for the format.
+    pub fn from_config(path: &Path, registry: &BlockRegistry) -> Result<Self> {
+        let config = fs::read_to_string(path).map_err(ControlSystemError::from_boxed)?;
+        Self::from_config_str(&config, registry)
+    }
+
+    /// Same as [`ControlSystemBuilder::from_config`], reading the TOML from a string
+    pub fn from_config_str(config: &str, registry: &BlockRegistry) -> Result<Self> {
+        let config: SystemConfig =
+            toml::from_str(config).map_err(ControlSystemError::from_boxed)?;
+        Self::from_system_config(&config, registry)
+    }
+
+    pub fn from_system_config(config: &SystemConfig, registry: &BlockRegistry) -> Result<Self> {
+        let mut builder = ControlSystemBuilder::default();
+
+        for (signal, typename) in config.external_signals.iter() {
+            add_external_signal(&mut builder, signal, typename)?;
+        }
+
+        for block in config.blocks.iter() {
+            let inputs: Vec<(&str, &str)> = block
+                .inputs
+                .iter()
+                .map(|(port, signal)| (port.as_str(), signal.as_str()))
+                .collect();
+            let outputs: Vec<(&str, &str)> = block
+                .outputs
+                .iter()
+                .map(|(port, signal)| (port.as_str(), signal.as_str()))
+                .collect();
+
+            builder.add_registered_block(
+                registry,
+                &block.block_type,
+                &block.name,
+                block.params.clone(),
+                &inputs,
+                &outputs,
+            )?;
+        }
+
+        Ok(builder)
+    }
+
+    /// Exports the wiring of the system as TOML, in the format read by
+    /// [`ControlSystemBuilder::from_config`].
+    ///
+    /// Only blocks added with [`ControlSystemBuilder::add_registered_block`] (and so
+    /// loaded from a configuration) have a known type and parameters: the other ones are
+    /// exported with their Rust type name and no parameters.
+    ///
+    /// Fails if an external signal carries a type not supported in configurations, or
+    /// if the parameters of a block contain null values inside arrays, which TOML can't
+    /// represent.
+    pub fn to_config(&self) -> Result<String> {
+        let mut config = self.to_system_config();
+
+        for (signal, typename) in config.external_signals.iter() {
+            if !CONFIG_SIGNAL_TYPES.contains(&typename.as_str()) {
+                return Err(ControlSystemError::UnsupportedSignalType {
+                    signal: signal.clone(),
+                    typename: typename.clone(),
+                });
+            }
+        }
+
+        // TOML has no null: absent fields are read back as `None` anyway
+        for block in config.blocks.iter_mut() {
+            remove_null_fields(&mut block.params);
+        }
+
+        toml::to_string(&config).map_err(ControlSystemError::from_boxed)
+    }
+}
+
+/// Generates the list of the types supported for external signals in configurations,
+/// and the function declaring them from their name
+macro_rules! config_signal_types {
+    ($($t:ty),*) => {
+        const CONFIG_SIGNAL_TYPES: &[&str] = &[$(stringify!($t)),*];
+
+        fn add_external_signal(
+            builder: &mut ControlSystemBuilder,
+            signal: &str,
+            typename: &str,
+        ) -> Result<()> {
+            match typename {
+                $(stringify!($t) => builder.add_external_signal::<$t>(signal).map(|_| ()),)*
+                _ => Err(ControlSystemError::UnsupportedSignalType {
+                    signal: signal.to_string(),
+                    typename: typename.to_string(),
+                }),
+            }
+        }
+    };
+}
+
+config_signal_types!(f64, f32, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, bool);
+
+/// Removes the null fields of the objects in `value`. Nulls inside arrays are kept, as
+/// removing them would shift the following elements.
+fn remove_null_fields(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|_, v| !v.is_null());
+            map.values_mut().for_each(remove_null_fields);
+        }
+        Value::Array(values) => values.iter_mut().for_each(remove_null_fields),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        io::{Input, Output},
+        Block, BlockIO, StepInfo, StepResult,
+    };
+    use serde_json::json;
+
+    /// Copies its input, accepting any parameters
+    #[derive(BlockIO)]
+    struct Copy {
+        #[blockio(block_name)]
+        name: String,
+
+        #[blockio(input)]
+        u: Input<f64>,
+
+        #[blockio(output)]
+        y: Output<f64>,
+    }
+
+    impl Block for Copy {
+        fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+            self.y.set(self.u.get());
+            Ok(StepResult::Continue)
+        }
+    }
+
+    fn registry() -> BlockRegistry {
+        let mut registry = BlockRegistry::new();
+        registry.register("Copy", |name, _| {
+            Ok(Box::new(Copy {
+                name: name.to_string(),
+                u: Input::default(),
+                y: Output::default(),
+            }))
+        });
+        registry
+    }
+
+    const CONFIG: &str = r#"
+        [external_signals]
+        u = "f64"
+
+        [[blocks]]
+        type = "Copy"
+        name = "copy"
+        params = { gain = 2.0, limits = [1.0, 3.0], filter = { order = 2 } }
+        inputs = { u = "u" }
+        outputs = { y = "y" }
+    "#;
+
+    #[test]
+    fn config_roundtrip() {
+        let registry = registry();
+        let builder = ControlSystemBuilder::from_config_str(CONFIG, &registry).unwrap();
+
+        let exported = builder.to_config().unwrap();
+        let reloaded = ControlSystemBuilder::from_config_str(&exported, &registry).unwrap();
+
+        assert_eq!(reloaded.to_system_config(), builder.to_system_config());
+        assert_eq!(
+            builder.to_system_config().external_signals,
+            BTreeMap::from([("u".to_string(), "f64".to_string())])
+        );
+    }
+
+    #[test]
+    fn unsupported_external_signal_type() {
+        let config = CONFIG.replace(r#"u = "f64""#, r#"u = "Vec<f64>""#);
+        assert!(matches!(
+            ControlSystemBuilder::from_config_str(&config, &registry()),
+            Err(ControlSystemError::UnsupportedSignalType { .. })
+        ));
+
+        let mut builder = ControlSystemBuilder::from_config_str(CONFIG, &registry()).unwrap();
+        builder.add_external_signal::<Vec<f64>>("v").unwrap();
+        assert!(matches!(
+            builder.to_config(),
+            Err(ControlSystemError::UnsupportedSignalType { signal, .. }) if signal == "v"
+        ));
+    }
+
+    #[test]
+    fn null_params() {
+        let registry = registry();
+        let mut builder = ControlSystemBuilder::default();
+        builder.add_external_signal::<f64>("u").unwrap();
+        builder
+            .add_registered_block(
+                &registry,
+                "Copy",
+                "copy",
+                json!({ "gain": null, "filter": { "order": null } }),
+                &[("u", "u")],
+                &[("y", "y")],
+            )
+            .unwrap();
+
+        let exported = builder.to_config().unwrap();
+        let reloaded = ControlSystemBuilder::from_config_str(&exported, &registry).unwrap();
+        assert_eq!(
+            reloaded.to_system_config().blocks[0].params,
+            json!({ "filter": {} })
+        );
+
+        builder
+            .add_registered_block(
+                &registry,
+                "Copy",
+                "copy_2",
+                json!({ "limits": [1.0, null] }),
+                &[("u", "u")],
+                &[("y", "y2")],
+            )
+            .unwrap();
+        assert!(builder.to_config().is_err());
+    }
+}
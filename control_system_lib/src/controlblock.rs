@@ -1,26 +1,105 @@
-use crate::{io::AnySignal, Result};
-use std::collections::HashMap;
+use crate::{
+    io::{AnySignal, SignalType},
+    ControlSystemError, Result,
+};
+use serde_json::Value;
+use std::{any::Any, collections::HashMap};
+
+/// Copy of the internal state of a block, see [`Block::save_state`]
+pub type BlockState = Box<dyn Any>;
 
 pub trait BlockIO {
     fn name(&self) -> String;
 
     fn input_signals(&mut self) -> HashMap<String, &mut Option<AnySignal>>;
     fn output_signals(&mut self) -> HashMap<String, &mut AnySignal>;
+
+    /// Names of the input ports, in the order they are declared
+    fn input_port_names(&self) -> Vec<String>;
+
+    /// Names of the output ports, in the order they are declared
+    fn output_port_names(&self) -> Vec<String>;
+
+    /// Names of the input ports that may be left unconnected
+    fn optional_inputs(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// Names of the output ports that may be left unconnected. The values written to
+    /// them are then discarded.
+    fn optional_outputs(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// Types read by the input ports, checked against the connected signals while
+    /// wiring the system. Ports missing from the map are not checked.
+    fn input_types(&self) -> HashMap<String, SignalType> {
+        HashMap::new()
+    }
 }
 
 pub trait Block: BlockIO {
     /// Propagates the block forward by one step
     fn step(&mut self, k: StepInfo) -> Result<StepResult>;
 
+    /// Restores the initial state of the block, as it was before the first step
+    fn reset(&mut self) {}
+
+    /// Copy of the internal state of the block, restored by [`Block::load_state`].
+    /// Stateless blocks return None.
+    fn save_state(&self) -> Option<BlockState> {
+        None
+    }
+
+    /// Restores a state previously returned by [`Block::save_state`], usually
+    /// downcast with [`downcast_state`]. Fails for blocks that don't save their state.
+    fn load_state(&mut self, _state: &dyn Any) -> Result<()> {
+        Err(ControlSystemError::InvalidState {
+            blockname: self.name(),
+            reason: "the block has no state to restore".to_string(),
+        })
+    }
+
+    /// Current parameters of the block, in the same format they are read from the
+    /// configuration. None for blocks without parameters.
+    fn get_params(&self) -> Option<Value> {
+        None
+    }
+
+    /// Replaces the parameters of the block while the system is running, taking
+    /// effect from the next step. The internal state of the block is preserved.
+    fn set_params(&mut self, _params: Value) -> Result<()> {
+        Err(ControlSystemError::ParamsNotSupported(self.name()))
+    }
+
     fn delay(&self) -> u32 {
         0
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Downcasts a state given to [`Block::load_state`] of block `name` to the type `S`
+/// saved by the block
+pub fn downcast_state<'a, S: 'static>(name: &str, state: &'a dyn Any) -> Result<&'a S> {
+    state
+        .downcast_ref::<S>()
+        .ok_or_else(|| ControlSystemError::InvalidState {
+            blockname: name.to_string(),
+            reason: format!("expected a state of type {}", std::any::type_name::<S>()),
+        })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum StepResult {
     Continue,
     Stop,
+    /// Stop, providing a description of why the system was halted
+    StopWithReason(String),
+}
+
+impl StepResult {
+    pub fn is_stop(&self) -> bool {
+        !matches!(self, StepResult::Continue)
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
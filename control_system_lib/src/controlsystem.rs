@@ -1,18 +1,32 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    any::Any,
+    collections::{hash_map::Entry, HashMap, VecDeque},
+    ops::Range,
+    time::{Duration, Instant},
+};
 
-use petgraph::{algo::toposort, dot::Dot, prelude::NodeIndex, Graph};
+use petgraph::{
+    algo::{has_path_connecting, tarjan_scc, toposort},
+    dot::Dot,
+    prelude::NodeIndex,
+    Graph,
+};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 use crate::{
-    controlblock::{Block, StepInfo, StepResult},
-    io::AnySignal,
-    ControlSystemError, ParameterStore, Result,
+    config::{BlockConfig, SystemConfig},
+    controlblock::{Block, BlockIO, BlockState, StepInfo, StepResult},
+    io::{AnySignal, SignalType},
+    BlockRegistry, ControlSystemError, ParameterStore, Result, TypeMismatch, UnitMismatch,
 };
 
 pub struct ControlSystem {
     name: String,
-    #[allow(unused)]
     signals: HashMap<String, AnySignal>,
+    /// Values of the signals when the system was built, restored by
+    /// [`ControlSystem::reset`]
+    initial_signals: HashMap<String, AnySignal>,
     blocks: Vec<Box<dyn Block>>,
     #[allow(unused)]
     graph: Graph<String, String>,
@@ -21,12 +35,85 @@ pub struct ControlSystem {
     params: ControlSystemParameters,
 
     step: StepInfo,
+    check_finite: bool,
+    loops: Vec<AlgebraicLoop>,
+    /// Time spent in the step of each block, in execution order. None if profiling is
+    /// disabled.
+    profile: Option<Vec<Duration>>,
+    /// Time given to the last call to [`ControlSystem::step_at`]
+    external_time: Option<f64>,
 }
+
 #[derive(Serialize, Deserialize)]
 pub struct ControlSystemParameters {
     pub dt: f64,
     /// Maximum number of iterations. 0 for unlimited
-    pub max_iter: usize, 
+    pub max_iter: usize,
+    /// Simulation time after which the system is stopped. None for unlimited
+    #[serde(default)]
+    pub max_time: Option<f64>,
+    /// Solver for algebraic loops. None to reject them when building the system
+    #[serde(default)]
+    pub algebraic_loops: Option<AlgebraicLoopParams>,
+}
+
+/// Settings of the fixed-point iteration used to solve algebraic loops, ie. cycles
+/// of blocks with no delay
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+///
+/// Every iteration restarts the blocks of the loop from the state they saved with
+/// [`Block::save_state`] at the beginning of the step. Blocks that do not save their
+/// state are stepped up to `max_iter` times per step instead, and should not
+/// accumulate anything in [`Block::step`].
+pub struct AlgebraicLoopParams {
+    /// Maximum number of iterations in a single step
+    pub max_iter: usize,
+    /// Largest change of the signals of the loop between two iterations for the loop to
+    /// be considered solved. Only floating point scalars and [`nalgebra::DVector`]s
+    /// are compared: building fails if a loop produces none of them.
+    pub tolerance: f64,
+}
+
+impl ControlSystemParameters {
+    /// Parameters with step `dt`, running until a block stops the system
+    pub fn new(dt: f64) -> Self {
+        ControlSystemParameters {
+            dt,
+            max_iter: 0,
+            max_time: None,
+            algebraic_loops: None,
+        }
+    }
+
+    pub fn with_max_iter(mut self, max_iter: usize) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    pub fn with_max_time(mut self, max_time: f64) -> Self {
+        self.max_time = Some(max_time);
+        self
+    }
+
+    pub fn with_algebraic_loops(mut self, params: AlgebraicLoopParams) -> Self {
+        self.algebraic_loops = Some(params);
+        self
+    }
+}
+
+/// Values of the signals and state of the blocks of a [`ControlSystem`] at some step,
+/// see [`ControlSystem::snapshot`]
+pub struct StateSnapshot {
+    step: StepInfo,
+    signals: HashMap<String, AnySignal>,
+    blocks: HashMap<String, Option<BlockState>>,
+}
+
+impl StateSnapshot {
+    /// Step that the system will execute next when restored
+    pub fn step(&self) -> StepInfo {
+        self.step
+    }
 }
 
 impl ControlSystem {
@@ -34,63 +121,719 @@ impl ControlSystem {
         &self.name
     }
 
-    pub fn step(&mut self) -> Result<StepResult> {
-        let mut stop = false;
+    pub(crate) fn get_signal(&self, name: &str) -> Option<&AnySignal> {
+        self.signals.get(name)
+    }
+
+    /// The signal named `name`, failing if it does not exist or does not carry
+    /// floating point values
+    pub(crate) fn float_signal(&self, name: &str) -> Result<&AnySignal> {
+        let signal = self
+            .get_signal(name)
+            .ok_or_else(|| ControlSystemError::UnknownSignalName(name.to_string()))?;
+
+        if !signal.is_float() {
+            return Err(ControlSystemError::TypeError {
+                signal: name.to_string(),
+                typename: "f64".to_string(),
+                signal_typename: signal.signal_type_name().to_string(),
+                producer: signal.producer().map(str::to_string),
+            });
+        }
+
+        Ok(signal)
+    }
+
+    /// Step that will be executed next
+    pub(crate) fn step_info(&self) -> StepInfo {
+        self.step
+    }
+
+    /// When enabled, every step fails with an error if a floating point signal
+    /// becomes NaN or infinite. Disabled by default.
+    pub fn set_check_finite(&mut self, enabled: bool) {
+        self.check_finite = enabled;
+    }
+
+    /// When enabled, the time spent in the step of each block is measured, see
+    /// [`ControlSystem::profile`]. Disabled by default. Enabling it clears the times
+    /// measured so far.
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profile = enabled.then(|| vec![Duration::ZERO; self.blocks.len()]);
+    }
+
+    /// Total time spent in the step of each block since profiling was enabled, in
+    /// execution order. Empty if profiling is disabled.
+    pub fn profile(&self) -> Vec<(String, Duration)> {
+        self.profile
+            .iter()
+            .flat_map(|profile| self.blocks.iter().map(|b| b.name()).zip(profile.clone()))
+            .collect()
+    }
+
+    /// Names of the blocks, in the order they are stepped
+    pub fn execution_order(&self) -> Vec<String> {
+        self.blocks.iter().map(|b| b.name()).collect()
+    }
+
+    /// Delay introduced by each block, in steps, in execution order
+    pub fn block_delays(&self) -> Vec<(String, u32)> {
+        self.blocks.iter().map(|b| (b.name(), b.delay())).collect()
+    }
+
+    /// Sum of the delays of all the blocks, in steps
+    pub fn total_delay(&self) -> u32 {
+        self.blocks.iter().map(|b| b.delay()).sum()
+    }
+
+    /// Current parameters of the block named `name`, see [`Block::get_params`]
+    pub fn block_params(&self, name: &str) -> Result<Option<Value>> {
+        self.blocks
+            .iter()
+            .find(|b| b.name() == name)
+            .map(|b| b.get_params())
+            .ok_or_else(|| ControlSystemError::UnknownBlock(name.to_string()))
+    }
+
+    /// Changes the parameters of the block named `name`, see [`Block::set_params`]
+    pub fn set_block_params(&mut self, name: &str, params: Value) -> Result<()> {
+        self.blocks
+            .iter_mut()
+            .find(|b| b.name() == name)
+            .ok_or_else(|| ControlSystemError::UnknownBlock(name.to_string()))?
+            .set_params(params)
+    }
+
+    /// Resets every block to its initial state and restarts the simulation from t = 0,
+    /// to run another trial without building the system again. The signals get back
+    /// the values they had when the system was built.
+    pub fn reset(&mut self) {
+        for b in self.blocks.iter_mut() {
+            b.reset();
+        }
+
+        for (name, initial) in self.initial_signals.iter() {
+            // Both copies come from the same signal, so their types always match
+            self.signals[name].copy_from(initial).unwrap();
+        }
+
+        self.step = StepInfo::new(self.params.dt);
+        self.external_time = None;
+    }
+
+    /// Captures the current state of the system, to be later resumed with
+    /// [`ControlSystem::restore`]. The state of the blocks is captured through
+    /// [`Block::save_state`].
+    pub fn snapshot(&self) -> StateSnapshot {
+        StateSnapshot {
+            step: self.step,
+            signals: self
+                .signals
+                .iter()
+                .map(|(name, signal)| (name.clone(), signal.duplicate()))
+                .collect(),
+            blocks: self
+                .blocks
+                .iter()
+                .map(|b| (b.name(), b.save_state()))
+                .collect(),
+        }
+    }
+
+    /// Brings the system back to the state captured by `snap`, which must have been
+    /// taken from this system or from an identical one
+    pub fn restore(&mut self, snap: &StateSnapshot) -> Result<()> {
+        for (name, value) in snap.signals.iter() {
+            self.signals
+                .get(name)
+                .ok_or(ControlSystemError::UnknownSignalName(name.clone()))?
+                .copy_from(value)?;
+        }
+
         for b in self.blocks.iter_mut() {
-            // In case of stop, complete this step and return it
-            stop = stop || b.step(self.step)? == StepResult::Stop;
+            let name = b.name();
+            let state = snap
+                .blocks
+                .get(&name)
+                .ok_or(ControlSystemError::UnknownBlock(name.clone()))?;
+
+            if let Some(state) = state {
+                b.load_state(state.as_ref())?;
+            }
+        }
+
+        self.step = snap.step;
+        self.external_time = None;
+        Ok(())
+    }
+
+    /// Runs up to `steps` steps and records the value of each signal in `signals` after
+    /// every step. Stops early if the system stops, after recording the last step.
+    ///
+    /// The signals must carry `f64` or `f32` values, and be written by the first step.
+    pub fn simulate(
+        &mut self,
+        steps: usize,
+        signals: &[&str],
+    ) -> Result<HashMap<String, Vec<f64>>> {
+        let recorded: Vec<AnySignal> = signals
+            .iter()
+            .map(|name| self.float_signal(name).cloned())
+            .collect::<Result<_>>()?;
+
+        let mut values: Vec<Vec<f64>> = vec![Vec::with_capacity(steps); signals.len()];
+        for _ in 0..steps {
+            let result = self.step()?;
+
+            for (signal, values) in recorded.iter().zip(values.iter_mut()) {
+                values.push(signal.try_float_value()?);
+            }
+
+            if result.is_stop() {
+                break;
+            }
+        }
+
+        Ok(signals
+            .iter()
+            .map(|name| name.to_string())
+            .zip(values)
+            .collect())
+    }
+
+    /// Runs a step at the absolute time `t` given by the caller, eg. another simulation
+    /// engine, instead of advancing the time by `dt`. The blocks are given the time
+    /// elapsed since the previous call as step size, or the current one on the first
+    /// call. Later calls to [`ControlSystem::step`] advance the time by `dt` again.
+    ///
+    /// Fails without stepping if `t` is not finite or not after the time of the
+    /// previous call.
+    pub fn step_at(&mut self, t: f64) -> Result<StepResult> {
+        if !t.is_finite() {
+            return Err(ControlSystemError::NonFiniteTime(t));
+        }
+
+        let mut step = self.step;
+        if let Some(previous) = self.external_time {
+            if t <= previous {
+                return Err(ControlSystemError::NonMonotonicTime { t, previous });
+            }
+
+            step.dt = t - previous;
+        }
+
+        step.t = t;
+        self.external_time = Some(t);
+        self.run_step(step)
+    }
+
+    pub fn step(&mut self) -> Result<StepResult> {
+        self.run_step(self.step)
+    }
+
+    /// Runs a step of a system nested in another one, with the time and step size of
+    /// the parent
+    pub(crate) fn step_nested(&mut self, step: StepInfo) -> Result<StepResult> {
+        self.run_step(step)
+    }
+
+    /// Steps the blocks with `step`, then prepares the next step after it keeping
+    /// the step size of the system
+    fn run_step(&mut self, step: StepInfo) -> Result<StepResult> {
+        let mut stop = None;
+        let mut i = 0;
+        while i < self.blocks.len() {
+            let results = match self.loops.iter().find(|l| l.blocks.start == i) {
+                Some(algebraic_loop) => {
+                    i = algebraic_loop.blocks.end;
+                    algebraic_loop.solve(
+                        &mut self.blocks[algebraic_loop.blocks.clone()],
+                        step,
+                        self.params.algebraic_loops.as_ref().unwrap(),
+                        self.profile
+                            .as_mut()
+                            .map(|profile| &mut profile[algebraic_loop.blocks.clone()]),
+                    )?
+                }
+                None => {
+                    i += 1;
+                    vec![step_block(
+                        self.blocks[i - 1].as_mut(),
+                        step,
+                        self.profile.as_mut().map(|profile| &mut profile[i - 1]),
+                    )?]
+                }
+            };
+
+            // In case of stop, complete this step and return the first stop reason
+            for result in results {
+                if stop.is_none() && result.is_stop() {
+                    stop = Some(result);
+                }
+            }
+        }
+
+        if self.check_finite {
+            self.check_signals_finite()?;
         }
 
-        self.step.k += 1;
-        self.step.t += self.step.dt;
+        self.step.k = step.k + 1;
+        self.step.t = step.t + step.dt;
 
-        if stop || (self.params.max_iter > 0 && self.step.k > self.params.max_iter) {
-            Ok(StepResult::Stop)
+        if let Some(stop) = stop {
+            Ok(stop)
+        } else if self.params.max_iter > 0 && self.step.k > self.params.max_iter {
+            Ok(StepResult::StopWithReason(format!(
+                "Reached the maximum number of iterations ({})",
+                self.params.max_iter
+            )))
+        } else if self.reached_max_time() {
+            Ok(StepResult::StopWithReason(format!(
+                "Reached the maximum simulation time ({} s)",
+                self.params.max_time.unwrap()
+            )))
         } else {
             Ok(StepResult::Continue)
         }
     }
 }
 
+impl ControlSystem {
+    fn reached_max_time(&self) -> bool {
+        // Tolerate the rounding errors accumulated while summing the steps
+        self.params
+            .max_time
+            .is_some_and(|max_time| self.step.t >= max_time - self.step.dt * 1e-9)
+    }
+
+    fn check_signals_finite(&self) -> Result<()> {
+        let non_finite = self
+            .signals
+            .iter()
+            .filter(|(_, signal)| signal.is_non_finite())
+            .map(|(name, _)| name)
+            .min();
+
+        match non_finite {
+            Some(signal) => Err(ControlSystemError::NonFiniteSignal {
+                signal: signal.clone(),
+                k: self.step.k,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Blocks forming an algebraic loop, solved by fixed-point iteration
+struct AlgebraicLoop {
+    /// Position of the blocks of the loop in the execution order
+    blocks: Range<usize>,
+    /// Floating point scalar and vector signals produced by the loop, checked for
+    /// convergence
+    signals: Vec<AnySignal>,
+}
+
+impl AlgebraicLoop {
+    /// Steps the blocks of the loop until the signals they produce stop changing
+    /// (Gauss-Seidel iteration). Every iteration starts from the state the blocks had
+    /// at the beginning of the step, restored with [`Block::load_state`].
+    fn solve(
+        &self,
+        blocks: &mut [Box<dyn Block>],
+        k: StepInfo,
+        params: &AlgebraicLoopParams,
+        mut profile: Option<&mut [Duration]>,
+    ) -> Result<Vec<StepResult>> {
+        let states: Vec<Option<BlockState>> = blocks.iter().map(|b| b.save_state()).collect();
+        let mut previous: Vec<Option<Vec<f64>>> =
+            self.signals.iter().map(AnySignal::float_values).collect();
+
+        for iteration in 0..params.max_iter {
+            if iteration > 0 {
+                for (b, state) in blocks.iter_mut().zip(states.iter()) {
+                    if let Some(state) = state {
+                        b.load_state(state.as_ref())?;
+                    }
+                }
+            }
+
+            let results = blocks
+                .iter_mut()
+                .enumerate()
+                .map(|(j, b)| {
+                    let time = profile.as_deref_mut().map(|profile| &mut profile[j]);
+                    step_block(b.as_mut(), k, time)
+                })
+                .collect::<Result<Vec<StepResult>>>()?;
+
+            let current: Vec<Option<Vec<f64>>> =
+                self.signals.iter().map(AnySignal::float_values).collect();
+
+            let converged = previous
+                .iter()
+                .zip(current.iter())
+                .all(|(p, c)| match (p, c) {
+                    (Some(p), Some(c)) => {
+                        p.len() == c.len()
+                            && p.iter()
+                                .zip(c)
+                                .all(|(p, c)| (c - p).abs() <= params.tolerance)
+                    }
+                    _ => false,
+                });
+
+            if converged {
+                return Ok(results);
+            }
+
+            previous = current;
+        }
+
+        Err(ControlSystemError::AlgebraicLoopNotConverged {
+            blocks: blocks.iter().map(|b| b.name()).collect(),
+            iterations: params.max_iter,
+            k: k.k,
+        })
+    }
+}
+
+/// Steps `block`, adding the time taken to `time` if given
+fn step_block(
+    block: &mut dyn Block,
+    k: StepInfo,
+    time: Option<&mut Duration>,
+) -> Result<StepResult> {
+    let Some(time) = time else {
+        return block.step(k);
+    };
+
+    let start = Instant::now();
+    let result = block.step(k);
+    *time += start.elapsed();
+    result
+}
+
 struct BlockData {
     block: Box<dyn Block>,
+    /// Signals the inputs are connected to, by port
     registered_inputs: HashMap<String, String>,
     registered_outputs: HashMap<String, String>,
+    /// Units expected by the inputs, by port
+    input_units: HashMap<String, String>,
+    /// Input ports that must be connected before building, sorted
+    required_inputs: Vec<String>,
+    /// Output ports that must be connected before building, sorted
+    required_outputs: Vec<String>,
+    /// Type the block was created as and its parameters, see [`BlockConfig`]
+    block_type: String,
+    params: Value,
 }
 
 #[derive(Default)]
 pub struct ControlSystemBuilder {
     signals: HashMap<String, AnySignal>,
     blocks: HashMap<String, BlockData>,
+    /// Names of the blocks reading each signal, to find the consumers of a signal
+    /// without going through all the blocks
+    consumers: HashMap<String, Vec<String>>,
+    /// Alternative names of signals, see [`ControlSystemBuilder::alias_signal`]
+    aliases: HashMap<String, String>,
 }
 
 impl ControlSystemBuilder {
+    /// Adds a block to the system, connecting its ports to the provided signals.
+    ///
+    /// Ports can also be connected later with [`ControlSystemBuilder::connect_input`] and
+    /// [`ControlSystemBuilder::connect_output`]: all non-optional ports must be connected
+    /// by the time the system is built.
     pub fn add_block<T: Block + 'static>(
         &mut self,
         block: T,
         input_connections: &[(&str, &str)],
         output_connections: &[(&str, &str)],
     ) -> Result<&mut Self, ControlSystemError> {
-        let name = block.name();
+        let block_type = std::any::type_name::<T>().to_string();
+        self.insert_block(
+            Box::new(block),
+            block_type,
+            Value::Null,
+            input_connections,
+            output_connections,
+        )
+    }
 
-        if self.blocks.contains_key(&name) {
-            return Err(ControlSystemError::DuplicateBlockName(name));
-        }
+    /// Same as [`ControlSystemBuilder::add_block`], for blocks whose type is only known
+    /// at runtime, eg. created by a [`crate::BlockRegistry`]
+    pub fn add_boxed_block(
+        &mut self,
+        block: Box<dyn Block>,
+        input_connections: &[(&str, &str)],
+        output_connections: &[(&str, &str)],
+    ) -> Result<&mut Self, ControlSystemError> {
+        let block_type = std::any::type_name_of_val(block.as_ref()).to_string();
+        self.insert_block(
+            block,
+            block_type,
+            Value::Null,
+            input_connections,
+            output_connections,
+        )
+    }
 
-        let mut block_data = BlockData {
+    /// Creates a block of type `block_type` with `registry` and adds it to the system.
+    /// Unlike the other blocks, its type and parameters are known when exporting the
+    /// system with [`ControlSystemBuilder::to_config`].
+    pub fn add_registered_block(
+        &mut self,
+        registry: &BlockRegistry,
+        block_type: &str,
+        name: &str,
+        params: Value,
+        input_connections: &[(&str, &str)],
+        output_connections: &[(&str, &str)],
+    ) -> Result<&mut Self, ControlSystemError> {
+        let block = registry.create(block_type, name, params.clone())?;
+        self.insert_block(
+            block,
+            block_type.to_string(),
+            params,
+            input_connections,
+            output_connections,
+        )
+    }
+
+    /// Same as [`ControlSystemBuilder::add_block`], prepending `prefix` to the name of
+    /// the block and to the signals it is connected to, so that the same group of
+    /// blocks can be added more than once without name collisions. The prefix is
+    /// prepended as is, eg. `"left/"` turns the block `pid` into `left/pid`.
+    ///
+    /// Ports connected afterwards, with [`ControlSystemBuilder::connect_input`] or
+    /// [`ControlSystemBuilder::connect_output`], take the prefixed block name and the
+    /// full signal name, eg. to read a signal shared between the copies.
+    pub fn add_block_prefixed<T: Block + 'static>(
+        &mut self,
+        prefix: &str,
+        block: T,
+        input_connections: &[(&str, &str)],
+        output_connections: &[(&str, &str)],
+    ) -> Result<&mut Self, ControlSystemError> {
+        let block_type = std::any::type_name::<T>().to_string();
+        let block = PrefixedBlock {
+            name: format!("{prefix}{}", block.name()),
             block: Box::new(block),
-            registered_inputs: HashMap::new(),
-            registered_outputs: HashMap::new(),
         };
 
-        self.connect_inputs(&mut block_data, input_connections)?;
-        self.connect_outputs(&mut block_data, output_connections)?;
+        let prefixed = |connections: &[(&str, &str)]| -> Vec<(String, String)> {
+            connections
+                .iter()
+                .map(|(port, signal)| (port.to_string(), format!("{prefix}{signal}")))
+                .collect()
+        };
+        let inputs = prefixed(input_connections);
+        let outputs = prefixed(output_connections);
 
-        self.blocks.insert(block_data.block.name(), block_data);
+        self.insert_block(
+            Box::new(block),
+            block_type,
+            Value::Null,
+            &inputs
+                .iter()
+                .map(|(port, signal)| (port.as_str(), signal.as_str()))
+                .collect::<Vec<_>>(),
+            &outputs
+                .iter()
+                .map(|(port, signal)| (port.as_str(), signal.as_str()))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Wiring of the system, as loaded by [`ControlSystemBuilder::from_system_config`].
+    /// Blocks are sorted by name.
+    pub fn to_system_config(&self) -> SystemConfig {
+        let mut blocks: Vec<(&String, &BlockData)> = self.blocks.iter().collect();
+        blocks.sort_by_key(|(name, _)| *name);
+
+        SystemConfig {
+            external_signals: self
+                .signals
+                .iter()
+                .filter(|(_, signal)| signal.producer().is_none())
+                .map(|(name, signal)| (name.clone(), signal.signal_type_name().to_string()))
+                .collect(),
+            blocks: blocks
+                .into_iter()
+                .map(|(name, data)| BlockConfig {
+                    block_type: data.block_type.clone(),
+                    name: name.clone(),
+                    params: data.params.clone(),
+                    inputs: data
+                        .registered_inputs
+                        .iter()
+                        .map(|(port, signal)| (port.clone(), signal.clone()))
+                        .collect(),
+                    outputs: data
+                        .registered_outputs
+                        .iter()
+                        .map(|(port, signal)| (port.clone(), signal.clone()))
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn has_block(&self, name: &str) -> bool {
+        self.blocks.contains_key(name)
+    }
+
+    /// Names of the blocks added so far, sorted alphabetically
+    pub fn block_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.blocks.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Graph of the blocks added so far in the DOT format of Graphviz, with an edge
+    /// from each producer to each consumer of its signals
+    pub fn to_dot(&self) -> String {
+        Dot::new(&self.build_graph(true)).to_string()
+    }
+
+    /// Declares a signal that is not produced by any block of this system, but is
+    /// written from the outside, eg. by a parent [`crate::SubSystem`].
+    pub fn add_external_signal<T: Clone + 'static>(&mut self, signal: &str) -> Result<&mut Self> {
+        if self.signals.contains_key(signal) || self.aliases.contains_key(signal) {
+            return Err(ControlSystemError::DuplicateSignal(signal.to_string()));
+        }
+
+        let mut any_signal = AnySignal::new::<T>();
+        any_signal.set_name(signal);
+        self.signals.insert(signal.to_string(), any_signal);
+
+        Ok(self)
+    }
+
+    /// Sets the value read from `signal` before its producer runs for the first time.
+    /// The signal must have already been connected to an output.
+    pub fn set_initial<T: 'static>(&mut self, signal: &str, value: T) -> Result<&mut Self> {
+        self.signals
+            .get(self.resolve(signal))
+            .ok_or(ControlSystemError::UnknownSignalName(signal.to_string()))?
+            .try_set(value)?;
+
+        Ok(self)
+    }
+
+    /// Makes `alias` another name for the `existing` signal, so that inputs can be
+    /// connected to it through either name, eg. to match the names expected by a
+    /// prebuilt subsystem. The signal itself keeps its original name.
+    ///
+    /// `existing` must already be produced or declared as external, and `alias` must
+    /// not be the name of another signal or alias.
+    pub fn alias_signal(&mut self, existing: &str, alias: &str) -> Result<&mut Self> {
+        let existing = self.resolve(existing).to_string();
+        if !self.signals.contains_key(&existing) {
+            return Err(ControlSystemError::UnknownSignalName(existing));
+        }
+
+        if self.signals.contains_key(alias) || self.aliases.contains_key(alias) {
+            return Err(ControlSystemError::DuplicateSignal(alias.to_string()));
+        }
+
+        self.aliases.insert(alias.to_string(), existing);
 
         Ok(self)
     }
 
+    /// Removes a previously added block, along with the signals it produces.
+    ///
+    /// Fails if any other block still consumes one of those signals: consumers must
+    /// be removed first.
+    pub fn remove_block(&mut self, name: &str) -> Result<()> {
+        let block_data = self
+            .blocks
+            .get(name)
+            .ok_or(ControlSystemError::UnknownBlock(name.to_string()))?;
+
+        let mut consumers: Vec<String> = block_data
+            .registered_outputs
+            .values()
+            .filter_map(|signal| self.consumers.get(signal))
+            .flatten()
+            .filter(|consumer| consumer.as_str() != name)
+            .cloned()
+            .collect();
+
+        if !consumers.is_empty() {
+            consumers.sort();
+            consumers.dedup();
+            return Err(ControlSystemError::BlockInUse {
+                blockname: name.to_string(),
+                consumers,
+            });
+        }
+
+        let block_data = self.blocks.remove(name).unwrap();
+        self.remove_consumer(&block_data);
+        for signal in block_data.registered_outputs.values() {
+            self.signals.remove(signal);
+        }
+        self.aliases
+            .retain(|_, signal| !block_data.registered_outputs.values().any(|s| s == signal));
+
+        Ok(())
+    }
+
+    /// Connects input `port` of the previously added block `block` to `signal`
+    pub fn connect_input(&mut self, block: &str, port: &str, signal: &str) -> Result<&mut Self> {
+        self.connect_block_input(block, port, signal, None)
+    }
+
+    /// Connects input `port` of the previously added block `block` to `signal`, which
+    /// must either have no unit or be expressed in `unit`
+    pub fn connect_input_with_unit(
+        &mut self,
+        block: &str,
+        port: &str,
+        signal: &str,
+        unit: &str,
+    ) -> Result<&mut Self> {
+        self.connect_block_input(block, port, signal, Some(unit))
+    }
+
+    /// Connects output `port` of the previously added block `block` to `signal`
+    pub fn connect_output(&mut self, block: &str, port: &str, signal: &str) -> Result<&mut Self> {
+        self.connect_block_output(block, port, signal, None)
+    }
+
+    /// Connects output `port` of the previously added block `block` to `signal`,
+    /// expressed in `unit` (eg. "m/s")
+    pub fn connect_output_with_unit(
+        &mut self,
+        block: &str,
+        port: &str,
+        signal: &str,
+        unit: &str,
+    ) -> Result<&mut Self> {
+        self.connect_block_output(block, port, signal, Some(unit))
+    }
+
+    /// Runs the checks on the wiring performed by [`ControlSystemBuilder::build`],
+    /// reporting every problem found instead of only the first one: unconnected ports,
+    /// inputs reading signals with no producer and inputs reading signals of the wrong
+    /// type. Duplicate names are already rejected when blocks are added, while cycles
+    /// are only checked when building, as they are accepted when solving algebraic loops.
+    pub fn validate(&self) -> Result<(), Vec<ControlSystemError>> {
+        let mut errors = self.unconnected_ports();
+        errors.extend(self.missing_producers());
+        errors.extend(self.type_mismatches());
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
     pub fn build_from_store(
         self,
         name: &str,
@@ -106,10 +849,19 @@ impl ControlSystemBuilder {
         name: &str,
         params: ControlSystemParameters,
     ) -> Result<ControlSystem, ControlSystemError> {
+        self.check_connected()?;
+        self.check_producers()?;
+
+        // Types are checked while wiring, but external signals may have been declared
+        // after the inputs reading them were connected
+        if let Some(e) = self.type_mismatches().into_iter().next() {
+            return Err(e);
+        }
+
         for (name, data) in self.blocks.iter_mut() {
             let mut input_signals = data.block.input_signals();
 
-            for (signal, input) in data.registered_inputs.iter() {
+            for (input, signal) in data.registered_inputs.iter() {
                 let signal = self
                     .signals
                     .get(signal)
@@ -123,14 +875,15 @@ impl ControlSystemBuilder {
             }
         }
 
-        let graph_cyclic = self.build_graph(true);
-        println!("{}", Dot::new(&graph_cyclic));
-
         let graph = self.build_graph(false);
-        let sorted = toposort(&graph, None);
+        let sorted = if params.algebraic_loops.is_some() {
+            Ok(self.schedule_with_loops(&graph)?)
+        } else {
+            toposort(&graph, None).map(|nodes| (nodes, vec![]))
+        };
 
         match sorted {
-            Ok(nodes) => {
+            Ok((nodes, loops)) => {
                 let mut blocks = vec![];
                 for node_ix in nodes {
                     let node = graph.node_weight(node_ix).unwrap();
@@ -139,130 +892,1802 @@ impl ControlSystemBuilder {
 
                 let dt = params.dt;
 
+                let initial_signals = self
+                    .signals
+                    .iter()
+                    .map(|(name, signal)| (name.clone(), signal.duplicate()))
+                    .collect();
+
                 Ok(ControlSystem {
                     name: name.to_string(),
                     signals: self.signals,
+                    initial_signals,
                     blocks,
                     graph,
                     params,
                     step: StepInfo::new(dt),
+                    check_finite: false,
+                    loops,
+                    profile: None,
+                    external_time: None,
                 })
             }
-            Err(cycle) => Err(ControlSystemError::CycleDetected(
-                graph.node_weight(cycle.node_id()).unwrap().clone(),
-            )),
+            Err(cycle) => Err(ControlSystemError::CycleDetected(find_cycle(
+                &graph,
+                cycle.node_id(),
+            ))),
         }
     }
 }
 
 impl ControlSystemBuilder {
-    fn connect_outputs(
+    fn insert_block(
         &mut self,
-        block_data: &mut BlockData,
+        block: Box<dyn Block>,
+        block_type: String,
+        params: Value,
+        input_connections: &[(&str, &str)],
         output_connections: &[(&str, &str)],
-    ) -> Result<(), ControlSystemError> {
-        let block_name = block_data.block.name();
-        let mut output_signals = block_data.block.output_signals();
+    ) -> Result<&mut Self, ControlSystemError> {
+        let name = block.name();
 
-        for (port, signal_name) in output_connections.iter() {
-            if self.signals.contains_key(*signal_name) {
-                // A signal with the same name is already produced by another output
-                return Err(ControlSystemError::MultipleProducers {
-                    port: port.to_string(),
-                    signal: signal_name.to_string(),
-                    blockname: block_name.clone(),
-                });
-            } else {
-                let signal =
-                    output_signals
-                        .get_mut(*port)
-                        .ok_or(ControlSystemError::UnknownPort {
-                            port: port.to_string(),
-                            blockname: block_name.clone(),
-                        })?;
-
-                signal.set_name(signal_name);
-
-                self.signals
-                    .insert(signal_name.to_string(), (*(signal)).clone());
-                block_data
-                    .registered_outputs
-                    .insert(port.to_string(), signal_name.to_string());
-                output_signals.remove(*port);
-            }
+        if self.blocks.contains_key(&name) {
+            return Err(ControlSystemError::DuplicateBlockName(name));
         }
 
-        if output_signals.is_empty() {
-            Ok(())
-        } else {
-            Err(ControlSystemError::UnconnectedPorts {
-                ports: output_signals.keys().cloned().collect(),
-                blockname: block_name.clone(),
-            })
-        }
-    }
+        let optional_inputs = block.optional_inputs();
+        let optional_outputs = block.optional_outputs();
 
-    fn connect_inputs(
-        &mut self,
-        block_data: &mut BlockData,
-        input_connections: &[(&str, &str)],
-    ) -> Result<(), ControlSystemError> {
-        let mut input_signals: HashSet<String> =
-            block_data.block.input_signals().into_keys().collect();
-
-        for (port, signal) in input_connections {
-            if input_signals.contains(*port) {
-                block_data
-                    .registered_inputs
-                    .insert(signal.to_string(), port.to_string());
-                input_signals.remove(*port);
-            } else {
-                return Err(ControlSystemError::UnknownPort {
-                    port: port.to_string(),
-                    blockname: block_data.block.name(),
-                });
+        let mut required_inputs: Vec<String> = block
+            .input_port_names()
+            .into_iter()
+            .filter(|port| !optional_inputs.contains(port))
+            .collect();
+        required_inputs.sort();
+
+        let mut required_outputs: Vec<String> = block
+            .output_port_names()
+            .into_iter()
+            .filter(|port| !optional_outputs.contains(port))
+            .collect();
+        required_outputs.sort();
+
+        let mut block_data = BlockData {
+            block,
+            registered_inputs: HashMap::new(),
+            registered_outputs: HashMap::new(),
+            input_units: HashMap::new(),
+            required_inputs,
+            required_outputs,
+            block_type,
+            params,
+        };
+
+        let connected = self
+            .connect_inputs(&mut block_data, input_connections)
+            .and_then(|_| self.connect_outputs(&mut block_data, output_connections));
+        if connected.is_err() {
+            // Undo the connections made before the failing one
+            self.remove_consumer(&block_data);
+            for signal in block_data.registered_outputs.values() {
+                self.signals.remove(signal);
             }
+            return connected.map(|_| self);
         }
 
-        if input_signals.is_empty() {
+        self.blocks.insert(block_data.block.name(), block_data);
+
+        Ok(self)
+    }
+
+    fn connect_block_input(
+        &mut self,
+        block: &str,
+        port: &str,
+        signal: &str,
+        unit: Option<&str>,
+    ) -> Result<&mut Self> {
+        let mut block_data = self
+            .blocks
+            .remove(block)
+            .ok_or(ControlSystemError::UnknownBlock(block.to_string()))?;
+
+        let result = self.connect_input_port(&mut block_data, port, signal, unit);
+        self.blocks.insert(block.to_string(), block_data);
+
+        result.map(|_| self)
+    }
+
+    fn connect_block_output(
+        &mut self,
+        block: &str,
+        port: &str,
+        signal: &str,
+        unit: Option<&str>,
+    ) -> Result<&mut Self> {
+        let mut block_data = self
+            .blocks
+            .remove(block)
+            .ok_or(ControlSystemError::UnknownBlock(block.to_string()))?;
+
+        let result = self.connect_output_port(&mut block_data, port, signal, unit);
+        self.blocks.insert(block.to_string(), block_data);
+
+        result.map(|_| self)
+    }
+
+    fn connect_outputs(
+        &mut self,
+        block_data: &mut BlockData,
+        output_connections: &[(&str, &str)],
+    ) -> Result<(), ControlSystemError> {
+        for (port, signal) in output_connections.iter() {
+            self.connect_output_port(block_data, port, signal, None)?;
+        }
+
+        Ok(())
+    }
+
+    fn connect_inputs(
+        &mut self,
+        block_data: &mut BlockData,
+        input_connections: &[(&str, &str)],
+    ) -> Result<(), ControlSystemError> {
+        for (port, signal) in input_connections.iter() {
+            self.connect_input_port(block_data, port, signal, None)?;
+        }
+
+        Ok(())
+    }
+
+    /// Connects an output of a block that is not in `self.blocks`, checking the types
+    /// and units of the inputs already connected to the same signal
+    fn connect_output_port(
+        &mut self,
+        block_data: &mut BlockData,
+        port: &str,
+        signal_name: &str,
+        unit: Option<&str>,
+    ) -> Result<()> {
+        let block_name = block_data.block.name();
+
+        if self.signals.contains_key(signal_name) || self.aliases.contains_key(signal_name) {
+            // A signal with the same name, or aliased to it, is already produced by another output
+            return Err(ControlSystemError::MultipleProducers {
+                port: port.to_string(),
+                signal: signal_name.to_string(),
+                blockname: block_name,
+            });
+        }
+
+        if block_data.registered_outputs.contains_key(port) {
+            return Err(ControlSystemError::PortAlreadyConnected {
+                port: port.to_string(),
+                blockname: block_name,
+            });
+        }
+
+        let mut output_signals = block_data.block.output_signals();
+        let signal = output_signals
+            .get_mut(port)
+            .ok_or(ControlSystemError::UnknownPort {
+                port: port.to_string(),
+                blockname: block_name.clone(),
+            })?;
+
+        signal.set_name(signal_name);
+        signal.set_producer(&block_name);
+        signal.set_unit(unit);
+        let signal = signal.clone();
+        drop(output_signals);
+
+        for consumer_name in self.consumers.get(signal_name).into_iter().flatten() {
+            // The block may read its own output, eg. through a delay
+            let consumer = match self.blocks.get(consumer_name) {
+                Some(consumer) => consumer,
+                None => &*block_data,
+            };
+
+            // The signal may be read through several inputs of the consumer
+            for (input, _) in consumer
+                .registered_inputs
+                .iter()
+                .filter(|(_, s)| *s == signal_name)
+            {
+                Self::check_input_type(&signal, consumer, input)?;
+                Self::check_input_unit(&signal, consumer, input)?;
+            }
+        }
+
+        self.signals.insert(signal_name.to_string(), signal);
+        block_data
+            .registered_outputs
+            .insert(port.to_string(), signal_name.to_string());
+
+        Ok(())
+    }
+
+    /// Connects an input of a block that is not in `self.blocks`, checking its type
+    /// and unit if the signal is already produced
+    fn connect_input_port(
+        &mut self,
+        block_data: &mut BlockData,
+        port: &str,
+        signal: &str,
+        unit: Option<&str>,
+    ) -> Result<()> {
+        let signal = self.resolve(signal).to_string();
+        let signal = signal.as_str();
+
+        if !block_data
+            .block
+            .input_port_names()
+            .iter()
+            .any(|p| p == port)
+        {
+            return Err(ControlSystemError::UnknownPort {
+                port: port.to_string(),
+                blockname: block_data.block.name(),
+            });
+        }
+
+        if block_data.registered_inputs.contains_key(port) {
+            return Err(ControlSystemError::PortAlreadyConnected {
+                port: port.to_string(),
+                blockname: block_data.block.name(),
+            });
+        }
+
+        if let Some(unit) = unit {
+            block_data
+                .input_units
+                .insert(port.to_string(), unit.to_string());
+        }
+
+        if let Some(any_signal) = self.signals.get(signal) {
+            let checked = Self::check_input_type(any_signal, block_data, port)
+                .and_then(|_| Self::check_input_unit(any_signal, block_data, port));
+
+            if checked.is_err() {
+                block_data.input_units.remove(port);
+                return checked;
+            }
+        }
+
+        block_data
+            .registered_inputs
+            .insert(port.to_string(), signal.to_string());
+        let consumers = self.consumers.entry(signal.to_string()).or_default();
+        let name = block_data.block.name();
+        if !consumers.contains(&name) {
+            consumers.push(name);
+        }
+
+        Ok(())
+    }
+
+    /// Name of the signal `signal` refers to, which may be an alias
+    fn resolve<'a>(&'a self, signal: &'a str) -> &'a str {
+        self.aliases.get(signal).map_or(signal, String::as_str)
+    }
+
+    /// Removes the block from the consumers of the signals it reads
+    fn remove_consumer(&mut self, block_data: &BlockData) {
+        let name = block_data.block.name();
+        for signal in block_data.registered_inputs.values() {
+            if let Some(consumers) = self.consumers.get_mut(signal) {
+                consumers.retain(|consumer| *consumer != name);
+                if consumers.is_empty() {
+                    self.consumers.remove(signal);
+                }
+            }
+        }
+    }
+
+    fn check_input_type(signal: &AnySignal, consumer: &BlockData, port: &str) -> Result<()> {
+        match consumer.block.input_types().get(port) {
+            Some(expected) if *expected != signal.signal_type() => Err(
+                ControlSystemError::ConnectionTypeMismatch(Box::new(TypeMismatch {
+                    signal: signal.name().clone().unwrap(),
+                    producer: signal.producer().map(str::to_string),
+                    producer_type: signal.signal_type_name().to_string(),
+                    consumer: consumer.block.name(),
+                    port: port.to_string(),
+                    consumer_type: expected.name().to_string(),
+                })),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_input_unit(signal: &AnySignal, consumer: &BlockData, port: &str) -> Result<()> {
+        match (signal.unit(), consumer.input_units.get(port)) {
+            (Some(signal_unit), Some(unit)) if signal_unit != unit => Err(
+                ControlSystemError::ConnectionUnitMismatch(Box::new(UnitMismatch {
+                    signal: signal.name().clone().unwrap(),
+                    signal_unit: signal_unit.to_string(),
+                    blockname: consumer.block.name(),
+                    port: port.to_string(),
+                    unit: unit.clone(),
+                })),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that every port is connected, except for optional ones
+    fn check_connected(&self) -> Result<()> {
+        match self.unconnected_ports().into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// One error for each block with unconnected inputs and one for each block with
+    /// unconnected outputs, sorted by block name
+    fn unconnected_ports(&self) -> Vec<ControlSystemError> {
+        let mut blocks: Vec<(&String, &BlockData)> = self.blocks.iter().collect();
+        blocks.sort_by_key(|(name, _)| *name);
+
+        let mut errors = vec![];
+        for (name, data) in blocks {
+            let inputs: Vec<String> = data
+                .required_inputs
+                .iter()
+                .filter(|port| !data.registered_inputs.contains_key(*port))
+                .cloned()
+                .collect();
+            if !inputs.is_empty() {
+                errors.push(ControlSystemError::UnconnectedInputs {
+                    ports: inputs,
+                    blockname: name.clone(),
+                });
+            }
+
+            let outputs: Vec<String> = data
+                .required_outputs
+                .iter()
+                .filter(|port| !data.registered_outputs.contains_key(*port))
+                .cloned()
+                .collect();
+            if !outputs.is_empty() {
+                errors.push(ControlSystemError::UnconnectedOutputs {
+                    ports: outputs,
+                    blockname: name.clone(),
+                });
+            }
+        }
+
+        errors
+    }
+
+    /// Inputs reading a signal of a different type than they expect, sorted by block name
+    fn type_mismatches(&self) -> Vec<ControlSystemError> {
+        let mut blocks: Vec<(&String, &BlockData)> = self.blocks.iter().collect();
+        blocks.sort_by_key(|(name, _)| *name);
+
+        let mut errors = vec![];
+        for (_, data) in blocks {
+            let input_types = data.block.input_types();
+
+            let mut inputs: Vec<(&String, &String)> = data.registered_inputs.iter().collect();
+            inputs.sort();
+
+            for (port, signal) in inputs {
+                let (Some(expected), Some(signal)) =
+                    (input_types.get(port), self.signals.get(signal))
+                else {
+                    continue;
+                };
+
+                if *expected != signal.signal_type() {
+                    errors.push(ControlSystemError::TypeError {
+                        signal: signal.name().clone().unwrap(),
+                        typename: expected.name().to_string(),
+                        signal_typename: signal.signal_type_name().to_string(),
+                        producer: signal.producer().map(str::to_string),
+                    });
+                }
+            }
+        }
+
+        errors
+    }
+
+    /// Checks that every signal consumed by an input is produced by some block,
+    /// reporting all the offending inputs at once.
+    fn check_producers(&self) -> Result<()> {
+        let missing = self.missing_producers();
+
+        if missing.is_empty() {
             Ok(())
         } else {
-            Err(ControlSystemError::UnconnectedPorts {
-                ports: input_signals.iter().cloned().collect(),
-                blockname: block_data.block.name(),
+            Err(ControlSystemError::MissingProducers(missing))
+        }
+    }
+
+    /// Inputs connected to signals that are never produced, sorted by block name
+    fn missing_producers(&self) -> Vec<ControlSystemError> {
+        let mut missing: Vec<(&String, &String, &String)> = self
+            .blocks
+            .iter()
+            .flat_map(|(name, data)| {
+                data.registered_inputs
+                    .iter()
+                    .filter(|(_, signal)| !self.signals.contains_key(*signal))
+                    .map(move |(port, signal)| (name, port, signal))
+            })
+            .collect();
+
+        missing.sort();
+        missing
+            .into_iter()
+            .map(|(name, port, signal)| ControlSystemError::UnknownSignal {
+                port: port.clone(),
+                signal: signal.clone(),
+                blockname: name.clone(),
             })
+            .collect()
+    }
+
+    /// Sorts the blocks in execution order, like a topological sort, but accepting
+    /// algebraic loops: the blocks of each loop are placed next to each other.
+    /// Fails if a loop has no signal its convergence can be checked on.
+    fn schedule_with_loops(
+        &self,
+        graph: &Graph<String, String>,
+    ) -> Result<(Vec<NodeIndex>, Vec<AlgebraicLoop>)> {
+        let mut order = vec![];
+        let mut loops = vec![];
+
+        // Strongly connected components are returned in reverse topological order
+        for mut scc in tarjan_scc(graph).into_iter().rev() {
+            if scc.len() > 1 || graph.contains_edge(scc[0], scc[0]) {
+                scc.sort_by_key(|n| &graph[*n]);
+
+                let signals: Vec<AnySignal> = scc
+                    .iter()
+                    .flat_map(|n| self.blocks[&graph[*n]].registered_outputs.values())
+                    .map(|signal| self.signals[signal].clone())
+                    .filter(AnySignal::has_float_values)
+                    .collect();
+
+                // The convergence of the loop could not be checked
+                if signals.is_empty() {
+                    return Err(ControlSystemError::UncheckedAlgebraicLoop {
+                        blocks: scc.iter().map(|n| graph[*n].clone()).collect(),
+                    });
+                }
+
+                loops.push(AlgebraicLoop {
+                    blocks: order.len()..order.len() + scc.len(),
+                    signals,
+                });
+            }
+
+            order.extend(scc);
         }
+
+        Ok((order, loops))
     }
 
+    /// Graph of the dependencies between the blocks. Nodes and edges are inserted
+    /// sorted by name, so that the execution order only depends on the wiring.
+    ///
+    /// Blocks with a delay read the value their producers wrote in the previous step,
+    /// so unless `cyclic_edges` is set, their input edges are reversed: the delayed
+    /// block must run before its producers overwrite the signal.
     fn build_graph(&self, cyclic_edges: bool) -> Graph<String, String> {
         let mut graph = Graph::new();
 
-        let mut node_indices: HashMap<String, NodeIndex> = HashMap::new();
-        for (name, _) in self.blocks.iter() {
-            let index = graph.add_node(name.clone());
-            node_indices.insert(name.clone(), index);
+        let mut blocks: Vec<(&String, &BlockData)> = self.blocks.iter().collect();
+        blocks.sort_by_key(|(name, _)| *name);
+
+        let mut node_indices: HashMap<&str, NodeIndex> = HashMap::new();
+        for (name, _) in blocks.iter() {
+            node_indices.insert(name.as_str(), graph.add_node((*name).clone()));
         }
 
-        for (producer_name, producer_block) in self.blocks.iter() {
-            producer_block
-                .registered_outputs
-                .iter()
-                .for_each(|(_, signal)| {
-                    for (consumer_name, consumer_block) in self.blocks.iter() {
-                        if consumer_block.registered_inputs.contains_key(signal) {
-                            let delay = self.blocks.get(consumer_name).unwrap().block.delay();
-                            if delay == 0 || cyclic_edges {
-                                graph.add_edge(
-                                    *node_indices.get(producer_name).unwrap(),
-                                    *node_indices.get(consumer_name).unwrap(),
-                                    signal.clone(),
-                                );
-                            }
-                        }
+        // Edges from delayed blocks to other delayed blocks producing their input
+        let mut delayed_edges = vec![];
+
+        for (producer_name, producer_block) in blocks.iter() {
+            let mut signals: Vec<&String> = producer_block.registered_outputs.values().collect();
+            signals.sort();
+
+            for signal in signals {
+                let mut consumers: Vec<&String> =
+                    self.consumers.get(signal).into_iter().flatten().collect();
+                consumers.sort();
+
+                for consumer_name in consumers {
+                    let consumer_block = &self.blocks[consumer_name];
+                    let producer = node_indices[producer_name.as_str()];
+                    let consumer = node_indices[consumer_name.as_str()];
+
+                    if consumer_block.block.delay() == 0 || cyclic_edges {
+                        graph.add_edge(producer, consumer, signal.clone());
+                    } else if producer_block.block.delay() == 0 {
+                        graph.add_edge(consumer, producer, signal.clone());
+                    } else {
+                        delayed_edges.push((consumer, producer, signal.clone()));
                     }
-                });
+                }
+            }
+        }
+
+        // Reversed edges only start from delayed blocks, and the other edges only reach
+        // blocks with no delay: cycles can only be closed by loops of delayed blocks,
+        // where one of the blocks necessarily reads the value of the current step
+        for (consumer, producer, signal) in delayed_edges {
+            if !has_path_connecting(&graph, producer, consumer, None) {
+                graph.add_edge(consumer, producer, signal);
+            }
         }
 
         graph
     }
 }
+
+/// Block added with [`ControlSystemBuilder::add_block_prefixed`], renamed by prepending
+/// the prefix to its name
+struct PrefixedBlock {
+    name: String,
+    block: Box<dyn Block>,
+}
+
+impl BlockIO for PrefixedBlock {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn input_signals(&mut self) -> HashMap<String, &mut Option<AnySignal>> {
+        self.block.input_signals()
+    }
+
+    fn output_signals(&mut self) -> HashMap<String, &mut AnySignal> {
+        self.block.output_signals()
+    }
+
+    fn input_port_names(&self) -> Vec<String> {
+        self.block.input_port_names()
+    }
+
+    fn output_port_names(&self) -> Vec<String> {
+        self.block.output_port_names()
+    }
+
+    fn optional_inputs(&self) -> Vec<String> {
+        self.block.optional_inputs()
+    }
+
+    fn optional_outputs(&self) -> Vec<String> {
+        self.block.optional_outputs()
+    }
+
+    fn input_types(&self) -> HashMap<String, SignalType> {
+        self.block.input_types()
+    }
+}
+
+impl Block for PrefixedBlock {
+    fn step(&mut self, k: StepInfo) -> Result<StepResult> {
+        self.block.step(k)
+    }
+
+    fn reset(&mut self) {
+        self.block.reset()
+    }
+
+    fn save_state(&self) -> Option<BlockState> {
+        self.block.save_state()
+    }
+
+    fn load_state(&mut self, state: &dyn Any) -> Result<()> {
+        self.block.load_state(state)
+    }
+
+    fn get_params(&self) -> Option<Value> {
+        self.block.get_params()
+    }
+
+    fn set_params(&mut self, params: Value) -> Result<()> {
+        self.block.set_params(params)
+    }
+
+    fn delay(&self) -> u32 {
+        self.block.delay()
+    }
+}
+
+/// Returns the names of the nodes of the shortest cycle going through `start`, starting
+/// from it
+fn find_cycle(graph: &Graph<String, String>, start: NodeIndex) -> Vec<String> {
+    // Breadth first search, remembering how each node was reached
+    let mut parents: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(node) = queue.pop_front() {
+        for next in graph.neighbors(node) {
+            if next == start {
+                let mut cycle = vec![graph[node].clone()];
+                let mut current = node;
+                while current != start {
+                    current = parents[&current];
+                    cycle.push(graph[current].clone());
+                }
+                cycle.reverse();
+                return cycle;
+            }
+
+            if let Entry::Vacant(e) = parents.entry(next) {
+                e.insert(node);
+                queue.push_back(next);
+            }
+        }
+    }
+
+    // Toposort reported `start` as part of a cycle, so it is always found above
+    vec![graph[start].clone()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        downcast_state,
+        io::{Input, Output},
+        BlockIO,
+    };
+
+    /// Outputs a constant value
+    #[derive(BlockIO)]
+    struct Constant {
+        #[blockio(block_name)]
+        name: String,
+
+        #[blockio(output)]
+        y: Output<f64>,
+
+        value: f64,
+    }
+
+    impl Constant {
+        fn new(name: &str, value: f64) -> Self {
+            Constant {
+                name: name.to_string(),
+                y: Output::default(),
+                value,
+            }
+        }
+    }
+
+    impl Block for Constant {
+        fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+            self.y.set(self.value);
+            Ok(StepResult::Continue)
+        }
+    }
+
+    /// Outputs the time and step size it is stepped with
+    #[derive(BlockIO)]
+    struct Clock {
+        #[blockio(block_name)]
+        name: String,
+
+        #[blockio(output)]
+        t: Output<f64>,
+
+        #[blockio(output)]
+        dt: Output<f64>,
+    }
+
+    impl Clock {
+        fn new(name: &str) -> Self {
+            Clock {
+                name: name.to_string(),
+                t: Output::default(),
+                dt: Output::default(),
+            }
+        }
+    }
+
+    impl Block for Clock {
+        fn step(&mut self, k: StepInfo) -> Result<StepResult> {
+            self.t.set(k.t);
+            self.dt.set(k.dt);
+            Ok(StepResult::Continue)
+        }
+    }
+
+    /// Multiplies its input by a constant
+    #[derive(BlockIO)]
+    struct Gain {
+        #[blockio(block_name)]
+        name: String,
+
+        #[blockio(input)]
+        u: Input<f64>,
+
+        #[blockio(output)]
+        y: Output<f64>,
+
+        k: f64,
+    }
+
+    impl Gain {
+        fn new(name: &str, k: f64) -> Self {
+            Gain {
+                name: name.to_string(),
+                u: Input::default(),
+                y: Output::default(),
+                k,
+            }
+        }
+    }
+
+    impl Block for Gain {
+        fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+            self.y.set(self.k * self.u.get());
+            Ok(StepResult::Continue)
+        }
+    }
+
+    /// Adds an offset to its input, zero if left unconnected
+    #[derive(BlockIO)]
+    struct Offset {
+        #[blockio(block_name)]
+        name: String,
+
+        #[blockio(input)]
+        u: Input<f64>,
+
+        #[blockio(input, optional)]
+        offset: Input<f64>,
+
+        #[blockio(output)]
+        y: Output<f64>,
+    }
+
+    impl Block for Offset {
+        fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+            self.y.set(self.u.get() + self.offset.get());
+            Ok(StepResult::Continue)
+        }
+    }
+
+    /// Sums the values its input had in the previous steps
+    #[derive(BlockIO)]
+    struct Accumulator {
+        #[blockio(block_name)]
+        name: String,
+
+        #[blockio(input)]
+        u: Input<f64>,
+
+        #[blockio(output)]
+        y: Output<f64>,
+
+        sum: f64,
+    }
+
+    impl Accumulator {
+        fn new(name: &str) -> Self {
+            Accumulator {
+                name: name.to_string(),
+                u: Input::default(),
+                y: Output::default(),
+                sum: 0.0,
+            }
+        }
+    }
+
+    impl Block for Accumulator {
+        fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+            self.sum += self.u.get();
+            self.y.set(self.sum);
+            Ok(StepResult::Continue)
+        }
+
+        fn reset(&mut self) {
+            self.sum = 0.0;
+        }
+
+        fn save_state(&self) -> Option<BlockState> {
+            Some(Box::new(self.sum))
+        }
+
+        fn load_state(&mut self, state: &dyn Any) -> Result<()> {
+            self.sum = *downcast_state::<f64>(&self.name, state)?;
+            Ok(())
+        }
+
+        fn delay(&self) -> u32 {
+            1
+        }
+    }
+
+    /// Outputs the number of the step
+    #[derive(BlockIO)]
+    struct Counter {
+        #[blockio(block_name)]
+        name: String,
+
+        #[blockio(output)]
+        k: Output<i32>,
+    }
+
+    impl Counter {
+        fn new(name: &str) -> Self {
+            Counter {
+                name: name.to_string(),
+                k: Output::default(),
+            }
+        }
+    }
+
+    impl Block for Counter {
+        fn step(&mut self, k: StepInfo) -> Result<StepResult> {
+            self.k.set(k.k as i32);
+            Ok(StepResult::Continue)
+        }
+    }
+
+    /// Outputs the reciprocal of its input
+    #[derive(BlockIO)]
+    struct Reciprocal {
+        #[blockio(block_name)]
+        name: String,
+
+        #[blockio(input)]
+        u: Input<f64>,
+
+        #[blockio(output)]
+        y: Output<f64>,
+    }
+
+    impl Block for Reciprocal {
+        fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+            self.y.set(1.0 / self.u.get());
+            Ok(StepResult::Continue)
+        }
+    }
+
+    /// Outputs the negation of its input
+    #[derive(BlockIO)]
+    struct Not {
+        #[blockio(block_name)]
+        name: String,
+
+        #[blockio(input)]
+        u: Input<bool>,
+
+        #[blockio(output)]
+        y: Output<bool>,
+    }
+
+    impl Block for Not {
+        fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+            self.y.set(!self.u.get());
+            Ok(StepResult::Continue)
+        }
+    }
+
+    /// Stops the system at step `k`, explaining why
+    #[derive(BlockIO)]
+    struct StopAt {
+        #[blockio(block_name)]
+        name: String,
+
+        k: usize,
+    }
+
+    impl Block for StopAt {
+        fn step(&mut self, k: StepInfo) -> Result<StepResult> {
+            if k.k == self.k {
+                Ok(StepResult::StopWithReason(format!("Reached step {}", k.k)))
+            } else {
+                Ok(StepResult::Continue)
+            }
+        }
+    }
+
+    fn params(dt: f64) -> ControlSystemParameters {
+        ControlSystemParameters::new(dt)
+    }
+
+    fn value(system: &ControlSystem, signal: &str) -> f64 {
+        system
+            .get_signal(signal)
+            .unwrap()
+            .try_get::<f64>()
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn removed_block_is_not_built() {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Constant::new("one", 1.0), &[], &[("y", "a")])
+            .unwrap();
+        builder
+            .add_block(Constant::new("two", 2.0), &[], &[("y", "b")])
+            .unwrap();
+        builder
+            .add_block(Gain::new("gain", 2.0), &[("u", "b")], &[("y", "y")])
+            .unwrap();
+
+        assert!(matches!(
+            builder.remove_block("two"),
+            Err(ControlSystemError::BlockInUse { consumers, .. }) if consumers == ["gain"]
+        ));
+
+        builder.remove_block("gain").unwrap();
+        builder.remove_block("two").unwrap();
+        assert!(!builder.has_block("two"));
+        assert!(builder.remove_block("two").is_err());
+
+        let mut system = builder.build("test", params(0.1)).unwrap();
+        assert_eq!(system.blocks.len(), 1);
+        assert!(!system.signals.contains_key("b"));
+        assert_eq!(system.step().unwrap(), StepResult::Continue);
+    }
+
+    #[test]
+    fn missing_producer_lists_all_consumers() {
+        let mut builder = ControlSystemBuilder::default();
+        for name in ["a", "b", "c"] {
+            builder
+                .add_block(Gain::new(name, 1.0), &[("u", "missing")], &[("y", name)])
+                .unwrap();
+        }
+
+        let Err(ControlSystemError::MissingProducers(errors)) =
+            builder.build("missing", params(0.1))
+        else {
+            panic!("expected missing producers");
+        };
+
+        let blocks: Vec<&str> = errors
+            .iter()
+            .map(|e| match e {
+                ControlSystemError::UnknownSignal {
+                    signal, blockname, ..
+                } if signal == "missing" => blockname.as_str(),
+                e => panic!("unexpected error: {e}"),
+            })
+            .collect();
+        assert_eq!(blocks, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn signal_read_by_two_ports() {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Constant::new("one", 1.0), &[], &[("y", "one")])
+            .unwrap()
+            .add_block(
+                Offset {
+                    name: "offset".to_string(),
+                    u: Input::default(),
+                    offset: Input::default(),
+                    y: Output::default(),
+                },
+                &[("u", "one"), ("offset", "one")],
+                &[("y", "y")],
+            )
+            .unwrap();
+
+        let config = builder.to_system_config();
+        assert_eq!(config.blocks[0].name, "offset");
+        assert_eq!(config.blocks[0].inputs.len(), 2);
+
+        let mut system = builder.build("test", params(1.0)).unwrap();
+        system.step().unwrap();
+        assert_eq!(value(&system, "y"), 2.0);
+    }
+
+    #[test]
+    fn optional_input_left_unconnected() {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Constant::new("one", 1.0), &[], &[("y", "a")])
+            .unwrap();
+        builder
+            .add_block(
+                Offset {
+                    name: "offset".to_string(),
+                    u: Input::default(),
+                    offset: Input::with_default(0.5),
+                    y: Output::default(),
+                },
+                &[("u", "a")],
+                &[("y", "y")],
+            )
+            .unwrap();
+
+        let mut system = builder.build("offset", params(0.1)).unwrap();
+        system.step().unwrap();
+        assert_eq!(value(&system, "y"), 1.5);
+    }
+
+    #[test]
+    fn feedback_reads_initial_value() {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Accumulator::new("acc"), &[("u", "fb")], &[("y", "x")])
+            .unwrap();
+        assert!(builder.set_initial("fb", 2.0).is_err());
+
+        builder
+            .add_block(Gain::new("gain", 0.5), &[("u", "x")], &[("y", "fb")])
+            .unwrap();
+        assert!(builder.set_initial("fb", 2).is_err());
+        builder.set_initial("fb", 2.0).unwrap();
+
+        let mut system = builder.build("feedback", params(0.1)).unwrap();
+        let order: Vec<String> = system.blocks.iter().map(|b| b.name()).collect();
+        assert_eq!(order, ["acc", "gain"]);
+
+        system.step().unwrap();
+        assert_eq!(value(&system, "x"), 2.0);
+        system.step().unwrap();
+        assert_eq!(value(&system, "x"), 3.0);
+    }
+
+    #[test]
+    fn stop_reason_is_returned() {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(
+                StopAt {
+                    name: "stop".to_string(),
+                    k: 3,
+                },
+                &[],
+                &[],
+            )
+            .unwrap();
+        let mut system = builder.build("stop", params(0.1)).unwrap();
+
+        assert_eq!(system.step().unwrap(), StepResult::Continue);
+        assert_eq!(system.step().unwrap(), StepResult::Continue);
+
+        let result = system.step().unwrap();
+        assert!(result.is_stop());
+        assert_eq!(
+            result,
+            StepResult::StopWithReason("Reached step 3".to_string())
+        );
+    }
+
+    #[test]
+    fn non_finite_signal_stops_the_system() {
+        let build = || {
+            let mut builder = ControlSystemBuilder::default();
+            builder
+                .add_block(Constant::new("zero", 0.0), &[], &[("y", "u")])
+                .unwrap();
+            builder
+                .add_block(
+                    Reciprocal {
+                        name: "reciprocal".to_string(),
+                        u: Input::default(),
+                        y: Output::default(),
+                    },
+                    &[("u", "u")],
+                    &[("y", "y")],
+                )
+                .unwrap();
+            builder.build("reciprocal", params(0.5)).unwrap()
+        };
+
+        // Not checked by default
+        let mut system = build();
+        system.step().unwrap();
+        assert_eq!(value(&system, "y"), f64::INFINITY);
+
+        let mut system = build();
+        system.set_check_finite(true);
+        let err = system.step().unwrap_err();
+        assert!(matches!(
+            &err,
+            ControlSystemError::NonFiniteSignal { signal, k: 1 } if signal == "y"
+        ));
+        assert!(err.to_string().contains("'y'"));
+    }
+
+    #[test]
+    fn connect_after_adding_blocks() {
+        let mut builder = ControlSystemBuilder::default();
+        builder.add_block(Gain::new("gain", 2.0), &[], &[]).unwrap();
+        builder
+            .add_block(Constant::new("one", 1.0), &[], &[])
+            .unwrap();
+        builder
+            .add_block(Constant::new("two", 2.0), &[], &[])
+            .unwrap();
+
+        builder
+            .connect_input("gain", "u", "a")
+            .unwrap()
+            .connect_output("gain", "y", "y")
+            .unwrap()
+            .connect_output("one", "y", "a")
+            .unwrap()
+            .connect_output("two", "y", "b")
+            .unwrap();
+        assert!(builder.connect_input("gain", "u", "b").is_err());
+        assert!(builder.connect_input("missing", "u", "b").is_err());
+
+        let mut system = builder.build("connect", params(0.5)).unwrap();
+        system.step().unwrap();
+        assert_eq!(value(&system, "y"), 2.0);
+    }
+
+    fn assert_type_mismatch(result: Result<&mut ControlSystemBuilder>) {
+        let Err(ControlSystemError::ConnectionTypeMismatch(mismatch)) = result else {
+            panic!("expected a type mismatch");
+        };
+
+        assert_eq!(mismatch.signal, "k");
+        assert_eq!(mismatch.producer.as_deref(), Some("counter"));
+        assert_eq!(mismatch.producer_type, "i32");
+        assert_eq!(mismatch.consumer, "gain");
+        assert_eq!(mismatch.port, "u");
+        assert_eq!(mismatch.consumer_type, "f64");
+    }
+
+    #[test]
+    fn connection_type_mismatch() {
+        // Producer connected first
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Counter::new("counter"), &[], &[("k", "k")])
+            .unwrap();
+        assert_type_mismatch(builder.add_block(Gain::new("gain", 1.0), &[("u", "k")], &[]));
+        assert!(!builder.has_block("gain"));
+
+        // Consumer connected first
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Gain::new("gain", 1.0), &[("u", "k")], &[("y", "y")])
+            .unwrap();
+        builder
+            .add_block(Counter::new("counter"), &[], &[])
+            .unwrap();
+        assert_type_mismatch(builder.connect_output("counter", "k", "k"));
+    }
+
+    #[test]
+    fn type_errors_name_the_producer() {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Counter::new("counter"), &[], &[("k", "k")])
+            .unwrap();
+        let Err(err) = builder.add_block(Gain::new("gain", 1.0), &[("u", "k")], &[("y", "y")])
+        else {
+            panic!("expected a type mismatch");
+        };
+        assert!(
+            err.to_string().contains("block 'counter' produces a 'i32'"),
+            "{err}"
+        );
+
+        let mut system = builder.build("counter", params(0.1)).unwrap();
+        system.step().unwrap();
+        let err = system
+            .get_signal("k")
+            .unwrap()
+            .try_get::<f64>()
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("'k', produced by block 'counter'"),
+            "{err}"
+        );
+
+        // External signals declared after connecting the input are checked when building
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Gain::new("gain", 1.0), &[("u", "u")], &[("y", "y")])
+            .unwrap();
+        builder.add_external_signal::<i32>("u").unwrap();
+        let Err(err) = builder.build("external", params(0.1)) else {
+            panic!("expected a type error");
+        };
+        assert!(
+            err.to_string().contains("produced by the external source"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn connection_unit_mismatch() {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Constant::new("one", 1.0), &[], &[])
+            .unwrap();
+        builder.add_block(Gain::new("gain", 1.0), &[], &[]).unwrap();
+        builder
+            .connect_output_with_unit("one", "y", "t", "s")
+            .unwrap();
+
+        let Err(ControlSystemError::ConnectionUnitMismatch(mismatch)) =
+            builder.connect_input_with_unit("gain", "u", "t", "ms")
+        else {
+            panic!("expected a unit mismatch");
+        };
+        assert_eq!(mismatch.signal, "t");
+        assert_eq!(mismatch.signal_unit, "s");
+        assert_eq!(mismatch.blockname, "gain");
+        assert_eq!(mismatch.port, "u");
+        assert_eq!(mismatch.unit, "ms");
+
+        // Inputs without a unit accept any signal
+        builder.connect_input("gain", "u", "t").unwrap();
+        assert_eq!(builder.signals["t"].unit(), Some("s"));
+
+        // Consumer connected first
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Constant::new("one", 1.0), &[], &[])
+            .unwrap();
+        builder.add_block(Gain::new("gain", 1.0), &[], &[]).unwrap();
+        builder
+            .connect_input_with_unit("gain", "u", "dt", "ms")
+            .unwrap();
+        assert!(matches!(
+            builder.connect_output_with_unit("one", "y", "dt", "s"),
+            Err(ControlSystemError::ConnectionUnitMismatch(_))
+        ));
+        builder
+            .connect_output_with_unit("one", "y", "dt", "ms")
+            .unwrap();
+    }
+
+    #[test]
+    fn block_names_are_sorted() {
+        let mut builder = ControlSystemBuilder::default();
+        for name in ["b", "c", "a"] {
+            builder
+                .add_block(Constant::new(name, 1.0), &[], &[])
+                .unwrap();
+        }
+
+        assert_eq!(builder.block_names(), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn dot_graph_has_an_edge_per_signal() {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Constant::new("one", 1.0), &[], &[("y", "u")])
+            .unwrap()
+            .add_block(Gain::new("gain", 2.0), &[("u", "u")], &[("y", "y")])
+            .unwrap();
+
+        let dot = builder.to_dot();
+        assert!(dot.starts_with("digraph"), "{dot}");
+        assert!(dot.contains("1 -> 0 [ label = \"u\" ]"), "{dot}");
+    }
+
+    #[test]
+    fn max_time_stops_the_system() {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Counter::new("counter"), &[], &[("k", "k")])
+            .unwrap();
+        let params = ControlSystemParameters::new(0.001).with_max_time(0.01);
+        let mut system = builder.build("counter", params).unwrap();
+
+        let mut steps = 1;
+        while !system.step().unwrap().is_stop() {
+            steps += 1;
+            assert!(steps <= 1000);
+        }
+        assert_eq!(steps, 10);
+        assert_eq!(
+            system.get_signal("k").unwrap().try_get::<i32>().unwrap(),
+            Some(10)
+        );
+    }
+
+    #[test]
+    fn parameters_defaults_and_setters() {
+        let params = ControlSystemParameters::new(0.01);
+        assert_eq!(params.dt, 0.01);
+        assert_eq!(params.max_iter, 0);
+        assert_eq!(params.max_time, None);
+
+        let params = ControlSystemParameters::new(0.01)
+            .with_max_iter(100)
+            .with_max_time(2.0);
+        assert_eq!(params.max_iter, 100);
+        assert_eq!(params.max_time, Some(2.0));
+
+        // Older configurations without max_time are still accepted
+        let params: ControlSystemParameters = toml::from_str("dt = 0.1\nmax_iter = 5").unwrap();
+        assert_eq!(params.max_time, None);
+    }
+
+    #[test]
+    fn reset_reruns_identically() {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Constant::new("one", 1.0), &[], &[("y", "one")])
+            .unwrap();
+        builder
+            .add_block(Accumulator::new("acc"), &[("u", "one")], &[("y", "x")])
+            .unwrap();
+        builder
+            .add_block(Counter::new("counter"), &[], &[("k", "k")])
+            .unwrap();
+        // The accumulator is a delay block, running before the constant
+        builder.set_initial("one", 1.0).unwrap();
+        let mut system = builder.build("rerun", params(0.1)).unwrap();
+
+        let run = |system: &mut ControlSystem| {
+            (0..5)
+                .map(|_| {
+                    system.step().unwrap();
+                    (
+                        value(system, "x"),
+                        system.get_signal("k").unwrap().try_get::<i32>().unwrap(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let first = run(&mut system);
+        system.reset();
+        let second = run(&mut system);
+
+        assert_eq!(first[0], (1.0, Some(1)));
+        assert_eq!(first[4], (5.0, Some(5)));
+        assert_eq!(second, first);
+    }
+
+    #[test]
+    fn restored_snapshot_replays_trajectory() {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Accumulator::new("acc"), &[("u", "fb")], &[("y", "x")])
+            .unwrap();
+        builder
+            .add_block(Gain::new("gain", 0.5), &[("u", "x")], &[("y", "fb")])
+            .unwrap();
+        builder.set_initial("fb", 2.0).unwrap();
+        let mut system = builder.build("feedback", params(0.1)).unwrap();
+
+        let run = |system: &mut ControlSystem| {
+            (0..4)
+                .map(|_| {
+                    system.step().unwrap();
+                    (value(system, "x"), value(system, "fb"))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        for _ in 0..3 {
+            system.step().unwrap();
+        }
+        let snap = system.snapshot();
+        assert_eq!(snap.step().k, 4);
+
+        let first = run(&mut system);
+        system.restore(&snap).unwrap();
+        assert_eq!(value(&system, "x"), 4.5);
+
+        let second = run(&mut system);
+        assert_eq!(first[0], (6.75, 3.375));
+        assert_eq!(second, first);
+    }
+
+    #[test]
+    fn reset_restores_initial_signals() {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Accumulator::new("acc"), &[("u", "fb")], &[("y", "x")])
+            .unwrap();
+        builder
+            .add_block(Gain::new("gain", 0.5), &[("u", "x")], &[("y", "fb")])
+            .unwrap();
+        builder.set_initial("fb", 2.0).unwrap();
+        let mut system = builder.build("feedback", params(0.1)).unwrap();
+
+        let run = |system: &mut ControlSystem| {
+            (0..5)
+                .map(|_| {
+                    system.step().unwrap();
+                    value(system, "x")
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let first = run(&mut system);
+        system.reset();
+        assert_eq!(value(&system, "fb"), 2.0);
+
+        let second = run(&mut system);
+        assert_eq!(first, [2.0, 3.0, 4.5, 6.75, 10.125]);
+        assert_eq!(second, first);
+    }
+
+    #[test]
+    fn delays_are_reported() {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Accumulator::new("acc"), &[("u", "fb")], &[("y", "x")])
+            .unwrap();
+        builder
+            .add_block(Gain::new("gain", 0.5), &[("u", "x")], &[("y", "v")])
+            .unwrap();
+        builder
+            .add_block(Accumulator::new("acc_v"), &[("u", "v")], &[("y", "fb")])
+            .unwrap();
+        let system = builder.build("delays", params(0.1)).unwrap();
+
+        let mut delays = system.block_delays();
+        delays.sort();
+        assert_eq!(
+            delays,
+            [
+                ("acc".to_string(), 1),
+                ("acc_v".to_string(), 1),
+                ("gain".to_string(), 0)
+            ]
+        );
+        assert_eq!(system.total_delay(), 2);
+    }
+
+    /// System solving `x = gain * x + 1` as an algebraic loop
+    fn algebraic_loop_system(gain: f64) -> ControlSystem {
+        let mut builder = ControlSystemBuilder::default();
+        builder.add_external_signal::<f64>("one").unwrap();
+        builder
+            .add_block(
+                Offset {
+                    name: "offset".to_string(),
+                    u: Input::default(),
+                    offset: Input::default(),
+                    y: Output::default(),
+                },
+                &[("u", "y"), ("offset", "one")],
+                &[("y", "x")],
+            )
+            .unwrap();
+        builder
+            .add_block(Gain::new("gain", gain), &[("u", "x")], &[("y", "y")])
+            .unwrap();
+        builder
+            .set_initial("one", 1.0)
+            .unwrap()
+            .set_initial("x", 0.0)
+            .unwrap()
+            .set_initial("y", 0.0)
+            .unwrap();
+
+        let params = ControlSystemParameters::new(0.1).with_algebraic_loops(AlgebraicLoopParams {
+            max_iter: 100,
+            tolerance: 1e-9,
+        });
+        builder.build("loop", params).unwrap()
+    }
+
+    #[test]
+    fn algebraic_loop_converges() {
+        let mut system = algebraic_loop_system(0.5);
+
+        system.step().unwrap();
+        assert!((value(&system, "x") - 2.0).abs() < 1e-8);
+        assert!((value(&system, "y") - 1.0).abs() < 1e-8);
+    }
+
+    #[test]
+    fn algebraic_loop_diverges() {
+        let mut system = algebraic_loop_system(2.0);
+
+        let Err(err) = system.step() else {
+            panic!("the loop should not converge");
+        };
+        let ControlSystemError::AlgebraicLoopNotConverged {
+            mut blocks,
+            iterations,
+            k,
+        } = err
+        else {
+            panic!("unexpected error: {err}");
+        };
+        blocks.sort();
+        assert_eq!(blocks, ["gain", "offset"]);
+        assert_eq!(iterations, 100);
+        assert_eq!(k, 1);
+    }
+
+    #[test]
+    fn algebraic_loop_without_float_signals() {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(
+                Not {
+                    name: "not".to_string(),
+                    u: Input::default(),
+                    y: Output::default(),
+                },
+                &[("u", "b")],
+                &[("y", "b")],
+            )
+            .unwrap()
+            .set_initial("b", false)
+            .unwrap();
+
+        let params = params(0.1).with_algebraic_loops(AlgebraicLoopParams {
+            max_iter: 100,
+            tolerance: 1e-9,
+        });
+        let Err(ControlSystemError::UncheckedAlgebraicLoop { blocks }) =
+            builder.build("loop", params)
+        else {
+            panic!("the loop should be rejected");
+        };
+        assert_eq!(blocks, ["not"]);
+    }
+
+    #[test]
+    fn cycle_lists_every_block() {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Gain::new("a", 1.0), &[("u", "z")], &[("y", "x")])
+            .unwrap()
+            .add_block(Gain::new("b", 1.0), &[("u", "x")], &[("y", "y")])
+            .unwrap()
+            .add_block(Gain::new("c", 1.0), &[("u", "y")], &[("y", "z")])
+            .unwrap();
+
+        let Err(ControlSystemError::CycleDetected(mut cycle)) =
+            builder.build("cycle", ControlSystemParameters::new(0.1))
+        else {
+            panic!("the cycle should be detected");
+        };
+
+        // The cycle may start from any of its blocks
+        let start = cycle.iter().position(|b| b == "a").unwrap();
+        cycle.rotate_left(start);
+        assert_eq!(cycle, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn execution_order_is_reproducible() {
+        let build = || {
+            let mut builder = ControlSystemBuilder::default();
+            builder
+                .add_block(Constant::new("source", 1.0), &[], &[("y", "t")])
+                .unwrap();
+            for name in ["e", "b", "d", "a", "c"] {
+                builder
+                    .add_block(Gain::new(name, 1.0), &[("u", "t")], &[("y", name)])
+                    .unwrap();
+            }
+            builder
+                .build("order", params(0.1))
+                .unwrap()
+                .execution_order()
+        };
+
+        let order = build();
+        assert_eq!(order[0], "source");
+        for _ in 0..10 {
+            assert_eq!(build(), order);
+        }
+    }
+
+    #[test]
+    fn validate_reports_every_error() {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Gain::new("a", 1.0), &[], &[("y", "ya")])
+            .unwrap()
+            .add_block(Gain::new("b", 1.0), &[("u", "missing")], &[("y", "yb")])
+            .unwrap()
+            .add_block(Counter::new("counter"), &[], &[])
+            .unwrap();
+
+        let errors = builder.validate().unwrap_err();
+        assert_eq!(errors.len(), 3, "{errors:?}");
+        assert!(matches!(
+            &errors[0],
+            ControlSystemError::UnconnectedInputs { ports, blockname }
+                if ports == &["u"] && blockname == "a"
+        ));
+        assert!(matches!(
+            &errors[1],
+            ControlSystemError::UnconnectedOutputs { ports, blockname }
+                if ports == &["k"] && blockname == "counter"
+        ));
+        assert!(matches!(
+            &errors[2],
+            ControlSystemError::UnknownSignal { port, signal, blockname }
+                if port == "u" && signal == "missing" && blockname == "b"
+        ));
+    }
+
+    #[test]
+    fn profile_has_an_entry_per_block() {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Clock::new("clock"), &[], &[("t", "t"), ("dt", "dt")])
+            .unwrap()
+            .add_block(Gain::new("gain", 2.0), &[("u", "t")], &[("y", "y")])
+            .unwrap();
+        let mut system = builder.build("profile", params(0.1)).unwrap();
+        assert!(system.profile().is_empty());
+
+        system.set_profiling(true);
+        for _ in 0..10 {
+            system.step().unwrap();
+        }
+        let names: Vec<String> = system.profile().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, system.execution_order());
+
+        system.set_profiling(false);
+        assert!(system.profile().is_empty());
+
+        // Blocks stepped while solving an algebraic loop are timed too
+        let mut system = algebraic_loop_system(0.5);
+        system.set_profiling(true);
+        system.step().unwrap();
+        let names: Vec<String> = system.profile().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, system.execution_order());
+    }
+
+    fn clock_system() -> ControlSystem {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Clock::new("clock"), &[], &[("t", "t"), ("dt", "dt")])
+            .unwrap();
+        builder.build("clock", params(0.1)).unwrap()
+    }
+
+    #[test]
+    fn step_at_uses_elapsed_time() {
+        let mut system = clock_system();
+
+        system.step_at(0.5).unwrap();
+        assert_eq!(value(&system, "t"), 0.5);
+        assert_eq!(value(&system, "dt"), 0.1);
+
+        system.step_at(0.75).unwrap();
+        assert_eq!(value(&system, "t"), 0.75);
+        assert_eq!(value(&system, "dt"), 0.25);
+    }
+
+    #[test]
+    fn step_at_rejects_earlier_time() {
+        let mut system = clock_system();
+
+        system.step_at(1.0).unwrap();
+        assert!(matches!(
+            system.step_at(1.0),
+            Err(ControlSystemError::NonMonotonicTime { t, previous }) if t == 1.0 && previous == 1.0
+        ));
+        assert!(system.step_at(0.5).is_err());
+        assert!(system.step_at(f64::NAN).is_err());
+        assert_eq!(value(&system, "t"), 1.0);
+
+        // The previous time is forgotten on reset
+        system.reset();
+        system.step_at(0.5).unwrap();
+        assert_eq!(value(&system, "t"), 0.5);
+    }
+
+    #[test]
+    fn step_after_step_at_keeps_dt() {
+        let mut system = clock_system();
+
+        system.step_at(0.5).unwrap();
+        system.step_at(0.75).unwrap();
+        system.step().unwrap();
+
+        assert_eq!(value(&system, "t"), 1.0);
+        assert_eq!(value(&system, "dt"), 0.1);
+    }
+
+    #[test]
+    fn step_at_rejects_non_finite_time() {
+        let mut system = clock_system();
+
+        assert!(matches!(
+            system.step_at(f64::NAN),
+            Err(ControlSystemError::NonFiniteTime(_))
+        ));
+        assert!(system.step_at(f64::INFINITY).is_err());
+        assert_eq!(system.step_info().k, 1);
+
+        system.step_at(1.0).unwrap();
+        assert!(system.step_at(f64::NEG_INFINITY).is_err());
+        assert_eq!(system.step_info().k, 2);
+    }
+
+    #[test]
+    fn unconnected_ports_direction() {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Gain::new("gain", 1.0), &[], &[("y", "y")])
+            .unwrap();
+        let Err(e) = builder.build("inputs", params(0.1)) else {
+            panic!("unconnected input not reported");
+        };
+        assert!(matches!(
+            &e,
+            ControlSystemError::UnconnectedInputs { ports, blockname }
+                if ports == &["u"] && blockname == "gain"
+        ));
+        assert_eq!(
+            e.to_string(),
+            "Inputs [\"u\"] in block 'gain' have not been connected"
+        );
+
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Counter::new("counter"), &[], &[])
+            .unwrap();
+        let Err(e) = builder.build("outputs", params(0.1)) else {
+            panic!("unconnected output not reported");
+        };
+        assert!(matches!(
+            &e,
+            ControlSystemError::UnconnectedOutputs { ports, blockname }
+                if ports == &["k"] && blockname == "counter"
+        ));
+        assert_eq!(
+            e.to_string(),
+            "Outputs [\"k\"] in block 'counter' have not been connected"
+        );
+
+        // Both directions of the same block are reported separately
+        let mut builder = ControlSystemBuilder::default();
+        builder.add_block(Gain::new("gain", 1.0), &[], &[]).unwrap();
+        let errors = builder.validate().unwrap_err();
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert!(
+            matches!(&errors[0], ControlSystemError::UnconnectedInputs { ports, .. } if ports == &["u"])
+        );
+        assert!(
+            matches!(&errors[1], ControlSystemError::UnconnectedOutputs { ports, .. } if ports == &["y"])
+        );
+    }
+
+    #[test]
+    fn alias_connects_consumer() {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_block(Clock::new("clock"), &[], &[("t", "t"), ("dt", "dt")])
+            .unwrap()
+            .alias_signal("t", "time")
+            .unwrap()
+            .add_block(Gain::new("gain", 2.0), &[("u", "time")], &[("y", "y")])
+            .unwrap();
+
+        let Err(e) = builder.alias_signal("dt", "time") else {
+            panic!("alias reused");
+        };
+        assert!(matches!(e, ControlSystemError::DuplicateSignal(alias) if alias == "time"));
+        assert!(matches!(
+            builder.alias_signal("missing", "other"),
+            Err(ControlSystemError::UnknownSignalName(_))
+        ));
+        assert!(matches!(
+            builder.add_block(Gain::new("other", 1.0), &[("u", "t")], &[("y", "time")]),
+            Err(ControlSystemError::MultipleProducers { .. })
+        ));
+
+        let mut system = builder.build("alias", params(0.5)).unwrap();
+        system.step().unwrap();
+        system.step().unwrap();
+
+        assert_eq!(value(&system, "y"), 1.0);
+        // The signal keeps its original name
+        assert!(system.get_signal("time").is_none());
+    }
+}
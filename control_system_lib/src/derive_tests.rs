@@ -0,0 +1,252 @@
+//! Compile tests of the `BlockIO` derive, run as doctests
+
+/// Fields marked with `#[blockio(skip)]` are ignored, whatever their type
+///
+/// ```
+/// extern crate control_system_lib as control_system;
+/// use control_system::{io::{Input, Output}, BlockIO};
+///
+/// enum Mode {
+///     Manual,
+///     Auto,
+/// }
+///
+/// #[derive(BlockIO)]
+/// struct Switch {
+///     #[blockio(block_name)]
+///     name: String,
+///     #[blockio(input)]
+///     u: Input<f64>,
+///     #[blockio(output)]
+///     y: Output<f64>,
+///     #[blockio(skip)]
+///     mode: Mode,
+/// }
+///
+/// fn main() {
+///     let mut switch = Switch {
+///         name: "switch".to_string(),
+///         u: Input::default(),
+///         y: Output::default(),
+///         mode: Mode::Auto,
+///     };
+///
+///     assert_eq!(switch.input_signals().len(), 1);
+///     assert_eq!(switch.output_signals().len(), 1);
+/// }
+/// ```
+///
+/// While the enum itself cannot derive `BlockIO`
+///
+/// ```compile_fail
+/// extern crate control_system_lib as control_system;
+/// use control_system::BlockIO;
+///
+/// #[derive(BlockIO)]
+/// enum Mode {
+///     Manual,
+///     Auto,
+/// }
+///
+/// fn main() {}
+/// ```
+pub struct SkippedField;
+
+/// Misspelled attribute kinds are rejected instead of being ignored
+///
+/// ```compile_fail
+/// extern crate control_system_lib as control_system;
+/// use control_system::{io::{Input, Output}, BlockIO};
+///
+/// #[derive(BlockIO)]
+/// struct Gain {
+///     #[blockio(block_name)]
+///     name: String,
+///     #[blockio(inptu)]
+///     u: Input<f64>,
+///     #[blockio(output)]
+///     y: Output<f64>,
+/// }
+///
+/// fn main() {}
+/// ```
+///
+/// As well as misspelled keys
+///
+/// ```compile_fail
+/// extern crate control_system_lib as control_system;
+/// use control_system::{io::{Input, Output}, BlockIO};
+///
+/// #[derive(BlockIO)]
+/// struct Gain {
+///     #[blockio(block_name)]
+///     name: String,
+///     #[blockio(input, nmae = "x")]
+///     u: Input<f64>,
+///     #[blockio(output)]
+///     y: Output<f64>,
+/// }
+///
+/// fn main() {}
+/// ```
+pub struct MisspelledAttribute;
+
+/// A field can only have one kind of `blockio` attribute
+///
+/// ```compile_fail
+/// extern crate control_system_lib as control_system;
+/// use control_system::{io::{Input, Output}, BlockIO};
+///
+/// #[derive(BlockIO)]
+/// struct Gain {
+///     #[blockio(block_name)]
+///     name: String,
+///     #[blockio(input)]
+///     #[blockio(output)]
+///     u: Input<f64>,
+///     #[blockio(output)]
+///     y: Output<f64>,
+/// }
+///
+/// fn main() {}
+/// ```
+///
+/// ```compile_fail
+/// extern crate control_system_lib as control_system;
+/// use control_system::{io::{Input, Output}, BlockIO};
+///
+/// #[derive(BlockIO)]
+/// struct Gain {
+///     #[blockio(block_name)]
+///     name: String,
+///     #[blockio(input output)]
+///     u: Input<f64>,
+///     #[blockio(output)]
+///     y: Output<f64>,
+/// }
+///
+/// fn main() {}
+/// ```
+///
+/// The block must have a name field
+///
+/// ```compile_fail
+/// extern crate control_system_lib as control_system;
+/// use control_system::{io::{Input, Output}, BlockIO};
+///
+/// #[derive(BlockIO)]
+/// struct Gain {
+///     #[blockio(input)]
+///     u: Input<f64>,
+///     #[blockio(output)]
+///     y: Output<f64>,
+/// }
+///
+/// fn main() {}
+/// ```
+///
+/// And must be a struct with named fields
+///
+/// ```compile_fail
+/// extern crate control_system_lib as control_system;
+/// use control_system::BlockIO;
+///
+/// #[derive(BlockIO)]
+/// union Value {
+///     int: i64,
+///     float: f64,
+/// }
+///
+/// fn main() {}
+/// ```
+///
+/// ```compile_fail
+/// extern crate control_system_lib as control_system;
+/// use control_system::{io::Input, BlockIO};
+///
+/// #[derive(BlockIO)]
+/// struct Gain(String, Input<f64>);
+///
+/// fn main() {}
+/// ```
+pub struct InvalidBlock;
+
+/// Two ports of the same direction cannot share a name
+///
+/// ```compile_fail
+/// extern crate control_system_lib as control_system;
+/// use control_system::{io::{Input, Output}, BlockIO};
+///
+/// #[derive(BlockIO)]
+/// struct Gain {
+///     #[blockio(block_name)]
+///     name: String,
+///     #[blockio(input, name = "u")]
+///     u1: Input<f64>,
+///     #[blockio(input, name = "u")]
+///     u2: Input<f64>,
+///     #[blockio(output)]
+///     y: Output<f64>,
+/// }
+///
+/// fn main() {}
+/// ```
+///
+/// While an input and an output can
+///
+/// ```
+/// extern crate control_system_lib as control_system;
+/// use control_system::{io::{Input, Output}, BlockIO};
+///
+/// #[derive(BlockIO)]
+/// struct Gain {
+///     #[blockio(block_name)]
+///     name: String,
+///     #[blockio(input, name = "x")]
+///     u: Input<f64>,
+///     #[blockio(output, name = "x")]
+///     y: Output<f64>,
+/// }
+///
+/// fn main() {}
+/// ```
+pub struct DuplicatePort;
+
+/// A `blockio` attribute without arguments is rejected
+///
+/// ```compile_fail
+/// extern crate control_system_lib as control_system;
+/// use control_system::{io::{Input, Output}, BlockIO};
+///
+/// #[derive(BlockIO)]
+/// struct Gain {
+///     #[blockio(block_name)]
+///     name: String,
+///     #[blockio]
+///     u: Input<f64>,
+///     #[blockio(output)]
+///     y: Output<f64>,
+/// }
+///
+/// fn main() {}
+/// ```
+///
+/// As well as one with a value instead of a list
+///
+/// ```compile_fail
+/// extern crate control_system_lib as control_system;
+/// use control_system::{io::{Input, Output}, BlockIO};
+///
+/// #[derive(BlockIO)]
+/// struct Gain {
+///     #[blockio(block_name)]
+///     name: String,
+///     #[blockio = "input"]
+///     u: Input<f64>,
+///     #[blockio(output)]
+///     y: Output<f64>,
+/// }
+///
+/// fn main() {}
+/// ```
+pub struct BareAttribute;
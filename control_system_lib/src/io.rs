@@ -1,18 +1,52 @@
 use std::{
     any::{Any, TypeId},
-    cell::RefCell,
+    cell::{OnceCell, RefCell},
     marker::PhantomData,
     rc::Rc,
 };
 
+use nalgebra::DVector;
+
 use crate::{ControlSystemError, Result};
 
+/// Runtime description of the type of the values carried by a signal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignalType {
+    id: TypeId,
+    name: &'static str,
+}
+
+impl SignalType {
+    pub fn of<T: 'static>() -> Self {
+        SignalType {
+            id: TypeId::of::<T>(),
+            name: std::any::type_name::<T>(),
+        }
+    }
+
+    pub fn id(&self) -> TypeId {
+        self.id
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AnySignal {
-    value: Rc<RefCell<dyn Any>>, // Option<T>
+    value: Rc<dyn Any>, // RefCell<Option<T>>
     name: Option<String>,
+    /// Name of the block producing this signal, if any
+    producer: Option<String>,
+    /// Unit of measurement of the values, eg. "m/s"
+    unit: Option<String>,
     signal_type_id: TypeId,
     signal_type_name: &'static str,
+    /// Copies an `Option<T>` into a new storage, see [`AnySignal::duplicate`]
+    duplicate_value: fn(&dyn Any) -> Rc<dyn Any>,
+    /// Assigns an `Option<T>` to another, see [`AnySignal::copy_from`]
+    copy_value: fn(&dyn Any, &dyn Any),
 }
 
 impl AnySignal {
@@ -27,66 +61,240 @@ impl AnySignal {
     pub fn signal_type_name(&self) -> &str {
         self.signal_type_name
     }
+
+    pub fn signal_type(&self) -> SignalType {
+        SignalType {
+            id: self.signal_type_id,
+            name: self.signal_type_name,
+        }
+    }
+
+    pub fn producer(&self) -> Option<&str> {
+        self.producer.as_deref()
+    }
+
+    pub fn unit(&self) -> Option<&str> {
+        self.unit.as_deref()
+    }
 }
 
 impl AnySignal {
-    pub(crate) fn new<T: 'static>() -> Self {
+    pub(crate) fn new<T: Clone + 'static>() -> Self {
         AnySignal {
             value: Rc::new(RefCell::new(Option::<T>::None)),
             name: None,
+            producer: None,
+            unit: None,
             signal_type_id: TypeId::of::<T>(),
             signal_type_name: std::any::type_name::<T>(),
+            duplicate_value: duplicate_value::<T>,
+            copy_value: copy_value::<T>,
         }
     }
 
-    pub(crate) fn try_get<T: Clone + 'static>(&self) -> Result<Option<T>, ControlSystemError> {
-        self.value
-            .borrow()
-            .downcast_ref::<Option<T>>()
-            .ok_or(ControlSystemError::TypeError {
-                signal: self.name.clone().unwrap(),
-                typename: std::any::type_name::<T>().to_string(),
+    /// Creates a signal with the same name and type, holding a copy of the current
+    /// value in a separate storage
+    pub(crate) fn duplicate(&self) -> AnySignal {
+        AnySignal {
+            value: (self.duplicate_value)(&*self.value),
+            ..self.clone()
+        }
+    }
+
+    /// Overwrites the value of this signal with the value of `other`
+    pub(crate) fn copy_from(&self, other: &AnySignal) -> Result<()> {
+        if other.signal_type_id != self.signal_type_id {
+            return Err(ControlSystemError::TypeError {
+                signal: self.display_name(),
+                typename: other.signal_type_name.to_string(),
                 signal_typename: self.signal_type_name.to_string(),
-            })
-            .map(|v| v.clone())
+                producer: self.producer.clone(),
+            });
+        }
+
+        (self.copy_value)(&*other.value, &*self.value);
+        Ok(())
     }
 
-    pub(crate) fn get<T: Clone + 'static>(&self) -> Option<T> {
-        self.try_get().unwrap()
+    /// Storage of the value of the signal, if it carries values of type `T`
+    pub(crate) fn typed<T: 'static>(&self) -> Result<Rc<RefCell<Option<T>>>> {
+        self.value
+            .clone()
+            .downcast::<RefCell<Option<T>>>()
+            .map_err(|_| self.type_error::<T>())
+    }
+
+    fn typed_ref<T: 'static>(&self) -> Result<&RefCell<Option<T>>> {
+        self.value
+            .downcast_ref::<RefCell<Option<T>>>()
+            .ok_or_else(|| self.type_error::<T>())
+    }
+
+    fn type_error<T>(&self) -> ControlSystemError {
+        ControlSystemError::TypeError {
+            signal: self.display_name(),
+            typename: std::any::type_name::<T>().to_string(),
+            signal_typename: self.signal_type_name.to_string(),
+            producer: self.producer.clone(),
+        }
+    }
+
+    pub(crate) fn try_get<T: Clone + 'static>(&self) -> Result<Option<T>, ControlSystemError> {
+        Ok(self.typed_ref::<T>()?.borrow().clone())
     }
 
     pub(crate) fn try_set<T: 'static>(&self, value: T) -> Result<()> {
-        let mut v = self.value.borrow_mut();
-        *v.downcast_mut::<Option<T>>()
-            .ok_or(ControlSystemError::TypeError {
-                signal: self.name.clone().unwrap(),
-                typename: std::any::type_name::<T>().to_string(),
-                signal_typename: self.signal_type_name.to_string(),
-            })? = Some(value);
+        *self.typed_ref::<T>()?.borrow_mut() = Some(value);
         Ok(())
     }
 
-    pub(crate) fn set<T: 'static>(&self, value: T) {
-        self.try_set(value).unwrap();
+    /// Whether the signal holds a floating point value that is NaN or infinite
+    pub(crate) fn is_non_finite(&self) -> bool {
+        self.float_value().is_some_and(|v| !v.is_finite())
+    }
+
+    pub(crate) fn is_float(&self) -> bool {
+        self.signal_type_id == TypeId::of::<f64>() || self.signal_type_id == TypeId::of::<f32>()
+    }
+
+    /// Current value of a floating point signal, as a f64. None for other types or if
+    /// the signal has not been written yet.
+    pub(crate) fn float_value(&self) -> Option<f64> {
+        if let Ok(v) = self.typed_ref::<f64>() {
+            *v.borrow()
+        } else if let Ok(v) = self.typed_ref::<f32>() {
+            v.borrow().map(f64::from)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the signal carries floating point scalars or [`DVector`]s, whose values
+    /// can be read with [`AnySignal::float_values`]
+    pub(crate) fn has_float_values(&self) -> bool {
+        self.is_float()
+            || self.signal_type_id == TypeId::of::<DVector<f64>>()
+            || self.signal_type_id == TypeId::of::<DVector<f32>>()
+    }
+
+    /// Current values of a floating point scalar or vector signal, as f64. None for
+    /// other types or if the signal has not been written yet.
+    pub(crate) fn float_values(&self) -> Option<Vec<f64>> {
+        if let Ok(v) = self.typed_ref::<DVector<f64>>() {
+            v.borrow().as_ref().map(|v| v.iter().copied().collect())
+        } else if let Ok(v) = self.typed_ref::<DVector<f32>>() {
+            v.borrow()
+                .as_ref()
+                .map(|v| v.iter().copied().map(f64::from).collect())
+        } else {
+            self.float_value().map(|v| vec![v])
+        }
+    }
+
+    /// Same as [`AnySignal::float_value`], failing if the signal has not been written
+    pub(crate) fn try_float_value(&self) -> Result<f64> {
+        self.float_value()
+            .ok_or_else(|| ControlSystemError::EmptySignal(self.display_name()))
+    }
+
+    /// Name of the signal in errors, with a placeholder if it was never named
+    fn display_name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| "<unnamed>".to_string())
     }
 
     pub(crate) fn set_name(&mut self, name: &str) {
         self.name = Some(name.to_string());
     }
+
+    pub(crate) fn set_producer(&mut self, producer: &str) {
+        self.producer = Some(producer.to_string());
+    }
+
+    pub(crate) fn set_unit(&mut self, unit: Option<&str>) {
+        self.unit = unit.map(str::to_string);
+    }
+}
+
+fn duplicate_value<T: Clone + 'static>(value: &dyn Any) -> Rc<dyn Any> {
+    let value = value.downcast_ref::<RefCell<Option<T>>>().unwrap();
+    Rc::new(value.clone())
+}
+
+fn copy_value<T: Clone + 'static>(from: &dyn Any, to: &dyn Any) {
+    let from = from.downcast_ref::<RefCell<Option<T>>>().unwrap();
+    let to = to.downcast_ref::<RefCell<Option<T>>>().unwrap();
+    to.borrow_mut().clone_from(&from.borrow());
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Clone)]
 pub struct Input<T> {
-    phantom: PhantomData<T>,
     signal: Option<AnySignal>,
+    /// Storage of the connected signal, downcast once instead of on every read
+    typed: OnceCell<Rc<RefCell<Option<T>>>>,
+    default: Option<T>,
+    /// Name of the port of the input, known once the block is added to a system
+    port: Option<String>,
+}
+
+impl<T> Default for Input<T> {
+    fn default() -> Self {
+        Input {
+            signal: None,
+            typed: OnceCell::new(),
+            default: None,
+            port: None,
+        }
+    }
+}
+
+impl<T> Input<T> {
+    /// Creates an input returning `default` when it is left unconnected.
+    /// The corresponding port must be marked with `#[blockio(input, optional)]`.
+    pub fn with_default(default: T) -> Self {
+        Input {
+            signal: None,
+            typed: OnceCell::new(),
+            default: Some(default),
+            port: None,
+        }
+    }
 }
 
 impl<T> Input<T>
 where
     T: Clone + 'static,
 {
+    /// Reads the current value of the connected signal, or the default value if the
+    /// input is not connected.
+    ///
+    /// Panics if the input is not connected and has no default, or if the signal has
+    /// not been written yet. See [`Input::try_get`] for a non-panicking version.
     pub fn get(&self) -> T {
-        self.signal.as_ref().unwrap().get::<T>().unwrap()
+        match &self.signal {
+            Some(signal) => self.typed_value(signal).unwrap().borrow().clone().unwrap(),
+            None => self.default.clone().unwrap(),
+        }
+    }
+
+    pub fn try_get(&self) -> Result<T> {
+        let Some(signal) = self.signal.as_ref() else {
+            return self.default.clone().ok_or_else(|| self.unconnected());
+        };
+
+        self.typed_value(signal)?
+            .borrow()
+            .clone()
+            .ok_or_else(|| ControlSystemError::EmptySignal(self.describe(signal)))
+    }
+
+    /// Typed handle on the connected signal, to read it without going through the
+    /// input. Fails if the input is not connected or carries another type.
+    pub fn handle(&self) -> Result<SignalHandle<T>> {
+        let signal = self.signal.as_ref().ok_or_else(|| self.unconnected())?;
+
+        Ok(SignalHandle {
+            value: self.typed_value(signal)?.clone(),
+        })
     }
 }
 
@@ -94,47 +302,89 @@ impl<T> Input<T>
 where
     T: 'static,
 {
+    pub fn signal_type(&self) -> SignalType {
+        SignalType::of::<T>()
+    }
+
+    /// Connects the input to `signal`, checking once that it carries values of type `T`
     pub fn connect(&mut self, signal: &AnySignal) -> Result<()> {
         debug_assert!(self.signal.is_none(), "Signal is already connected!");
 
-        if signal.signal_type_id() != TypeId::of::<T>() {
-            return Err(ControlSystemError::TypeError {
-                signal: signal.name.clone().unwrap(),
-                typename: std::any::type_name::<T>().to_string(),
-                signal_typename: signal.signal_type_name.to_string(),
-            });
-        }
-
+        self.typed = OnceCell::from(signal.typed::<T>()?);
         self.signal = Some(signal.clone());
         Ok(())
     }
+
+    /// Storage of the connected `signal`, downcast on the first read if the input was
+    /// not connected through [`Input::connect`]
+    fn typed_value(&self, signal: &AnySignal) -> Result<&Rc<RefCell<Option<T>>>> {
+        if let Some(typed) = self.typed.get() {
+            return Ok(typed);
+        }
+
+        let typed = signal.typed::<T>()?;
+        Ok(self.typed.get_or_init(|| typed))
+    }
 }
 
 impl<T> Input<T> {
+    pub fn is_connected(&self) -> bool {
+        self.signal.is_some()
+    }
+
     pub fn get_signal(&self) -> &Option<AnySignal> {
         &self.signal
     }
 
+    /// The signal may be replaced through the returned reference, so the typed storage
+    /// is looked up again on the next read
     pub fn get_signal_mut(&mut self) -> &mut Option<AnySignal> {
+        self.typed.take();
         &mut self.signal
     }
 
+    /// Like [`Input::get_signal_mut`], also recording the name of the `port` the input
+    /// is exposed as, to report it in errors
+    pub fn port_signal_mut(&mut self, port: &str) -> &mut Option<AnySignal> {
+        self.port = Some(port.to_string());
+        self.get_signal_mut()
+    }
+
     pub fn signal_name(&self) -> String {
         self.signal.as_ref().unwrap().name.as_ref().unwrap().clone()
     }
+
+    /// Name of the connected `signal` in errors, falling back to the name of the port
+    /// if the signal was never named
+    fn describe(&self, signal: &AnySignal) -> String {
+        match (&signal.name, &self.port) {
+            (Some(name), _) | (None, Some(name)) => name.clone(),
+            (None, None) => signal.display_name(),
+        }
+    }
+
+    fn unconnected(&self) -> ControlSystemError {
+        ControlSystemError::UnconnectedInput {
+            port: self.port.clone(),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct Output<T> {
     phantom: PhantomData<T>,
     signal: AnySignal,
+    /// Storage of the signal, downcast once instead of on every write
+    typed: OnceCell<Rc<RefCell<Option<T>>>>,
 }
 
-impl<T: 'static> Default for Output<T> {
+// Signals are read by cloning their value, so only `Clone` types can be carried
+impl<T: Clone + 'static> Default for Output<T> {
     fn default() -> Self {
         Output {
             phantom: PhantomData,
             signal: AnySignal::new::<T>(),
+            typed: OnceCell::new(),
         }
     }
 }
@@ -144,7 +394,17 @@ where
     T: 'static,
 {
     pub fn set(&mut self, value: T) {
-        self.signal.set(value)
+        let typed = self.typed.get_or_init(|| self.signal.typed::<T>().unwrap());
+        *typed.borrow_mut() = Some(value);
+    }
+
+    /// Typed handle on the signal, to write it without going through the output
+    pub fn handle(&self) -> SignalHandle<T> {
+        let typed = self.typed.get_or_init(|| self.signal.typed::<T>().unwrap());
+
+        SignalHandle {
+            value: typed.clone(),
+        }
     }
 }
 
@@ -153,7 +413,9 @@ impl<T> Output<T> {
         &self.signal
     }
 
+    /// See [`Input::get_signal_mut`]
     pub fn get_signal_mut(&mut self) -> &mut AnySignal {
+        self.typed.take();
         &mut self.signal
     }
 
@@ -161,3 +423,240 @@ impl<T> Output<T> {
         self.signal.name.as_ref().unwrap().clone()
     }
 }
+
+/// Typed access to the value of a signal, obtained from a connected [`Input`] with
+/// [`Input::handle`] or from an [`Output`] with [`Output::handle`].
+///
+/// Reading and writing through a handle skips the checks done by [`Input::get`] and
+/// [`Output::set`] on every call. A block can get its handles on its first step and
+/// keep them for the rest of the run: they are not updated if the block is connected
+/// to other signals afterwards.
+#[derive(Debug, Clone)]
+pub struct SignalHandle<T> {
+    value: Rc<RefCell<Option<T>>>,
+}
+
+impl<T: Clone> SignalHandle<T> {
+    /// Current value of the signal. Panics if it has not been written yet.
+    pub fn get(&self) -> T {
+        self.value.borrow().clone().unwrap()
+    }
+
+    /// Current value of the signal, None if it has not been written yet
+    pub fn try_get(&self) -> Option<T> {
+        self.value.borrow().clone()
+    }
+}
+
+impl<T> SignalHandle<T> {
+    pub fn set(&self, value: T) {
+        *self.value.borrow_mut() = Some(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BlockIO;
+
+    #[test]
+    fn try_get_reports_missing_values() {
+        let mut input = Input::<f64>::default();
+        assert!(matches!(
+            input.try_get(),
+            Err(ControlSystemError::UnconnectedInput { port: None })
+        ));
+
+        let mut output = Output::<f64>::default();
+        output.get_signal_mut().set_name("x");
+        input.connect(output.get_signal()).unwrap();
+        assert!(matches!(
+            input.try_get(),
+            Err(ControlSystemError::EmptySignal(signal)) if signal == "x"
+        ));
+
+        output.set(1.5);
+        assert_eq!(input.try_get().unwrap(), 1.5);
+    }
+
+    #[derive(BlockIO)]
+    struct Mux {
+        #[blockio(block_name)]
+        name: String,
+
+        #[blockio(input_arr, name = "u", base = 0)]
+        inputs: [Input<f64>; 2],
+
+        #[blockio(output_arr, name = "y", format = "{name}[{index}]")]
+        outputs: [Output<f64>; 2],
+    }
+
+    #[test]
+    fn array_port_names() {
+        let mut mux = Mux {
+            name: "mux".to_string(),
+            inputs: [Input::default(), Input::default()],
+            outputs: [Output::default(), Output::default()],
+        };
+
+        let mut inputs: Vec<String> = mux.input_signals().into_keys().collect();
+        inputs.sort();
+        assert_eq!(inputs, ["u0", "u1"]);
+
+        let mut outputs: Vec<String> = mux.output_signals().into_keys().collect();
+        outputs.sort();
+        assert_eq!(outputs, ["y[1]", "y[2]"]);
+    }
+
+    #[derive(BlockIO)]
+    struct Controller {
+        #[blockio(block_name)]
+        name: String,
+
+        #[blockio(input, name = "reference")]
+        r: Input<f64>,
+
+        #[blockio(input)]
+        measurement: Input<f64>,
+
+        #[blockio(input, optional)]
+        feedforward: Input<f64>,
+
+        #[blockio(output)]
+        command: Output<f64>,
+
+        #[blockio(output, optional)]
+        saturated: Output<bool>,
+
+        #[allow(unused)]
+        gain: f64,
+    }
+
+    #[test]
+    fn port_names_of_immutable_block() {
+        let controller = Controller {
+            name: "controller".to_string(),
+            r: Input::default(),
+            measurement: Input::default(),
+            feedforward: Input::default(),
+            command: Output::default(),
+            saturated: Output::default(),
+            gain: 1.0,
+        };
+
+        assert_eq!(
+            controller.input_port_names(),
+            ["reference", "measurement", "feedforward"]
+        );
+        assert_eq!(controller.output_port_names(), ["command", "saturated"]);
+    }
+
+    #[test]
+    fn handles_match_input_and_output() {
+        let mut output = Output::<f64>::default();
+        *output.get_signal_mut() = AnySignal {
+            name: Some("u".to_string()),
+            ..AnySignal::new::<f64>()
+        };
+        let mut input = Input::<f64>::default();
+        input.connect(output.get_signal()).unwrap();
+
+        let input_handle = input.handle().unwrap();
+        let output_handle = output.handle();
+        assert_eq!(input_handle.try_get(), None);
+
+        for v in [1.0, -2.5, 1e9] {
+            output.set(v);
+            assert_eq!(input_handle.get(), input.get());
+            assert_eq!(input_handle.get(), v);
+
+            output_handle.set(2.0 * v);
+            assert_eq!(input.get(), input_handle.get());
+            assert_eq!(input.get(), 2.0 * v);
+        }
+
+        let wrong_type = Input::<i32>::default().connect(output.get_signal());
+        assert!(matches!(
+            wrong_type,
+            Err(ControlSystemError::TypeError { .. })
+        ));
+    }
+
+    #[derive(BlockIO)]
+    struct Reader {
+        #[blockio(block_name)]
+        name: String,
+
+        #[blockio(input)]
+        u: Input<f64>,
+
+        #[blockio(input, optional)]
+        offset: Input<f64>,
+    }
+
+    #[test]
+    fn try_get_unconnected_input() {
+        let input = Input::<f64>::default();
+        assert!(matches!(
+            input.try_get(),
+            Err(ControlSystemError::UnconnectedInput { port: None })
+        ));
+
+        let input = Input::with_default(1.0);
+        assert_eq!(input.try_get().unwrap(), 1.0);
+    }
+
+    #[test]
+    fn try_get_reports_port_name() {
+        let mut reader = Reader {
+            name: "reader".to_string(),
+            u: Input::default(),
+            offset: Input::default(),
+        };
+        reader.input_signals();
+
+        let err = reader.u.try_get().unwrap_err();
+        assert!(matches!(
+            &err,
+            ControlSystemError::UnconnectedInput { port: Some(port) } if port == "u"
+        ));
+        assert_eq!(err.to_string(), "Input 'u' is not connected to any signal");
+        assert!(reader.offset.handle().is_err());
+    }
+
+    #[test]
+    fn try_get_empty_signal() {
+        let mut input = Input::<f64>::default();
+        input
+            .connect(&AnySignal {
+                name: Some("u".to_string()),
+                ..AnySignal::new::<f64>()
+            })
+            .unwrap();
+
+        assert!(matches!(
+            input.try_get(),
+            Err(ControlSystemError::EmptySignal(signal)) if signal == "u"
+        ));
+    }
+    #[test]
+    fn errors_on_unnamed_signals() {
+        let mut input = Input::<f64>::default();
+        input.connect(&AnySignal::new::<f64>()).unwrap();
+        assert!(matches!(
+            input.try_get(),
+            Err(ControlSystemError::EmptySignal(signal)) if signal == "<unnamed>"
+        ));
+
+        input.port_signal_mut("u");
+        assert!(matches!(
+            input.try_get(),
+            Err(ControlSystemError::EmptySignal(signal)) if signal == "u"
+        ));
+
+        assert!(matches!(
+            AnySignal::new::<f64>().typed::<i32>(),
+            Err(ControlSystemError::TypeError { signal, .. }) if signal == "<unnamed>"
+        ));
+    }
+}
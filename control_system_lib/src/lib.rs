@@ -1,6 +1,12 @@
+mod autotune;
+mod config;
 mod controlblock;
 mod controlsystem;
+#[cfg(doctest)]
+mod derive_tests;
 mod parameters;
+mod registry;
+mod subsystem;
 
 pub mod io;
 pub mod numeric;
@@ -8,9 +14,20 @@ use std::error::Error;
 
 pub use control_system_derive::BlockIO;
 
-pub use controlblock::{Block, BlockIO, StepInfo, StepResult};
-pub use controlsystem::{ControlSystem, ControlSystemBuilder, ControlSystemParameters};
+// Lets the derived code refer to this crate as `control_system` in the tests
+#[cfg(test)]
+extern crate self as control_system;
+
+pub use autotune::{relay_autotune, RelayAutotuneParams, RelayAutotuneResult};
+pub use config::{BlockConfig, SystemConfig};
+pub use controlblock::{downcast_state, Block, BlockIO, BlockState, StepInfo, StepResult};
+pub use controlsystem::{
+    AlgebraicLoopParams, ControlSystem, ControlSystemBuilder, ControlSystemParameters,
+    StateSnapshot,
+};
 pub use parameters::{ParameterStore, ParameterStoreError};
+pub use registry::{invalid_params, parse_params, BlockRegistry};
+pub use subsystem::SubSystem;
 
 use thiserror::Error;
 
@@ -21,6 +38,9 @@ pub enum ControlSystemError {
     #[error("A block named '{0}' is already present in the control system")]
     DuplicateBlockName(String),
 
+    #[error("A signal named '{0}' is already present in the control system")]
+    DuplicateSignal(String),
+
     #[error("Could not connect port {port} of block '{blockname}': No signal named '{signal}'")]
     UnknownSignal {
         port: String,
@@ -28,11 +48,44 @@ pub enum ControlSystemError {
         blockname: String,
     },
 
+    #[error("Some inputs are connected to signals with no producer: {}", .0.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("; "))]
+    MissingProducers(Vec<ControlSystemError>),
+
+    #[error("No signal named '{0}' in the control system")]
+    UnknownSignalName(String),
+
+    #[error("No block named '{0}' in the control system")]
+    UnknownBlock(String),
+
+    #[error("No block type named '{0}' in the registry")]
+    UnknownBlockType(String),
+
+    #[error("Invalid parameters for block '{blockname}': {source}")]
+    InvalidBlockParams {
+        blockname: String,
+        source: serde_json::Error,
+    },
+
+    #[error("Block '{0}' does not support changing its parameters")]
+    ParamsNotSupported(String),
+
+    #[error(
+        "Cannot remove block '{blockname}': its outputs are still consumed by blocks {consumers:?}"
+    )]
+    BlockInUse {
+        blockname: String,
+        consumers: Vec<String>,
+    },
+
     #[error("No port named '{port}' in block '{blockname}'")]
     UnknownPort { port: String, blockname: String },
 
-    #[error("Control system presents a cycle containing node '{0}'")]
-    CycleDetected(String),
+    #[error("Port '{port}' of block '{blockname}' is already connected")]
+    PortAlreadyConnected { port: String, blockname: String },
+
+    /// Blocks forming a cycle with no delay, in the order the signals flow through them
+    #[error("Control system presents a cycle with no delay: {}", cycle_description(.0))]
+    CycleDetected(Vec<String>),
 
     #[error("Cannot connect output '{port}' of block '{blockname}' to signal '{signal}': The signal is already connected to another output.")]
     MultipleProducers {
@@ -41,19 +94,70 @@ pub enum ControlSystemError {
         blockname: String,
     },
 
-    #[error("Ports {ports:?} in block '{blockname}' have not been connected")]
-    UnconnectedPorts {
+    #[error("Inputs {ports:?} in block '{blockname}' have not been connected")]
+    UnconnectedInputs {
+        ports: Vec<String>,
+        blockname: String,
+    },
+
+    #[error("Outputs {ports:?} in block '{blockname}' have not been connected")]
+    UnconnectedOutputs {
         ports: Vec<String>,
         blockname: String,
     },
 
-    #[error("Expected signal '{signal}' to be a '{typename}', but is a '{signal_typename}'")]
+    #[error("Input{} is not connected to any signal", port_description(.port))]
+    UnconnectedInput {
+        /// Name of the port, `None` if the input was never added to a system
+        port: Option<String>,
+    },
+
+    #[error("Signal '{0}' has not been written yet")]
+    EmptySignal(String),
+
+    #[error(transparent)]
+    ConnectionTypeMismatch(Box<TypeMismatch>),
+
+    #[error(transparent)]
+    ConnectionUnitMismatch(Box<UnitMismatch>),
+
+    #[error("Signal '{signal}' is NaN or infinite at step {k}")]
+    NonFiniteSignal { signal: String, k: usize },
+
+    #[error("Expected signal '{signal}', produced by {}, to be a '{typename}', but is a '{signal_typename}'", producer_description(.producer))]
     TypeError {
         signal: String,
         typename: String,
         signal_typename: String,
+        /// Block producing the signal, `None` for external signals
+        producer: Option<String>,
+    },
+
+    #[error("The algebraic loop between blocks {blocks:?} did not converge in {iterations} iterations at step {k}")]
+    AlgebraicLoopNotConverged {
+        blocks: Vec<String>,
+        iterations: usize,
+        k: usize,
     },
 
+    #[error("The algebraic loop between blocks {blocks:?} produces no floating point signal to check its convergence")]
+    UncheckedAlgebraicLoop { blocks: Vec<String> },
+
+    #[error("Step time {t} is not after the time of the previous step, {previous}")]
+    NonMonotonicTime { t: f64, previous: f64 },
+
+    #[error("External signal '{signal}' has type '{typename}', which is not supported in configurations")]
+    UnsupportedSignalType { signal: String, typename: String },
+
+    #[error("Step time {0} is not finite")]
+    NonFiniteTime(f64),
+
+    #[error("Relay autotuning failed: {0}")]
+    AutotuneFailed(String),
+
+    #[error("Could not restore the state of block '{blockname}': {reason}")]
+    InvalidState { blockname: String, reason: String },
+
     #[error(transparent)]
     ParameterError {
         #[from]
@@ -64,8 +168,52 @@ pub enum ControlSystemError {
     Other(#[from] Box<dyn std::error::Error + Send + Sync + 'static>),
 }
 
+/// A connection between an input and a signal of a different type
+#[derive(Error, Debug)]
+#[error("Cannot connect input '{port}' of block '{consumer}', expecting a '{consumer_type}', to signal '{signal}': {} produces a '{producer_type}'", producer_description(.producer))]
+pub struct TypeMismatch {
+    pub signal: String,
+    /// Block producing the signal, `None` for external signals
+    pub producer: Option<String>,
+    pub producer_type: String,
+    pub consumer: String,
+    pub port: String,
+    pub consumer_type: String,
+}
+
+/// A connection between an input and a signal expressed in a different unit
+#[derive(Error, Debug)]
+#[error("Input '{port}' of block '{blockname}' expects values in '{unit}', but signal '{signal}' is in '{signal_unit}'")]
+pub struct UnitMismatch {
+    pub signal: String,
+    pub signal_unit: String,
+    pub blockname: String,
+    pub port: String,
+    pub unit: String,
+}
+
+fn cycle_description(blocks: &[String]) -> String {
+    let mut path: Vec<&str> = blocks.iter().map(String::as_str).collect();
+    path.extend(blocks.first().map(String::as_str));
+    path.join(" -> ")
+}
+
+fn producer_description(producer: &Option<String>) -> String {
+    match producer {
+        Some(producer) => format!("block '{}'", producer),
+        None => "the external source".to_string(),
+    }
+}
+
+fn port_description(port: &Option<String>) -> String {
+    match port {
+        Some(port) => format!(" '{}'", port),
+        None => String::new(),
+    }
+}
+
 impl ControlSystemError {
     pub fn from_boxed<E: Error + Send + Sync + 'static>(e: E) -> Self {
         ControlSystemError::Other(Box::new(e) as Box<dyn Error + Send + Sync + 'static>)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,63 @@
+use nalgebra::DMatrix;
+
+/// Discretizes the continuous system `dx/dt = A x + B u` with a zero-order hold on the
+/// input over steps of `dt`, returning `(Ad, Bd)` of `x(k+1) = Ad x(k) + Bd u(k)`.
+///
+/// Both are read from the exponential of the augmented matrix:
+///
+/// exp([A B; 0 0] dt) = [Ad Bd; 0 I]
+///
+/// Panics if `a` is not square or `b` does not have as many rows as `a`.
+pub fn c2d(a: &DMatrix<f64>, b: &DMatrix<f64>, dt: f64) -> (DMatrix<f64>, DMatrix<f64>) {
+    let n = a.nrows();
+    let m = b.ncols();
+
+    assert!(a.is_square(), "A must be square, is {:?}", a.shape());
+    assert!(b.nrows() == n, "B must have {} rows, has {}", n, b.nrows());
+
+    let mut augmented = DMatrix::zeros(n + m, n + m);
+    augmented.view_mut((0, 0), (n, n)).copy_from(&(a * dt));
+    augmented.view_mut((0, n), (n, m)).copy_from(&(b * dt));
+
+    let exp = augmented.exp();
+    let ad = exp.view((0, 0), (n, n)).into_owned();
+    let bd = exp.view((0, n), (n, m)).into_owned();
+
+    (ad, bd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_order_system() {
+        // dx/dt = -2 x + 3 u
+        let dt = 0.1;
+        let (ad, bd) = c2d(
+            &DMatrix::from_element(1, 1, -2.0),
+            &DMatrix::from_element(1, 1, 3.0),
+            dt,
+        );
+
+        let decay = (-2.0 * dt).exp();
+        assert!((ad[(0, 0)] - decay).abs() < 1e-12, "{ad}");
+        assert!((bd[(0, 0)] - 1.5 * (1.0 - decay)).abs() < 1e-12, "{bd}");
+    }
+
+    #[test]
+    fn double_integrator() {
+        // A is singular, so Bd can not be computed as A^-1 (Ad - I) B
+        let dt = 0.5;
+        let (ad, bd) = c2d(
+            &DMatrix::from_row_slice(2, 2, &[0.0, 1.0, 0.0, 0.0]),
+            &DMatrix::from_row_slice(2, 1, &[0.0, 1.0]),
+            dt,
+        );
+
+        let expected_ad = DMatrix::from_row_slice(2, 2, &[1.0, dt, 0.0, 1.0]);
+        let expected_bd = DMatrix::from_row_slice(2, 1, &[dt * dt / 2.0, dt]);
+        assert!((ad - expected_ad).abs().max() < 1e-12);
+        assert!((bd - expected_bd).abs().max() < 1e-12);
+    }
+}
@@ -0,0 +1,106 @@
+use nalgebra::{RealField, SMatrix, SVector};
+
+/// Jacobian of `f` at `x`, estimated with central differences.
+///
+/// Each component of `x` is perturbed proportionally to its magnitude, so that states
+/// of very different scales are estimated with the same relative accuracy. See
+/// [`jacobian_with_perturbation`] to choose the size of the perturbation.
+pub fn jacobian<T, const R: usize, const C: usize, F>(f: F, x: SVector<T, C>) -> SMatrix<T, R, C>
+where
+    T: RealField + Copy,
+    F: Fn(SVector<T, C>) -> SVector<T, R>,
+{
+    // Balances the truncation and rounding errors of central differences
+    jacobian_with_perturbation(f, x, T::default_epsilon().cbrt())
+}
+
+/// Same as [`jacobian`], perturbing `x[j]` by `perturbation * max(|x[j]|, 1)`: relative
+/// to the value for large components, absolute for components close to zero.
+pub fn jacobian_with_perturbation<T, const R: usize, const C: usize, F>(
+    f: F,
+    x: SVector<T, C>,
+    perturbation: T,
+) -> SMatrix<T, R, C>
+where
+    T: RealField + Copy,
+    F: Fn(SVector<T, C>) -> SVector<T, R>,
+{
+    let mut jacobian = SMatrix::<T, R, C>::zeros();
+    for j in 0..C {
+        let h = perturbation * x[j].abs().max(T::one());
+
+        let mut x_plus = x;
+        let mut x_minus = x;
+        x_plus[j] += h;
+        x_minus[j] -= h;
+
+        // Divide by the step actually taken, after rounding
+        let step = x_plus[j] - x_minus[j];
+        jacobian.set_column(j, &((f(x_plus) - f(x_minus)) / step));
+    }
+
+    jacobian
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{Matrix2, Matrix3, Vector2, Vector3};
+
+    fn quadratic(x: Vector3<f64>) -> Vector3<f64> {
+        Vector3::new(
+            x[0] * x[0] + x[1] * x[2],
+            2.0 * x[1] * x[1] - x[0],
+            x[0] * x[2],
+        )
+    }
+
+    fn quadratic_jacobian(x: Vector3<f64>) -> Matrix3<f64> {
+        Matrix3::new(
+            2.0 * x[0],
+            x[2],
+            x[1],
+            -1.0,
+            4.0 * x[1],
+            0.0,
+            x[2],
+            0.0,
+            x[0],
+        )
+    }
+
+    /// Largest difference between the elements of `a` and `b`, relative to the
+    /// magnitude of `b`
+    fn relative_error<const R: usize, const C: usize>(
+        a: SMatrix<f64, R, C>,
+        b: SMatrix<f64, R, C>,
+    ) -> f64 {
+        a.zip_map(&b, |a, b| (a - b).abs() / b.abs().max(1.0)).max()
+    }
+
+    #[test]
+    fn quadratic_field() {
+        let x = Vector3::new(1.5, -2.0, 0.5);
+        let expected = quadratic_jacobian(x);
+
+        assert!(relative_error(jacobian(quadratic, x), expected) < 1e-9);
+
+        // Central differences are exact on quadratic fields, whatever the perturbation
+        let coarse = jacobian_with_perturbation(quadratic, x, 0.1);
+        assert!(relative_error(coarse, expected) < 1e-12);
+    }
+
+    #[test]
+    fn components_of_different_scales() {
+        let x = Vector2::new(1e6, 1e-3);
+        let j = jacobian(|x: Vector2<f64>| x.component_mul(&x), x);
+        let expected = Matrix2::new(2e6, 0.0, 0.0, 2e-3);
+
+        for i in 0..2 {
+            let error = (j[(i, i)] - expected[(i, i)]).abs() / expected[(i, i)];
+            assert!(error < 1e-8, "{j}");
+        }
+        assert_eq!(j[(0, 1)], 0.0);
+        assert_eq!(j[(1, 0)], 0.0);
+    }
+}
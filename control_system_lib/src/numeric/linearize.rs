@@ -0,0 +1,74 @@
+use nalgebra::{RealField, SMatrix, SVector};
+
+use super::jacobian;
+
+/// Linearizes the continuous dynamics `dx/dt = f(t, x, u)` around the operating point
+/// `(x0, u0)` at time `t`, returning the matrices `(A, B)` of
+///
+/// d(dx)/dt = A dx + B du
+///
+/// with `dx = x - x0` and `du = u - u0`. The Jacobians are estimated with [`jacobian`].
+pub fn linearize<T, const N: usize, const M: usize, F>(
+    f: F,
+    t: T,
+    x0: SVector<T, N>,
+    u0: SVector<T, M>,
+) -> (SMatrix<T, N, N>, SMatrix<T, N, M>)
+where
+    T: RealField + Copy,
+    F: Fn(T, SVector<T, N>, SVector<T, M>) -> SVector<T, N>,
+{
+    let a = jacobian(|x| f(t, x, u0), x0);
+    let b = jacobian(|u| f(t, x0, u), u0);
+
+    (a, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{Matrix2, Vector1, Vector2};
+
+    const G_OVER_L: f64 = 9.81 / 0.5;
+
+    /// Pendulum with the angle measured from the upright position and a torque input,
+    /// per unit of inertia
+    fn pendulum(_: f64, x: Vector2<f64>, u: Vector1<f64>) -> Vector2<f64> {
+        Vector2::new(x[1], G_OVER_L * x[0].sin() - 0.1 * x[1] + u[0])
+    }
+
+    #[test]
+    fn pendulum_upright() {
+        let (a, b) = linearize(pendulum, 0.0, Vector2::zeros(), Vector1::zeros());
+
+        let expected = Matrix2::new(0.0, 1.0, G_OVER_L, -0.1);
+        assert!((a - expected).abs().max() < 1e-8, "{a}");
+        assert!((b - Vector2::new(0.0, 1.0)).abs().max() < 1e-8, "{b}");
+
+        // One unstable and one stable real pole, roots of s^2 + 0.1 s - g / l
+        let root = (0.01 + 4.0 * G_OVER_L).sqrt();
+        let mut poles: Vec<f64> = a.complex_eigenvalues().iter().map(|p| p.re).collect();
+        poles.sort_by(f64::total_cmp);
+        assert!((poles[0] - (-0.1 - root) / 2.0).abs() < 1e-6, "{poles:?}");
+        assert!((poles[1] - (-0.1 + root) / 2.0).abs() < 1e-6, "{poles:?}");
+
+        // The unstable mode moves the angle and the velocity in the same direction
+        let eigenvector = Vector2::new(1.0, poles[1]);
+        assert!((a * eigenvector - eigenvector * poles[1]).norm() < 1e-6);
+    }
+
+    #[test]
+    fn pendulum_hanging() {
+        let x0 = Vector2::new(std::f64::consts::PI, 0.0);
+        let (a, _) = linearize(pendulum, 0.0, x0, Vector1::zeros());
+
+        // Damped oscillation at about sqrt(g / l)
+        for pole in a.complex_eigenvalues().iter() {
+            assert!((pole.re + 0.05).abs() < 1e-6, "{pole}");
+            assert!(
+                (pole.im.abs() - (G_OVER_L - 0.0025).sqrt()).abs() < 1e-6,
+                "{pole}"
+            );
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use nalgebra::DMatrix;
+
+const MAX_ITER: usize = 100_000;
+const TOLERANCE: f64 = 1e-12;
+
+/// Gain `K` of the infinite-horizon discrete LQR for `x(k+1) = A x(k) + B u(k)`, with the
+/// control `u(k) = -K x(k)` minimizing the sum of `x' Q x + u' R u`.
+///
+/// The discrete algebraic Riccati equation is solved by iterating
+///
+/// P = Q + A' P A - A' P B (R + B' P B)^-1 B' P A
+///
+/// from `P = Q`, and `K = (R + B' P B)^-1 B' P A`. Returns None if the dimensions of the
+/// matrices are not consistent, or if the iteration does not converge, eg. when (A, B) is
+/// not stabilizable.
+pub fn lqr(
+    a: &DMatrix<f64>,
+    b: &DMatrix<f64>,
+    q: &DMatrix<f64>,
+    r: &DMatrix<f64>,
+) -> Option<DMatrix<f64>> {
+    let n = a.nrows();
+    let m = b.ncols();
+
+    if !a.is_square() || b.nrows() != n || q.shape() != (n, n) || r.shape() != (m, m) {
+        return None;
+    }
+
+    let at = a.transpose();
+    let bt = b.transpose();
+    let gain = |p: &DMatrix<f64>| -> Option<DMatrix<f64>> {
+        Some((r + &bt * p * b).try_inverse()? * &bt * p * a)
+    };
+
+    let mut p = q.clone();
+    for _ in 0..MAX_ITER {
+        let next = q + &at * &p * a - &at * &p * b * gain(&p)?;
+
+        let change = (&next - &p).amax();
+        p = next;
+
+        if !p.iter().all(|v| v.is_finite()) {
+            return None;
+        }
+        if change <= TOLERANCE * (1.0 + p.amax()) {
+            return gain(&p);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scalar(v: f64) -> DMatrix<f64> {
+        DMatrix::from_element(1, 1, v)
+    }
+
+    #[test]
+    fn scalar_integrator() {
+        // P solves P^2 = P + 1, so K = P / (1 + P) = 1 / P, the inverse of the golden ratio
+        let k = lqr(&scalar(1.0), &scalar(1.0), &scalar(1.0), &scalar(1.0)).unwrap();
+
+        let golden_ratio = (1.0 + 5f64.sqrt()) / 2.0;
+        assert!((k[(0, 0)] - 1.0 / golden_ratio).abs() < 1e-9, "{k}");
+    }
+
+    #[test]
+    fn double_integrator() {
+        let dt = 0.1;
+        let a = DMatrix::from_row_slice(2, 2, &[1.0, dt, 0.0, 1.0]);
+        let b = DMatrix::from_row_slice(2, 1, &[dt * dt / 2.0, dt]);
+        let q = DMatrix::identity(2, 2);
+        let r = scalar(0.1);
+        let k = lqr(&a, &b, &q, &r).unwrap();
+
+        // Precomputed
+        let expected = DMatrix::from_row_slice(1, 2, &[2.58570, 3.44344]);
+        assert!((&k - expected).amax() < 1e-3, "{k}");
+
+        let closed_loop = &a - &b * &k;
+        for pole in closed_loop.complex_eigenvalues().iter() {
+            assert!(pole.re.hypot(pole.im) < 1.0, "{pole}");
+        }
+    }
+
+    #[test]
+    fn unstabilizable_system() {
+        // Unstable and unaffected by the control
+        assert!(lqr(&scalar(2.0), &scalar(0.0), &scalar(1.0), &scalar(1.0)).is_none());
+    }
+
+    #[test]
+    fn inconsistent_dimensions() {
+        let a = DMatrix::identity(2, 2);
+        let b = DMatrix::from_element(2, 1, 1.0);
+        assert!(lqr(&a, &scalar(1.0), &DMatrix::identity(2, 2), &scalar(1.0)).is_none());
+        assert!(lqr(&a, &b, &scalar(1.0), &scalar(1.0)).is_none());
+        assert!(lqr(&a, &b, &DMatrix::identity(2, 2), &DMatrix::identity(2, 2)).is_none());
+    }
+}
@@ -1 +1,10 @@
-pub mod ode;
\ No newline at end of file
+mod discretize;
+mod jacobian;
+mod linearize;
+mod lqr;
+pub mod ode;
+
+pub use discretize::c2d;
+pub use jacobian::{jacobian, jacobian_with_perturbation};
+pub use linearize::linearize;
+pub use lqr::lqr;
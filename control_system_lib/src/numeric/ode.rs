@@ -1,7 +1,8 @@
-use nalgebra::SVector;
+use nalgebra::{RealField, SMatrix, SVector};
 
-pub trait ODESolver<T>
-{
+use super::jacobian;
+
+pub trait ODESolver<T> {
     fn solve<const D: usize, F>(f: F, t0: T, dt: T, y0: SVector<T, D>) -> SVector<T, D>
     where
         F: Fn(T, SVector<T, D>) -> SVector<T, D>;
@@ -9,32 +10,176 @@ pub trait ODESolver<T>
 
 pub struct RungeKutta4;
 
-impl ODESolver<f64> for RungeKutta4
+impl<T> ODESolver<T> for RungeKutta4
+where
+    T: RealField + Copy,
 {
-    fn solve<const D: usize, F>(f: F, t0: f64, dt: f64, y0: SVector<f64, D>) -> SVector<f64, D>
+    fn solve<const D: usize, F>(f: F, t0: T, dt: T, y0: SVector<T, D>) -> SVector<T, D>
     where
-        F: Fn(f64, SVector<f64, D>) -> SVector<f64, D>,
+        F: Fn(T, SVector<T, D>) -> SVector<T, D>,
     {
-        let hdt = dt;
+        let two: T = nalgebra::convert(2f64);
+        let six: T = nalgebra::convert(6f64);
+        let hdt = dt / two;
 
         let k1 = f(t0, y0);
         let k2 = f(t0 + hdt, y0 + k1 * hdt);
         let k3 = f(t0 + hdt, y0 + k2 * hdt);
         let k4 = f(t0 + dt, y0 + k3 * dt);
 
-        y0 + (k1 + 2f64*k2 + 2f64*k3 + k4) * dt / 6f64
+        y0 + (k1 + k2 * two + k3 * two + k4) * dt / six
     }
 }
 
-
 pub struct ForwardEuler;
 
-impl ODESolver<f64> for ForwardEuler
+impl<T> ODESolver<T> for ForwardEuler
+where
+    T: RealField + Copy,
 {
-    fn solve<const D: usize, F>(f: F, t0: f64, dt: f64, y0: SVector<f64, D>) -> SVector<f64, D>
+    fn solve<const D: usize, F>(f: F, t0: T, dt: T, y0: SVector<T, D>) -> SVector<T, D>
     where
-        F: Fn(f64, SVector<f64, D>) -> SVector<f64, D>,
+        F: Fn(T, SVector<T, D>) -> SVector<T, D>,
     {
         y0 + f(t0, y0) * dt
     }
-}
\ No newline at end of file
+}
+
+/// Implicit Euler method, stable on stiff systems at step sizes where the explicit
+/// methods diverge.
+///
+/// Each step solves `y1 = y0 + dt * f(t0 + dt, y1)` with Newton's method. The
+/// [`ODESolver`] implementation estimates the Jacobian of `f` with [`jacobian`],
+/// use [`BackwardEuler::solve_with_jacobian`] to provide it instead.
+pub struct BackwardEuler;
+
+impl BackwardEuler {
+    const MAX_NEWTON_ITER: usize = 20;
+
+    /// Same as [`ODESolver::solve`], with `jacobian(t, y)` the Jacobian of `f` at `y`
+    pub fn solve_with_jacobian<T, const D: usize, F, J>(
+        f: F,
+        jacobian: J,
+        t0: T,
+        dt: T,
+        y0: SVector<T, D>,
+    ) -> SVector<T, D>
+    where
+        T: RealField + Copy,
+        F: Fn(T, SVector<T, D>) -> SVector<T, D>,
+        J: Fn(T, SVector<T, D>) -> SMatrix<T, D, D>,
+    {
+        let t1 = t0 + dt;
+        let tolerance = T::default_epsilon().sqrt();
+
+        let mut y1 = y0;
+        for _ in 0..Self::MAX_NEWTON_ITER {
+            let residual = y1 - y0 - f(t1, y1) * dt;
+            let residual_jacobian = SMatrix::<T, D, D>::identity() - jacobian(t1, y1) * dt;
+
+            // A singular Jacobian leaves the best estimate so far
+            let Some(inverse) = residual_jacobian.try_inverse() else {
+                break;
+            };
+            let delta = inverse * residual;
+
+            y1 -= delta;
+            if delta.norm() <= tolerance * (T::one() + y1.norm()) {
+                break;
+            }
+        }
+
+        y1
+    }
+}
+
+impl<T> ODESolver<T> for BackwardEuler
+where
+    T: RealField + Copy,
+{
+    fn solve<const D: usize, F>(f: F, t0: T, dt: T, y0: SVector<T, D>) -> SVector<T, D>
+    where
+        F: Fn(T, SVector<T, D>) -> SVector<T, D>,
+    {
+        Self::solve_with_jacobian(&f, |t, y| jacobian(|y| f(t, y), y), t0, dt, y0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{Matrix1, Vector1, Vector2};
+
+    /// Harmonic oscillator `x'' = -x`
+    fn oscillator<T: RealField + Copy>(_: T, y: Vector2<T>) -> Vector2<T> {
+        Vector2::new(y[1], -y[0])
+    }
+
+    /// State after `steps` steps of `dt` from `y0` with the solver `S`
+    fn integrate<T: RealField + Copy, S: ODESolver<T>>(
+        y0: Vector2<T>,
+        dt: T,
+        steps: usize,
+    ) -> Vector2<T> {
+        let mut t = T::zero();
+        let mut y = y0;
+        for _ in 0..steps {
+            y = S::solve(oscillator, t, dt, y);
+            t += dt;
+        }
+        y
+    }
+
+    /// Compares the f32 and f64 solutions of the oscillator with the solver `S`
+    fn assert_f32_matches_f64<S: ODESolver<f32> + ODESolver<f64>>() {
+        let y32 = integrate::<f32, S>(Vector2::new(1.0, 0.0), 0.01, 100);
+        let y64 = integrate::<f64, S>(Vector2::new(1.0, 0.0), 0.01, 100);
+
+        for i in 0..2 {
+            assert!((y32[i] as f64 - y64[i]).abs() < 1e-5, "{y32} != {y64}");
+        }
+    }
+
+    #[test]
+    fn f32_matches_f64() {
+        assert_f32_matches_f64::<RungeKutta4>();
+        assert_f32_matches_f64::<ForwardEuler>();
+    }
+
+    /// Stiff system `y' = -1000 (y - cos(t))`, quickly converging to `cos(t)`
+    fn stiff(t: f64, y: Vector1<f64>) -> Vector1<f64> {
+        Vector1::new(-1000.0 * (y[0] - t.cos()))
+    }
+
+    /// Value of the stiff system after `steps` steps of 0.01 s from 0, with the solver `S`
+    fn integrate_stiff<S: ODESolver<f64>>(steps: usize) -> f64 {
+        let dt = 0.01;
+        let mut y = Vector1::new(0.0);
+        for i in 0..steps {
+            y = S::solve(stiff, i as f64 * dt, dt, y);
+        }
+        y[0]
+    }
+
+    #[test]
+    fn backward_euler_stable_on_stiff_system() {
+        // The explicit update multiplies the error by 1 - 1000 * dt = -9 on every step
+        assert!(integrate_stiff::<ForwardEuler>(100).abs() > 1e10);
+
+        let y = integrate_stiff::<BackwardEuler>(100);
+        assert!((y - 1.0f64.cos()).abs() < 1e-3, "{y}");
+    }
+
+    #[test]
+    fn backward_euler_with_analytic_jacobian() {
+        let y0 = Vector1::new(0.5);
+        let numeric = BackwardEuler::solve(stiff, 0.0, 0.01, y0);
+        let analytic =
+            BackwardEuler::solve_with_jacobian(stiff, |_, _| Matrix1::new(-1000.0), 0.0, 0.01, y0);
+
+        // Linear in y, a single Newton iteration gives the exact update
+        let expected = (0.5 + 10.0 * 0.01f64.cos()) / 11.0;
+        assert!((analytic[0] - expected).abs() < 1e-12);
+        assert!((numeric[0] - expected).abs() < 1e-9);
+    }
+}
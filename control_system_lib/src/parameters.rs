@@ -1,4 +1,4 @@
-use config::{Config, FileFormat};
+use config::{Config, ConfigError};
 use serde::{de::DeserializeOwned, Serialize};
 use std::{
     io::{self},
@@ -9,23 +9,24 @@ use toml::Table;
 
 pub struct ParameterStore {
     file: PathBuf,
-    config: Config,
     control_system_name: String,
 
+    /// Content of the file, so that the entries not read by this run are preserved
+    /// when saving
+    loaded: Table,
     write_back: Table,
 }
 
 impl ParameterStore {
     pub fn new(file: &Path, control_sys_name: &str) -> Result<Self, ParameterStoreError> {
-        let config = if file.exists() {
-            Config::builder()
-                .add_source(config::File::new(
-                    file.as_os_str().to_str().unwrap(),
-                    FileFormat::Toml,
-                ))
-                .build()?
+        let loaded = if file.exists() {
+            let content = std::fs::read_to_string(file)?;
+            toml::from_str(&content).map_err(|e| ConfigError::FileParse {
+                uri: Some(file.display().to_string()),
+                cause: Box::new(e),
+            })?
         } else {
-            Config::default()
+            Table::new()
         };
 
         let mut table_cs = Table::new();
@@ -36,8 +37,8 @@ impl ParameterStore {
 
         Ok(ParameterStore {
             file: file.to_owned(),
-            config,
             control_system_name: control_sys_name.to_string(),
+            loaded,
             write_back,
         })
     }
@@ -46,13 +47,7 @@ impl ParameterStore {
         &mut self,
         default: T,
     ) -> Result<T, ParameterStoreError> {
-        let default = Config::try_from(&default).unwrap();
-        let key = format!("{}.params", self.control_system_name);
-        let param: T = Config::builder()
-            .set_default(key.as_str(), default.cache)?
-            .add_source(self.config.clone())
-            .build()?
-            .get(key.as_str())?;
+        let param: T = self.read(&[&self.control_system_name, "params"], &default)?;
 
         let block_table = self.write_back.get_mut(&self.control_system_name).expect(
             "Internal toml table has a bad structure: does not contain control system root element",
@@ -69,13 +64,7 @@ impl ParameterStore {
         block_name: &str,
         default: T,
     ) -> Result<T, ParameterStoreError> {
-        let default = Config::try_from(&default).unwrap();
-        let key = format!("{}.blocks.{}", self.control_system_name, block_name);
-        let param: T = Config::builder()
-            .set_default(key.as_str(), default.cache)?
-            .add_source(self.config.clone())
-            .build()?
-            .get(key.as_str())?;
+        let param: T = self.read(&[&self.control_system_name, "blocks", block_name], &default)?;
 
         let block_table = self
             .write_back
@@ -92,13 +81,66 @@ impl ParameterStore {
         Ok(param)
     }
 
+    /// Writes the parameters read so far back to the file. Entries of the file that
+    /// were not read, eg. blocks not present in this run or other control systems,
+    /// are kept as they were.
     pub fn save(&self) -> Result<(), ParameterStoreError> {
-        let ser_toml: String = toml::to_string_pretty(&self.write_back)?;
+        let mut table = toml::Value::Table(self.loaded.clone());
+        merge(&mut table, &toml::Value::Table(self.write_back.clone()));
+
+        let ser_toml: String = toml::to_string_pretty(&table)?;
 
         std::fs::write(&self.file, ser_toml)?;
 
         Ok(())
     }
+
+    /// Reads the value at `path` in the file, taking the fields it does not specify
+    /// from `default`, at any depth
+    fn read<T: DeserializeOwned + Serialize>(
+        &self,
+        path: &[&str],
+        default: &T,
+    ) -> Result<T, ParameterStoreError> {
+        let mut value = toml::Value::try_from(default)?;
+
+        let stored = path[1..].iter().fold(self.loaded.get(path[0]), |v, key| {
+            v.and_then(|v| v.get(key))
+        });
+        if let Some(stored) = stored {
+            merge(&mut value, stored);
+        }
+
+        Ok(Config::try_from(&value)?.try_deserialize()?)
+    }
+}
+
+/// Recursively replaces the values in `into` with the ones in `from`. Tables are merged
+/// key by key and arrays element by element, so that the entries of `into` missing in
+/// `from` are kept.
+fn merge(into: &mut toml::Value, from: &toml::Value) {
+    match (into, from) {
+        (toml::Value::Table(into), toml::Value::Table(from)) => {
+            for (key, value) in from {
+                match into.get_mut(key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        into.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (toml::Value::Array(into), toml::Value::Array(from)) => {
+            into.truncate(from.len());
+            for (i, value) in from.iter().enumerate() {
+                match into.get_mut(i) {
+                    Some(existing) => merge(existing, value),
+                    None => into.push(value.clone()),
+                }
+            }
+        }
+        (into, from) => *into = from.clone(),
+    }
 }
 
 #[derive(Error, Debug)]
@@ -138,3 +180,140 @@ pub struct SerializationError {
     #[from]
     source: toml::ser::Error,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    struct TestConfig1 {
+        a: i32,
+        b: String,
+        c: f64,
+    }
+
+    fn test_config1() -> TestConfig1 {
+        TestConfig1 {
+            a: 23,
+            b: "Hello".to_string(),
+            c: 123.456,
+        }
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    struct TestConfig2 {
+        x: TestConfig1,
+        y: Option<bool>,
+    }
+
+    /// Parameter file in the temporary directory, unique to the test `name`, with the
+    /// given content
+    fn parameter_file(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "control_system_params_{}_{}.toml",
+            name,
+            std::process::id()
+        ));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn save_keeps_unread_sections() {
+        let path = parameter_file(
+            "save_keeps_unread_sections",
+            r#"
+[other_cs.blocks.pid]
+kp = 2.0
+
+[test_cs.blocks.unread]
+w = "kept"
+
+[test_cs.blocks.block_1]
+a = 1
+"#,
+        );
+
+        let mut store = ParameterStore::new(&path, "test_cs").unwrap();
+        let params = store.get_block_params("block_1", test_config1()).unwrap();
+        assert_eq!(params.a, 1);
+        store.save().unwrap();
+
+        let saved: Table = toml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            saved["other_cs"]["blocks"]["pid"]["kp"].as_float(),
+            Some(2.0)
+        );
+        let blocks = &saved["test_cs"]["blocks"];
+        assert_eq!(blocks["unread"]["w"].as_str(), Some("kept"));
+
+        // The block that was read is written back with its defaults filled in
+        assert_eq!(blocks["block_1"]["a"].as_integer(), Some(1));
+        assert_eq!(blocks["block_1"]["b"].as_str(), Some("Hello"));
+    }
+
+    #[test]
+    fn nested_defaults_field_by_field() {
+        let path = parameter_file(
+            "nested_defaults_field_by_field",
+            "[test_cs.blocks.block_2.x]\na = 5\n",
+        );
+
+        let default = TestConfig2 {
+            x: test_config1(),
+            y: Some(false),
+        };
+        let mut store = ParameterStore::new(&path, "test_cs").unwrap();
+        let params = store.get_block_params("block_2", default).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            params,
+            TestConfig2 {
+                x: TestConfig1 {
+                    a: 5,
+                    ..test_config1()
+                },
+                y: Some(false),
+            }
+        );
+    }
+
+    #[test]
+    fn array_defaults_element_by_element() {
+        let path = parameter_file(
+            "array_defaults_element_by_element",
+            "[[test_cs.blocks.block_3.list]]\na = 7\n\n[[test_cs.blocks.block_3.list]]\nb = \"no a\"\n",
+        );
+
+        #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+        struct TestList {
+            list: Vec<TestConfig1>,
+        }
+
+        let default = TestList {
+            list: vec![test_config1()],
+        };
+        let mut store = ParameterStore::new(&path, "test_cs").unwrap();
+        let params = store.get_block_params::<TestList>("block_3", default.clone());
+
+        // The second element has no default to take the missing field from
+        assert!(params.is_err());
+
+        std::fs::write(&path, "[[test_cs.blocks.block_3.list]]\na = 7\n").unwrap();
+        let mut store = ParameterStore::new(&path, "test_cs").unwrap();
+        let params = store.get_block_params("block_3", default).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            params.list,
+            [TestConfig1 {
+                a: 7,
+                ..test_config1()
+            }]
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{Block, ControlSystemError, Result};
+
+type BlockConstructor = Box<dyn Fn(&str, Value) -> Result<Box<dyn Block>>>;
+
+/// Constructors of blocks by type name, to create blocks described by configuration
+/// data, eg. read from a file
+#[derive(Default)]
+pub struct BlockRegistry {
+    constructors: HashMap<String, BlockConstructor>,
+}
+
+impl BlockRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a constructor for blocks of type `type_name`, receiving the name of
+    /// the block and its parameters. Replaces any constructor previously registered
+    /// for the same type.
+    pub fn register<F>(&mut self, type_name: &str, constructor: F) -> &mut Self
+    where
+        F: Fn(&str, Value) -> Result<Box<dyn Block>> + 'static,
+    {
+        self.constructors
+            .insert(type_name.to_string(), Box::new(constructor));
+        self
+    }
+
+    /// Registers a block built by `new` from its name and parameters of type `P`
+    pub fn register_new<P, B>(&mut self, type_name: &str, new: fn(&str, P) -> B) -> &mut Self
+    where
+        P: DeserializeOwned + 'static,
+        B: Block + 'static,
+    {
+        self.register(type_name, move |name, params| {
+            let params = parse_params(name, params)?;
+            Ok(Box::new(new(name, params)))
+        })
+    }
+
+    pub fn contains(&self, type_name: &str) -> bool {
+        self.constructors.contains_key(type_name)
+    }
+
+    /// Names of the registered block types, sorted alphabetically
+    pub fn type_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.constructors.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Creates a block of type `type_name` named `name`
+    pub fn create(&self, type_name: &str, name: &str, params: Value) -> Result<Box<dyn Block>> {
+        let constructor = self
+            .constructors
+            .get(type_name)
+            .ok_or(ControlSystemError::UnknownBlockType(type_name.to_string()))?;
+
+        constructor(name, params)
+    }
+}
+
+/// Deserializes the parameters of block `name`, for use in constructors passed to
+/// [`BlockRegistry::register`]
+pub fn parse_params<P: DeserializeOwned>(name: &str, params: Value) -> Result<P> {
+    serde_json::from_value(params).map_err(|source| ControlSystemError::InvalidBlockParams {
+        blockname: name.to_string(),
+        source,
+    })
+}
+
+/// Error for parameters of block `name` that are well formed but have values the block
+/// can't use
+pub fn invalid_params(name: &str, reason: &str) -> ControlSystemError {
+    ControlSystemError::InvalidBlockParams {
+        blockname: name.to_string(),
+        source: serde::de::Error::custom(reason),
+    }
+}
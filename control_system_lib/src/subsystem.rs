@@ -0,0 +1,451 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+};
+
+use crate::{
+    downcast_state,
+    io::{AnySignal, SignalType},
+    Block, BlockIO, BlockState, ControlSystem, ControlSystemError, Result, StateSnapshot, StepInfo,
+    StepResult,
+};
+
+struct SubSystemInput {
+    outer: Option<AnySignal>,
+    inner: AnySignal,
+    copy: fn(&AnySignal, &AnySignal) -> Result<()>,
+}
+
+/// A block wrapping a whole control system, which is stepped once for every step
+/// of the parent system, with the same time and step size.
+///
+/// Input ports copy the value of the parent signal into an external signal of the
+/// inner system (see [`crate::ControlSystemBuilder::add_external_signal`]), while
+/// output ports directly expose signals of the inner system.
+pub struct SubSystem {
+    name: String,
+    system: ControlSystem,
+
+    inputs: HashMap<String, SubSystemInput>,
+    outputs: HashMap<String, AnySignal>,
+}
+
+impl SubSystem {
+    pub fn new(name: &str, system: ControlSystem) -> Self {
+        SubSystem {
+            name: name.to_string(),
+            system,
+            inputs: HashMap::new(),
+            outputs: HashMap::new(),
+        }
+    }
+
+    /// Adds an input port named `port`, forwarding its value to the inner signal `signal`
+    pub fn with_input<T: Clone + 'static>(mut self, port: &str, signal: &str) -> Result<Self> {
+        let inner = self.inner_signal::<T>(port, signal)?;
+
+        self.inputs.insert(
+            port.to_string(),
+            SubSystemInput {
+                outer: None,
+                inner,
+                copy: copy_signal::<T>,
+            },
+        );
+
+        Ok(self)
+    }
+
+    /// Adds an output port named `port`, exposing the inner signal `signal`
+    pub fn with_output<T: 'static>(mut self, port: &str, signal: &str) -> Result<Self> {
+        let inner = self.inner_signal::<T>(port, signal)?;
+        self.outputs.insert(port.to_string(), inner);
+
+        Ok(self)
+    }
+
+    pub fn system(&self) -> &ControlSystem {
+        &self.system
+    }
+
+    fn inner_signal<T: 'static>(&self, port: &str, signal: &str) -> Result<AnySignal> {
+        let inner = self
+            .system
+            .get_signal(signal)
+            .ok_or(ControlSystemError::UnknownSignal {
+                port: port.to_string(),
+                signal: signal.to_string(),
+                blockname: self.name.clone(),
+            })?;
+
+        if inner.signal_type_id() != TypeId::of::<T>() {
+            return Err(ControlSystemError::TypeError {
+                signal: signal.to_string(),
+                typename: std::any::type_name::<T>().to_string(),
+                signal_typename: inner.signal_type_name().to_string(),
+                producer: inner.producer().map(str::to_string),
+            });
+        }
+
+        Ok(inner.clone())
+    }
+}
+
+fn copy_signal<T: Clone + 'static>(from: &AnySignal, to: &AnySignal) -> Result<()> {
+    if let Some(value) = from.try_get::<T>()? {
+        to.try_set(value)?;
+    }
+    Ok(())
+}
+
+impl BlockIO for SubSystem {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn input_signals(&mut self) -> HashMap<String, &mut Option<AnySignal>> {
+        self.inputs
+            .iter_mut()
+            .map(|(port, input)| (port.clone(), &mut input.outer))
+            .collect()
+    }
+
+    fn input_port_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.inputs.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    fn output_port_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.outputs.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    fn input_types(&self) -> HashMap<String, SignalType> {
+        self.inputs
+            .iter()
+            .map(|(port, input)| (port.clone(), input.inner.signal_type()))
+            .collect()
+    }
+
+    fn output_signals(&mut self) -> HashMap<String, &mut AnySignal> {
+        self.outputs
+            .iter_mut()
+            .map(|(port, signal)| (port.clone(), signal))
+            .collect()
+    }
+}
+
+impl Block for SubSystem {
+    fn step(&mut self, k: StepInfo) -> Result<StepResult> {
+        for input in self.inputs.values() {
+            (input.copy)(input.outer.as_ref().unwrap(), &input.inner)?;
+        }
+
+        self.system.step_nested(k)
+    }
+
+    fn reset(&mut self) {
+        self.system.reset();
+    }
+
+    fn save_state(&self) -> Option<BlockState> {
+        Some(Box::new(self.system.snapshot()))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) -> Result<()> {
+        self.system
+            .restore(downcast_state::<StateSnapshot>(&self.name, state)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        io::{Input, Output},
+        ControlSystemBuilder, ControlSystemParameters,
+    };
+
+    /// Outputs a constant value
+    #[derive(BlockIO)]
+    struct Constant {
+        #[blockio(block_name)]
+        name: String,
+
+        #[blockio(output)]
+        y: Output<f64>,
+
+        value: f64,
+    }
+
+    impl Block for Constant {
+        fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+            self.y.set(self.value);
+            Ok(StepResult::Continue)
+        }
+    }
+
+    /// Integrates its input over time
+    #[derive(BlockIO)]
+    struct Integrator {
+        #[blockio(block_name)]
+        name: String,
+
+        #[blockio(input)]
+        u: Input<f64>,
+
+        #[blockio(output)]
+        y: Output<f64>,
+
+        acc: f64,
+    }
+
+    impl Block for Integrator {
+        fn step(&mut self, k: StepInfo) -> Result<StepResult> {
+            self.acc += self.u.get() * k.dt;
+            self.y.set(self.acc);
+            Ok(StepResult::Continue)
+        }
+
+        fn save_state(&self) -> Option<BlockState> {
+            Some(Box::new(self.acc))
+        }
+
+        fn load_state(&mut self, state: &dyn Any) -> Result<()> {
+            self.acc = *downcast_state::<f64>(&self.name, state)?;
+            Ok(())
+        }
+    }
+
+    fn params(dt: f64) -> ControlSystemParameters {
+        ControlSystemParameters::new(dt)
+    }
+
+    /// Parent system with a constant input of 1, integrated inside a subsystem
+    fn nested_system() -> ControlSystem {
+        let mut inner = ControlSystemBuilder::default();
+        inner.add_external_signal::<f64>("u").unwrap();
+        inner
+            .add_block(
+                Integrator {
+                    name: "integrator".to_string(),
+                    u: Input::default(),
+                    y: Output::default(),
+                    acc: 0.0,
+                },
+                &[("u", "u")],
+                &[("y", "y")],
+            )
+            .unwrap();
+        // The step size of the inner system is not used
+        let inner = inner.build("inner", params(1.0)).unwrap();
+
+        let sub = SubSystem::new("sub", inner)
+            .with_input::<f64>("u", "u")
+            .unwrap()
+            .with_output::<f64>("y", "y")
+            .unwrap();
+
+        let mut outer = ControlSystemBuilder::default();
+        outer
+            .add_block(
+                Constant {
+                    name: "one".to_string(),
+                    y: Output::default(),
+                    value: 1.0,
+                },
+                &[],
+                &[("y", "u")],
+            )
+            .unwrap();
+        outer.add_block(sub, &[("u", "u")], &[("y", "y")]).unwrap();
+        outer.build("outer", params(0.1)).unwrap()
+    }
+
+    /// Velocity of a cart of unit mass, pushed by the force of the previous step
+    #[derive(BlockIO)]
+    struct Cart {
+        #[blockio(block_name)]
+        name: String,
+
+        #[blockio(input)]
+        u_force: Input<f64>,
+
+        #[blockio(output)]
+        y_vel: Output<f64>,
+
+        vel: f64,
+    }
+
+    impl Block for Cart {
+        fn step(&mut self, k: StepInfo) -> Result<StepResult> {
+            self.vel += self.u_force.get() * k.dt;
+            self.y_vel.set(self.vel);
+            Ok(StepResult::Continue)
+        }
+
+        fn delay(&self) -> u32 {
+            1
+        }
+    }
+
+    /// Proportional controller of the velocity of the cart
+    #[derive(BlockIO)]
+    struct VelocityController {
+        #[blockio(block_name)]
+        name: String,
+
+        #[blockio(input)]
+        u_ref: Input<f64>,
+
+        #[blockio(input)]
+        u_vel: Input<f64>,
+
+        #[blockio(output)]
+        y_force: Output<f64>,
+    }
+
+    impl Block for VelocityController {
+        fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+            self.y_force
+                .set(4.0 * (self.u_ref.get() - self.u_vel.get()));
+            Ok(StepResult::Continue)
+        }
+    }
+
+    fn add_velocity_loop(builder: &mut ControlSystemBuilder) {
+        builder
+            .add_block(
+                VelocityController {
+                    name: "pid_vel".to_string(),
+                    u_ref: Input::default(),
+                    u_vel: Input::default(),
+                    y_force: Output::default(),
+                },
+                &[("u_ref", "/ref/vel"), ("u_vel", "/cart/vel")],
+                &[("y_force", "/force")],
+            )
+            .unwrap();
+    }
+
+    /// Cart driven to a velocity of 2, either by `controller` or by a velocity loop
+    /// added directly to the system if None
+    fn cart_system(controller: Option<SubSystem>) -> ControlSystem {
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_external_signal::<f64>("/ref/vel")
+            .unwrap()
+            .set_initial("/ref/vel", 2.0)
+            .unwrap();
+        builder
+            .add_block(
+                Cart {
+                    name: "cart".to_string(),
+                    u_force: Input::default(),
+                    y_vel: Output::default(),
+                    vel: 0.0,
+                },
+                &[("u_force", "/force")],
+                &[("y_vel", "/cart/vel")],
+            )
+            .unwrap();
+
+        match controller {
+            Some(sub) => {
+                builder
+                    .add_block(
+                        sub,
+                        &[("ref", "/ref/vel"), ("vel", "/cart/vel")],
+                        &[("force", "/force")],
+                    )
+                    .unwrap();
+            }
+            None => add_velocity_loop(&mut builder),
+        }
+
+        builder.set_initial("/force", 0.0).unwrap();
+        builder.build("cart", params(0.1)).unwrap()
+    }
+
+    fn output(system: &ControlSystem) -> f64 {
+        system
+            .get_signal("y")
+            .unwrap()
+            .try_get::<f64>()
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn inner_system_steps_with_the_parent() {
+        let mut system = nested_system();
+        for _ in 0..3 {
+            system.step().unwrap();
+        }
+        assert!((output(&system) - 0.3).abs() < 1e-12);
+    }
+
+    #[test]
+    fn inner_system_uses_step_at_times() {
+        let mut system = nested_system();
+
+        system.step_at(0.5).unwrap();
+        system.step_at(0.75).unwrap();
+        assert!((output(&system) - 0.35).abs() < 1e-12);
+    }
+
+    #[test]
+    fn snapshot_restores_inner_state() {
+        let mut system = nested_system();
+
+        system.step().unwrap();
+        let snap = system.snapshot();
+        system.step().unwrap();
+        system.step().unwrap();
+
+        system.restore(&snap).unwrap();
+        system.step().unwrap();
+        assert!((output(&system) - 0.2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn ports_check_the_inner_signals() {
+        let mut inner = ControlSystemBuilder::default();
+        inner.add_external_signal::<f64>("u").unwrap();
+        let inner = inner.build("inner", params(0.1)).unwrap();
+
+        assert!(matches!(
+            SubSystem::new("sub", inner).with_input::<u32>("u", "u"),
+            Err(ControlSystemError::TypeError { .. })
+        ));
+    }
+
+    #[test]
+    fn cart_velocity_loop_as_subsystem() {
+        let mut inner = ControlSystemBuilder::default();
+        inner
+            .add_external_signal::<f64>("/ref/vel")
+            .unwrap()
+            .add_external_signal::<f64>("/cart/vel")
+            .unwrap();
+        add_velocity_loop(&mut inner);
+        let inner = inner.build("velocity_loop", params(0.1)).unwrap();
+
+        let sub = SubSystem::new("velocity_loop", inner)
+            .with_input::<f64>("ref", "/ref/vel")
+            .unwrap()
+            .with_input::<f64>("vel", "/cart/vel")
+            .unwrap()
+            .with_output::<f64>("force", "/force")
+            .unwrap();
+
+        let signals = ["/cart/vel", "/force"];
+        let nested = cart_system(Some(sub)).simulate(50, &signals).unwrap();
+        let flat = cart_system(None).simulate(50, &signals).unwrap();
+
+        assert_eq!(nested, flat);
+        assert!((nested["/cart/vel"][49] - 2.0).abs() < 1e-6);
+    }
+}
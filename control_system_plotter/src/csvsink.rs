@@ -0,0 +1,141 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+};
+
+use control_system::{
+    invalid_params, io::Input, Block, BlockIO, ControlSystemError, Result, StepInfo, StepResult,
+};
+
+use crate::AsF64Signals;
+
+#[derive(Debug, Clone)]
+pub struct CsvSinkParams {
+    pub path: PathBuf,
+    /// The rows are flushed to the file every `flush_interval` steps
+    pub flush_interval: usize,
+}
+
+/// Writes the input to a CSV file, one `time,value` row per step. Values with more
+/// than one component have a column for each of them.
+#[derive(BlockIO)]
+pub struct CsvSink<T> {
+    #[blockio(block_name)]
+    name: String,
+
+    #[blockio(input)]
+    u: Input<T>,
+
+    writer: BufWriter<File>,
+    params: CsvSinkParams,
+}
+
+impl<T: AsF64Signals> CsvSink<T> {
+    /// Creates the file and writes the header, failing if the file can't be created or
+    /// if the flush interval is 0
+    pub fn new(name: &str, params: CsvSinkParams) -> Result<Self> {
+        if params.flush_interval == 0 {
+            return Err(invalid_params(
+                name,
+                "CsvSink flush interval must be at least 1",
+            ));
+        }
+
+        let mut writer =
+            BufWriter::new(File::create(&params.path).map_err(ControlSystemError::from_boxed)?);
+
+        let header: Vec<String> = T::names().iter().map(|n| format!("value{n}")).collect();
+        writeln!(writer, "time,{}", header.join(",")).map_err(ControlSystemError::from_boxed)?;
+
+        Ok(CsvSink {
+            name: name.to_string(),
+            u: Input::default(),
+            writer,
+            params,
+        })
+    }
+}
+
+impl<T: Clone + AsF64Signals + 'static> Block for CsvSink<T> {
+    fn step(&mut self, k: StepInfo) -> Result<StepResult> {
+        let values: Vec<String> = self.u.get().values().iter().map(f64::to_string).collect();
+        writeln!(self.writer, "{},{}", k.t, values.join(","))
+            .map_err(ControlSystemError::from_boxed)?;
+
+        if k.k.is_multiple_of(self.params.flush_interval) {
+            self.writer
+                .flush()
+                .map_err(ControlSystemError::from_boxed)?;
+        }
+
+        Ok(StepResult::Continue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+    use control_system::{ControlSystemBuilder, ControlSystemParameters};
+    use nalgebra::Vector2;
+
+    #[test]
+    fn rows_written_to_file() {
+        let path = env::temp_dir().join(format!(
+            "control_system_plotter_csvsink_{}.csv",
+            std::process::id()
+        ));
+        let params = CsvSinkParams {
+            path: path.clone(),
+            flush_interval: 2,
+        };
+
+        let mut builder = ControlSystemBuilder::default();
+        builder
+            .add_external_signal::<Vector2<f64>>("pos")
+            .unwrap()
+            .set_initial("pos", Vector2::new(1.0, -2.5))
+            .unwrap();
+        builder
+            .add_block(
+                CsvSink::<Vector2<f64>>::new("sink", params).unwrap(),
+                &[("u", "pos")],
+                &[],
+            )
+            .unwrap();
+        let mut system = builder
+            .build("csv", ControlSystemParameters::new(0.5))
+            .unwrap();
+        for _ in 0..3 {
+            system.step().unwrap();
+        }
+
+        // The last row is only flushed when the sink is dropped
+        let flushed = fs::read_to_string(&path).unwrap();
+        drop(system);
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(flushed, "time,value/x,value/y\n0,1,-2.5\n0.5,1,-2.5\n");
+        assert_eq!(contents, format!("{flushed}1,1,-2.5\n"));
+    }
+    #[test]
+    fn rejects_zero_flush_interval() {
+        let path = env::temp_dir().join(format!(
+            "control_system_plotter_csvsink_zero_{}.csv",
+            std::process::id()
+        ));
+        let params = CsvSinkParams {
+            path: path.clone(),
+            flush_interval: 0,
+        };
+
+        assert!(matches!(
+            CsvSink::<f64>::new("sink", params),
+            Err(ControlSystemError::InvalidBlockParams { .. })
+        ));
+        assert!(!path.exists());
+    }
+}
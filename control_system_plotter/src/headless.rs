@@ -0,0 +1,97 @@
+//! File backend for the plotter, for environments without a display.
+//!
+//! Mirrors the API of `rust_data_inspector_signals`, so that [`crate::Plotter`] and
+//! [`crate::add_plotter`] work unchanged. Samples of all the signals are written to a
+//! single CSV file, one `time,signal,value` row per sample.
+
+use std::{
+    cell::RefCell,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    rc::Rc,
+};
+
+pub type SignalID = usize;
+
+#[derive(Debug, Clone, Copy)]
+pub struct PlotSignalSample {
+    pub time: f64,
+    pub value: f64,
+}
+
+struct CsvSink {
+    writer: Box<dyn Write>,
+    /// First error encountered while writing, reported by [`PlotSignals::flush`]
+    error: Option<io::Error>,
+}
+
+impl CsvSink {
+    fn write_row(&mut self, time: f64, signal: &str, value: f64) {
+        if self.error.is_none() {
+            if let Err(e) = writeln!(self.writer, "{},{},{}", time, signal, value) {
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+pub struct PlotSignals {
+    sink: Rc<RefCell<CsvSink>>,
+    num_signals: usize,
+}
+
+impl PlotSignals {
+    /// Creates the signals, writing the samples to the file at `path`
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_writer(BufWriter::new(File::create(path)?))
+    }
+
+    /// Creates the signals, writing the samples to `writer`
+    pub fn from_writer<W: Write + 'static>(mut writer: W) -> io::Result<Self> {
+        writeln!(writer, "time,signal,value")?;
+
+        Ok(PlotSignals {
+            sink: Rc::new(RefCell::new(CsvSink {
+                writer: Box::new(writer),
+                error: None,
+            })),
+            num_signals: 0,
+        })
+    }
+
+    pub fn add_signal(&mut self, name: &str) -> io::Result<(SignalID, PlotSampleSender)> {
+        let id = self.num_signals;
+        self.num_signals += 1;
+
+        Ok((
+            id,
+            PlotSampleSender {
+                name: name.to_string(),
+                sink: self.sink.clone(),
+            },
+        ))
+    }
+
+    /// Flushes the samples written so far, returning the first write error, if any
+    pub fn flush(&mut self) -> io::Result<()> {
+        let mut sink = self.sink.borrow_mut();
+        if let Some(e) = sink.error.take() {
+            return Err(e);
+        }
+        sink.writer.flush()
+    }
+}
+
+pub struct PlotSampleSender {
+    name: String,
+    sink: Rc<RefCell<CsvSink>>,
+}
+
+impl PlotSampleSender {
+    pub fn send(&mut self, sample: PlotSignalSample) {
+        self.sink
+            .borrow_mut()
+            .write_row(sample.time, &self.name, sample.value);
+    }
+}
@@ -1,21 +1,128 @@
 extern crate control_system_lib as control_system;
+
+mod csvsink;
+#[cfg(feature = "headless")]
+pub mod headless;
 mod plotter;
-pub use plotter::{add_plotter, Plotter};
+pub use csvsink::{CsvSink, CsvSinkParams};
+pub use plotter::{add_plotter, add_plotter_with_params, Plotter, PlotterParams};
+
+#[cfg(not(any(feature = "gui", feature = "headless")))]
+compile_error!(
+    "Either the 'gui' or the 'headless' feature of control_system_plotter must be enabled"
+);
 
-use num_traits::Num;
+use nalgebra::{Vector2, Vector3};
+use num_complex::Complex;
 
+/// Values that can be plotted, as one or more `f64` components
 pub trait AsF64Signals {
+    /// Suffix appended to the topic of each component, eg. `/x`.
+    /// Scalars have a single component with an empty suffix.
     fn names() -> Vec<String>;
 
     fn values(&self) -> Vec<f64>;
 }
 
-impl<T: Num + Into<f64> + Copy> AsF64Signals for T {
+macro_rules! impl_scalar_signals {
+    ($($t:ty),*) => {
+        $(
+            impl AsF64Signals for $t {
+                fn names() -> Vec<String> {
+                    vec!["".to_string()]
+                }
+
+                fn values(&self) -> Vec<f64> {
+                    vec![(*self).into()]
+                }
+            }
+        )*
+    };
+}
+
+impl_scalar_signals!(f64, f32, i32, i16, i8, u32, u16, u8);
+
+/// Implements [`AsF64Signals`] for a struct carried as a single signal, eg. the state of a
+/// plant, by listing its fields and their types. Each field must implement
+/// [`AsF64Signals`] and is plotted with its name as suffix:
+///
+/// ```ignore
+/// #[derive(Clone, Default)]
+/// struct CartState {
+///     pos: f64,
+///     vel: f64,
+/// }
+///
+/// impl_struct_signals!(CartState { pos: f64, vel: f64 }); // "/pos" and "/vel"
+/// ```
+#[macro_export]
+macro_rules! impl_struct_signals {
+    ($t:ty { $($field:ident: $field_type:ty),* $(,)? }) => {
+        impl $crate::AsF64Signals for $t {
+            fn names() -> Vec<String> {
+                let mut names = vec![];
+                $(
+                    names.extend(
+                        <$field_type as $crate::AsF64Signals>::names()
+                            .into_iter()
+                            .map(|name| format!("/{}{}", stringify!($field), name)),
+                    );
+                )*
+                names
+            }
+
+            fn values(&self) -> Vec<f64> {
+                let mut values = vec![];
+                $(
+                    values.extend($crate::AsF64Signals::values(&self.$field));
+                )*
+                values
+            }
+        }
+    };
+}
+
+impl AsF64Signals for Vector2<f64> {
+    fn names() -> Vec<String> {
+        vec!["/x".to_string(), "/y".to_string()]
+    }
+
+    fn values(&self) -> Vec<f64> {
+        self.iter().copied().collect()
+    }
+}
+
+impl AsF64Signals for Vector3<f64> {
+    fn names() -> Vec<String> {
+        vec!["/x".to_string(), "/y".to_string(), "/z".to_string()]
+    }
+
+    fn values(&self) -> Vec<f64> {
+        self.iter().copied().collect()
+    }
+}
+
+/// Complex numbers are plotted as their real and imaginary parts
+impl AsF64Signals for Complex<f64> {
     fn names() -> Vec<String> {
-        vec!["".to_string()]
+        vec!["/re".to_string(), "/im".to_string()]
     }
 
     fn values(&self) -> Vec<f64> {
-        vec![(*self).into()]
+        vec![self.re, self.im]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vector_component_names() {
+        assert_eq!(Vector2::<f64>::names(), ["/x", "/y"]);
+        assert_eq!(Vector3::<f64>::names(), ["/x", "/y", "/z"]);
+
+        assert_eq!(Vector2::new(1.0, 2.0).values(), [1.0, 2.0]);
+        assert_eq!(Vector3::new(1.0, 2.0, 3.0).values(), [1.0, 2.0, 3.0]);
     }
 }
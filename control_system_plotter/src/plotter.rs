@@ -1,12 +1,34 @@
-use std::error::Error;
-
-use control_system::{io::Input, Block, ControlSystemError, StepResult};
+use control_system::{invalid_params, io::Input, Block, ControlSystemError, StepResult};
 use control_system::{BlockIO, ControlSystemBuilder, StepInfo};
+
+// The file backend takes precedence, so that it can be selected even if some other
+// crate enables the GUI
+#[cfg(feature = "headless")]
+use crate::headless::{PlotSampleSender, PlotSignalSample, PlotSignals};
+#[cfg(not(feature = "headless"))]
 use rust_data_inspector_signals::{PlotSampleSender, PlotSignalSample, PlotSignals};
 
 use crate::AsF64Signals;
 use control_system_lib::Result;
 
+#[derive(Debug, Clone, Copy)]
+pub struct PlotterParams {
+    /// Only one sample every `decimation` steps is plotted
+    pub decimation: usize,
+}
+
+impl Default for PlotterParams {
+    fn default() -> Self {
+        PlotterParams { decimation: 1 }
+    }
+}
+
+impl From<usize> for PlotterParams {
+    fn from(value: usize) -> Self {
+        PlotterParams { decimation: value }
+    }
+}
+
 #[derive(BlockIO)]
 pub struct Plotter<T> {
     #[blockio(block_name)]
@@ -16,10 +38,28 @@ pub struct Plotter<T> {
     u: Input<T>,
 
     senders: Vec<PlotSampleSender>,
+    params: PlotterParams,
 }
 
 impl<T: AsF64Signals + Default> Plotter<T> {
     pub fn new(name: &str, topic: &str, signals: &mut PlotSignals) -> Result<Self> {
+        Self::with_params(name, topic, signals, PlotterParams::default())
+    }
+
+    /// Fails if the decimation is 0
+    pub fn with_params(
+        name: &str,
+        topic: &str,
+        signals: &mut PlotSignals,
+        params: PlotterParams,
+    ) -> Result<Self> {
+        if params.decimation == 0 {
+            return Err(invalid_params(
+                name,
+                "Plotter decimation must be at least 1",
+            ));
+        }
+
         let names = T::names();
 
         let senders = names
@@ -36,13 +76,18 @@ impl<T: AsF64Signals + Default> Plotter<T> {
             name: name.to_string(),
             u: Input::default(),
             senders,
+            params,
         })
     }
 }
 
 impl<T: Clone + AsF64Signals + 'static> Block for Plotter<T> {
     fn step(&mut self, k: StepInfo) -> Result<StepResult, ControlSystemError> {
-        // self.u.get().for(k.t, &mut self.senders);
+        // Always plot the first step
+        if !(k.k - 1).is_multiple_of(self.params.decimation) {
+            return Ok(StepResult::Continue);
+        }
+
         let sig = self.u.get();
         for (i, v) in sig.values().iter().enumerate() {
             self.senders[i].send(PlotSignalSample {
@@ -55,27 +100,310 @@ impl<T: Clone + AsF64Signals + 'static> Block for Plotter<T> {
     }
 }
 
+/// Adds a [`Plotter`] for the signal `signal_name` to the builder, returning the name
+/// of the new block
 pub fn add_plotter<T>(
     signal_name: &str,
     builder: &mut ControlSystemBuilder,
     signals: &mut PlotSignals,
-) -> control_system_lib::Result<()>
+) -> control_system_lib::Result<String>
 where
     T: AsF64Signals + Default + Clone + 'static,
 {
-    use rand::distributions::Alphanumeric;
-    use rand::{thread_rng, Rng};
-
-    let rand_string: String = thread_rng()
-        .sample_iter(&Alphanumeric)
-        .take(6)
-        .map(char::from)
-        .collect();
+    add_plotter_with_params::<T>(signal_name, builder, signals, PlotterParams::default())
+}
 
-    let name = format!("plotter{}_{}", signal_name.replace('/', "_"), rand_string);
-    let plotter = Plotter::<T>::new(name.as_str(), signal_name, signals)?;
+/// Same as [`add_plotter`], configuring the plotter with `params`
+pub fn add_plotter_with_params<T>(
+    signal_name: &str,
+    builder: &mut ControlSystemBuilder,
+    signals: &mut PlotSignals,
+    params: PlotterParams,
+) -> control_system_lib::Result<String>
+where
+    T: AsF64Signals + Default + Clone + 'static,
+{
+    let name = plotter_name(signal_name, builder);
+    let plotter = Plotter::<T>::with_params(name.as_str(), signal_name, signals, params)?;
 
     builder.add_block(plotter, &[("u", signal_name)], &[])?;
 
-    Ok(())
+    Ok(name)
+}
+
+/// Name of a new plotter block for `signal_name`, derived from the signal path
+fn plotter_name(signal_name: &str, builder: &ControlSystemBuilder) -> String {
+    let base_name = format!("plotter{}", signal_name.replace('/', "_"));
+
+    // Signals may be plotted more than once: number the additional plotters
+    let mut name = base_name.clone();
+    let mut count = 1;
+    while builder.has_block(&name) {
+        name = format!("{}_{}", base_name, count);
+        count += 1;
+    }
+
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Does nothing, standing in for a plotter already added to the builder
+    #[derive(BlockIO)]
+    struct Placeholder {
+        #[blockio(block_name)]
+        name: String,
+    }
+
+    impl Block for Placeholder {
+        fn step(&mut self, _: StepInfo) -> Result<StepResult> {
+            Ok(StepResult::Continue)
+        }
+    }
+
+    #[test]
+    fn plotter_names_are_stable_and_distinct() {
+        let mut builder = ControlSystemBuilder::default();
+        for expected in [
+            "plotter_cart_pos",
+            "plotter_cart_pos_1",
+            "plotter_cart_pos_2",
+        ] {
+            let name = plotter_name("/cart/pos", &builder);
+            assert_eq!(name, expected);
+
+            builder.add_block(Placeholder { name }, &[], &[]).unwrap();
+        }
+    }
+
+    #[cfg(feature = "headless")]
+    mod headless {
+        use std::{env, fs, path::PathBuf};
+
+        use super::*;
+        use control_system::{io::Output, ControlSystemParameters};
+        use nalgebra::Vector2;
+        use num_complex::Complex;
+
+        /// Outputs `f(t)`
+        #[derive(BlockIO)]
+        struct Source<T, F> {
+            #[blockio(block_name)]
+            name: String,
+
+            #[blockio(output)]
+            y: Output<T>,
+
+            f: F,
+        }
+
+        impl<T: Clone + 'static, F: Fn(f64) -> T> Block for Source<T, F> {
+            fn step(&mut self, k: StepInfo) -> Result<StepResult> {
+                self.y.set((self.f)(k.t));
+                Ok(StepResult::Continue)
+            }
+        }
+
+        /// Adds a [`Source`] producing `signal`
+        fn add_source<T: Clone + 'static>(
+            builder: &mut ControlSystemBuilder,
+            signal: &str,
+            f: impl Fn(f64) -> T + 'static,
+        ) {
+            let source = Source {
+                name: format!("source{}", signal.replace('/', "_")),
+                y: Output::default(),
+                f,
+            };
+            builder.add_block(source, &[], &[("y", signal)]).unwrap();
+        }
+
+        /// File in the temporary directory, unique to the test `name`
+        fn temp_file(name: &str) -> PathBuf {
+            env::temp_dir().join(format!(
+                "control_system_plotter_{}_{}.csv",
+                name,
+                std::process::id()
+            ))
+        }
+
+        /// Rows `(time, signal, value)` plotted while running for `steps` steps of
+        /// 0.5 s the system built by `build`
+        fn plot(
+            name: &str,
+            steps: usize,
+            build: impl FnOnce(&mut ControlSystemBuilder, &mut PlotSignals),
+        ) -> Vec<(f64, String, f64)> {
+            let path = temp_file(name);
+            let mut signals = PlotSignals::create(&path).unwrap();
+
+            let mut builder = ControlSystemBuilder::default();
+            build(&mut builder, &mut signals);
+            let params = ControlSystemParameters::new(0.5);
+            let mut system = builder.build(name, params).unwrap();
+            for _ in 0..steps {
+                system.step().unwrap();
+            }
+            signals.flush().unwrap();
+
+            let contents = fs::read_to_string(&path).unwrap();
+            fs::remove_file(&path).unwrap();
+
+            let mut lines = contents.lines();
+            assert_eq!(lines.next(), Some("time,signal,value"));
+            lines
+                .map(|line| {
+                    let fields: Vec<&str> = line.split(',').collect();
+                    assert_eq!(fields.len(), 3, "{line}");
+                    (
+                        fields[0].parse().unwrap(),
+                        fields[1].to_string(),
+                        fields[2].parse().unwrap(),
+                    )
+                })
+                .collect()
+        }
+
+        fn row(time: f64, signal: &str, value: f64) -> (f64, String, f64) {
+            (time, signal.to_string(), value)
+        }
+
+        #[test]
+        fn headless_file_rows() {
+            let mut rows = plot("headless_file_rows", 3, |builder, signals| {
+                add_source(builder, "/a", |t| t);
+                add_source(builder, "/b", |t| -2.0 * t);
+                add_plotter::<f64>("/a", builder, signals).unwrap();
+                add_plotter::<f64>("/b", builder, signals).unwrap();
+            });
+
+            // Signals plotted in the same step may be written in any order
+            rows.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+            assert_eq!(
+                rows,
+                [
+                    row(0.0, "/a", 0.0),
+                    row(0.0, "/b", 0.0),
+                    row(0.5, "/a", 0.5),
+                    row(0.5, "/b", -1.0),
+                    row(1.0, "/a", 1.0),
+                    row(1.0, "/b", -2.0),
+                ]
+            );
+        }
+
+        #[test]
+        fn vector_component_topics() {
+            let rows = plot("vector_component_topics", 2, |builder, signals| {
+                add_source(builder, "/pos", |t| Vector2::new(t, 2.0 * t));
+                add_plotter::<Vector2<f64>>("/pos", builder, signals).unwrap();
+            });
+
+            assert_eq!(
+                rows,
+                [
+                    row(0.0, "/pos/x", 0.0),
+                    row(0.0, "/pos/y", 0.0),
+                    row(0.5, "/pos/x", 0.5),
+                    row(0.5, "/pos/y", 1.0),
+                ]
+            );
+        }
+
+        #[test]
+        fn add_plotter_returns_block_name() {
+            let mut signals = PlotSignals::from_writer(std::io::sink()).unwrap();
+            let mut builder = ControlSystemBuilder::default();
+            add_source(&mut builder, "/pos", |t| t);
+
+            let name = add_plotter::<f64>("/pos", &mut builder, &mut signals).unwrap();
+            assert!(builder.block_names().contains(&name));
+        }
+
+        #[test]
+        fn decimation_drops_samples() {
+            let rows = plot("decimation_drops_samples", 100, |builder, signals| {
+                add_source(builder, "/a", |t| t);
+                add_plotter_with_params::<f64>("/a", builder, signals, 10.into()).unwrap();
+            });
+
+            assert_eq!(rows.len(), 10);
+            for (i, (time, _, _)) in rows.iter().enumerate() {
+                assert_eq!(*time, i as f64 * 5.0);
+            }
+        }
+
+        #[test]
+        fn rejects_zero_decimation() {
+            let mut signals = PlotSignals::from_writer(std::io::sink()).unwrap();
+            let mut builder = ControlSystemBuilder::default();
+            add_source(&mut builder, "/a", |t| t);
+
+            assert!(matches!(
+                add_plotter_with_params::<f64>("/a", &mut builder, &mut signals, 0.into()),
+                Err(ControlSystemError::InvalidBlockParams { .. })
+            ));
+        }
+
+        #[test]
+        fn rotating_phasor_topics() {
+            let rows = plot("rotating_phasor_topics", 2, |builder, signals| {
+                add_source(builder, "/phasor", |t| {
+                    Complex::from_polar(1.0, std::f64::consts::PI * t)
+                });
+                add_plotter::<Complex<f64>>("/phasor", builder, signals).unwrap();
+            });
+
+            let topics: Vec<&str> = rows.iter().map(|(_, topic, _)| topic.as_str()).collect();
+            assert_eq!(
+                topics,
+                ["/phasor/re", "/phasor/im", "/phasor/re", "/phasor/im"]
+            );
+
+            // A quarter turn after 0.5 s
+            assert!(rows[2].2.abs() < 1e-12);
+            assert!((rows[3].2 - 1.0).abs() < 1e-12);
+        }
+
+        #[derive(Clone, Default)]
+        struct CartState {
+            pos: f64,
+            vel: f64,
+            force: Vector2<f64>,
+        }
+
+        crate::impl_struct_signals!(CartState {
+            pos: f64,
+            vel: f64,
+            force: Vector2<f64>,
+        });
+
+        #[test]
+        fn struct_signal_fields() {
+            let rows = plot("struct_signal_fields", 2, |builder, signals| {
+                add_source(builder, "/cart", |t| CartState {
+                    pos: t * t,
+                    vel: 2.0 * t,
+                    force: Vector2::new(1.0, -t),
+                });
+                add_plotter::<CartState>("/cart", builder, signals).unwrap();
+            });
+
+            assert_eq!(
+                rows,
+                [
+                    row(0.0, "/cart/pos", 0.0),
+                    row(0.0, "/cart/vel", 0.0),
+                    row(0.0, "/cart/force/x", 1.0),
+                    row(0.0, "/cart/force/y", 0.0),
+                    row(0.5, "/cart/pos", 0.25),
+                    row(0.5, "/cart/vel", 1.0),
+                    row(0.5, "/cart/force/x", 1.0),
+                    row(0.5, "/cart/force/y", -0.5),
+                ]
+            );
+        }
+    }
 }
@@ -3,4 +3,3 @@ pub use control_system_blocks::*;
 
 #[cfg(feature = "plotter")]
 pub use control_system_plotter::*;
-
@@ -1,4 +1,3 @@
 pub use control_system_lib::*;
-pub use control_system_derive::*;
 
-pub mod blocks;
\ No newline at end of file
+pub mod blocks;